@@ -1,10 +1,18 @@
 use crate::api_handling::api_desc_dir::{
-    OutputFiles, ParameterAndType, RequestFile, RequestFunction, ResponseFile, SpecVersion,
+    OutputFiles, ParameterAndType, RequestFile, RequestFunction, ResponseFile, Service, SpecVersion,
 };
+use crate::api_handling::capabilities_module::marker_type;
+use crate::api_handling::examples::{escape_xml, example_response, example_value};
+use crate::api_handling::merge::Availability;
+use crate::api_handling::naming::{action_item, argument_item, service_item, Names};
+use crate::api_handling::options::{DateTimeMapping, FieldMode, UuidMapping};
+use crate::api_handling::serde_helpers;
 
 ///Struct to deserialize response from "fritz.box/xyzSCPD.xml" into.
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct ApiDesc {
+    // Mirrors the XML, the generator doesn't read it.
+    #[allow(dead_code)]
     #[serde(rename = "specVersion")]
     pub spec_version: SpecVersion,
     #[serde(rename = "actionList")]
@@ -13,14 +21,14 @@ pub struct ApiDesc {
     pub service_state_table: ServiceStateTable,
 }
 ///Struct to deserialize the ActionList part of the response from "fritz.box/xyzSCPD.xml" into.
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct ActionList {
     #[serde(rename = "action")]
     #[serde(default)]
     pub action: Vec<Action>,
 }
 ///Struct to deserialize the Action part of the response from "fritz.box/xyzSCPD.xml" into.
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct Action {
     pub name: String,
     #[serde(rename = "argumentList")]
@@ -28,14 +36,14 @@ pub struct Action {
     pub argument_list: ArgumentList,
 }
 ///Struct to deserialize the ArgumentList part of the response from "fritz.box/xyzSCPD.xml" into.
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct ArgumentList {
     #[serde(rename = "argument")]
     pub argument: Vec<Argument>,
 }
 
 ///Struct to deserialize the Argument part of the response from "fritz.box/xyzSCPD.xml" into.
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct Argument {
     pub name: String,
     pub direction: String,
@@ -44,15 +52,18 @@ pub struct Argument {
 }
 
 ///Struct to deserialize the ServiceStateTable part of the response from "fritz.box/xyzSCPD.xml" into.
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct ServiceStateTable {
     #[serde(rename = "stateVariable")]
     pub state_variable: Vec<StateVariable>,
 }
 
 ///Struct to deserialize the StateVariable part of the response from "fritz.box/xyzSCPD.xml" into.
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct StateVariable {
+    #[serde(rename = "sendEvents")]
+    #[serde(default)]
+    pub send_events: String,
     pub name: String,
     #[serde(rename = "dataType")]
     pub data_type: String,
@@ -65,40 +76,119 @@ pub struct StateVariable {
 }
 
 ///Struct to deserialize the AllowedValueList part of the response from "fritz.box/xyzSCPD.xml" into.
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct AllowedValueList {
     #[serde(rename = "allowedValue")]
     pub allowed_value: Vec<String>,
 }
 
+impl StateVariable {
+    /// Whether the device sends events when the variable changes, see `sendEvents`.
+    pub fn is_evented(&self) -> bool {
+        self.send_events == "yes"
+    }
+}
+
 impl ApiDesc {
-    ///Takes  an `OutputFiles`, `name`, `control_url` and `service_type` and populates the `OutputFiles`
+    ///Takes  an `OutputFiles`, the `Names` of all items, the `Service` this is the SCPD of and the
+    ///`Availability` of merged sets and populates the `OutputFiles`
     pub fn fill_output_files(
         &self,
         output_files: &mut OutputFiles,
-        name: &str,
-        control_url: &str,
-        service_type: &str,
+        names: &Names,
+        service: &Service,
+        availability: &Availability,
     ) {
-        let rusty_name = self.rustify_string(&name);
         let mut response_file = ResponseFile::new();
         let mut request_file = RequestFile::new();
-        response_file.name = rusty_name.clone();
-        request_file.name = rusty_name;
+        response_file.name = names.module(service).to_string();
+        request_file.name = names.module(service).to_string();
+        response_file.folder = names.folder(service).to_vec();
+        request_file.folder = names.folder(service).to_vec();
+        // Path from the files of the service to the helper modules.
+        let root = "super::".repeat(response_file.folder.len() + 1);
+        let file_documentation = format!(
+            "//! Service `{}`, control URL {}, event URL {}.\n{}\n",
+            service.service_type,
+            code_span(&service.control_url),
+            code_span(&service.event_sub_url),
+            if availability.is_merged() {
+                format!(
+                    "//!\n//! Declared by {}.\n",
+                    availability.describe(&service_item(service))
+                )
+            } else if output_files.firmware_version.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    "//!\n//! Generated from the description of FRITZ!OS {}.\n",
+                    output_files.firmware_version
+                )
+            }
+        );
+        response_file.content.push(file_documentation.clone());
+        request_file.documentation = file_documentation;
+        if output_files.eventing {
+            request_file.event = self.event_code(
+                names,
+                service,
+                &format!("{}{}eventing", root, output_files.prefix),
+            );
+        }
+        if availability.is_merged() {
+            request_file.models = availability.models(&service_item(service));
+        }
+        // Path from the request files to the response file of the service, for the tests.
+        let response_module = format!(
+            "{}super::{}::{}{}",
+            root,
+            output_files.tests_responses_module,
+            output_files.prefix,
+            response_file
+                .folder
+                .iter()
+                .chain(std::iter::once(&response_file.name))
+                .cloned()
+                .collect::<Vec<String>>()
+                .join("::")
+        );
         for action in &self.action_list.action {
+            let action_availability = if availability.is_merged() {
+                format!(
+                    "///\n/// Declared by {}.\n",
+                    availability.describe(&action_item(service, action))
+                )
+            } else {
+                String::new()
+            };
             let mut request_function = RequestFunction::new();
             request_function.name = action.name.clone();
-            request_function.name_rusty = self.rustify_string(action.name.as_str());
+            request_function.name_rusty = names.function(service, action).to_string();
             request_function.action_name = action.name.clone();
-            request_function.control_url = control_url.to_string();
-            request_function.service_type = service_type.to_string();
+            request_function.control_url = service.control_url.clone();
+            request_function.instances = service.instances.clone();
+            request_function.service_type = service.service_type.clone();
+            request_function.prefix = output_files.prefix.clone();
+            request_function.root = root.clone();
+            request_function.availability = action_availability.clone();
+            if availability.is_merged() {
+                request_function.models = availability.models(&action_item(service, action));
+            }
+            if !output_files.capabilities_module.is_empty() {
+                request_function.marker =
+                    marker_type(names.response_struct(service, action)).to_string();
+            }
 
+            response_file.content.push(format!(
+                "/// Response of a `{}` request for service `{}`.\n{}",
+                action.name, service.service_type, action_availability
+            ));
             response_file
                 .content
                 .push(String::from("#[derive(Deserialize, Debug)]\n"));
             response_file.content.push(format!(
-                "pub struct {}Response{{\n",
-                action.name.replace("-", "").replace("_", "")
+                "pub struct {}{{\n",
+                names.response_struct(service, action)
             ));
             output_files
                 .annotation_string
@@ -108,46 +198,255 @@ impl ApiDesc {
                 if argument.direction == "out" {
                     response_file
                         .content
-                        .push(format!("\t#[serde(rename = \"{}\")]\n", argument.name));
-                    let variable_type =
-                        self.search_state_variable_type(argument.related_state_variable.as_str());
-                    let variable_name: String = self.rustify_string(&argument.name);
+                        .push(format!("\t/// {}\n", self.document_argument(argument)));
+                    // Fields not every model returns are optional, whatever the field mode.
+                    let item = argument_item(service, action, argument);
+                    let field_mode =
+                        if availability.declared_with(&item, &action_item(service, action)) {
+                            output_files.field_mode
+                        } else {
+                            response_file.content.push(format!(
+                                "\t///\n\t/// Only returned by {}.\n",
+                                availability.describe(&item)
+                            ));
+                            FieldMode::Optional
+                        };
+                    let variable_type = self.search_state_variable_type(
+                        argument.related_state_variable.as_str(),
+                        output_files.date_time_mapping,
+                        output_files.uuid_mapping,
+                    );
+                    let data_type = self
+                        .search_state_variable_data_type(argument.related_state_variable.as_str());
+                    let (helpers, helper) =
+                        serde_helpers::deserializer(data_type, &variable_type, field_mode);
+                    for helper in helpers {
+                        output_files.add_response_helper(helper);
+                    }
+                    let (default, variable_type) = match field_mode {
+                        FieldMode::Strict => ("", variable_type),
+                        FieldMode::Default => (", default", variable_type),
+                        FieldMode::Optional | FieldMode::EmptyAsNone => {
+                            (", default", format!("Option<{}>", variable_type))
+                        }
+                    };
+                    let serde_attribute = format!(
+                        "{}, deserialize_with = \"{}{}serde_helpers::{}\"",
+                        default, root, output_files.prefix, helper
+                    );
+                    response_file.content.push(format!(
+                        "\t#[serde(rename = \"{}\"{})]\n",
+                        argument.name, serde_attribute
+                    ));
+                    let variable_name = names.argument(service, action, argument);
                     response_file
                         .content
                         .push(format!("\tpub {}: {},\n", variable_name, variable_type));
                 } else if argument.direction == "in" {
                     let mut param = ParameterAndType::new();
                     param.parameter_name = argument.name.clone();
-                    param.parameter_name_rusty = self.rustify_string(&argument.name);
-                    param.type_name =
-                        self.search_state_variable_type(argument.related_state_variable.as_str());
+                    param.parameter_name_rusty =
+                        names.argument(service, action, argument).to_string();
+                    param.type_name = self.search_state_variable_type(
+                        argument.related_state_variable.as_str(),
+                        output_files.date_time_mapping,
+                        output_files.uuid_mapping,
+                    );
+                    param.data_type = self
+                        .search_state_variable_data_type(argument.related_state_variable.as_str())
+                        .to_string();
+                    match param.data_type.as_str() {
+                        "bin.base64" => {
+                            output_files.add_request_helper(serde_helpers::ENCODE_BASE64)
+                        }
+                        "bin.hex" => output_files.add_request_helper(serde_helpers::ENCODE_HEX),
+                        _ if param.type_name == "String" => {
+                            output_files.add_request_helper(serde_helpers::ESCAPE_XML)
+                        }
+                        _ => {}
+                    }
+                    param.documentation = self.document_argument(argument);
                     request_function.parameter.push(param);
                 }
             }
             response_file.content.push("}\n\n".to_string());
+            if !output_files.tests_responses_module.is_empty() {
+                request_file.tests.push(self.test_code(
+                    names,
+                    service,
+                    action,
+                    &request_function,
+                    &response_module,
+                    &output_files.tests_responses_module,
+                ));
+            }
             request_file.request_functions.push(request_function);
         }
         output_files.request_files.push(request_file);
         output_files.response_files.push(response_file);
     }
 
+    /// Returns the `EVENT_URL` constant and the `Event` struct with the evented state variables of
+    /// the service, empty if it has none. Variables of types without `EventValue` implementation
+    /// in the eventing module `eventing` are kept as `String`.
+    fn event_code(&self, names: &Names, service: &Service, eventing: &str) -> String {
+        let variables: Vec<&StateVariable> = self
+            .service_state_table
+            .state_variable
+            .iter()
+            .filter(|variable| variable.is_evented())
+            .collect();
+        if variables.is_empty() {
+            return String::new();
+        }
+        let mut fields = String::new();
+        let mut assignments = vec![];
+        for variable in variables {
+            let variable_type = match variable.data_type.as_str() {
+                "boolean" => "bool",
+                "ui1" | "ui2" | "ui4" => "u32",
+                "i1" | "i2" | "i4" => "i32",
+                _ => "String",
+            };
+            let name = names.variable(service, variable);
+            fields.push_str(&format!(
+                "\t/// `{}` of type `{}`.\n\tpub {}: Option<{}>,\n",
+                variable.name, variable.data_type, name, variable_type
+            ));
+            assignments.push(format!(
+                "if name == \"{}\" {{\n\t\t\t\tevent.{} = Some({}::EventValue::parse_event_value(value)\
+                 .map_err(|e| format!(\"{{}}: {{}}\", name, e))?);\n\t\t\t}}",
+                variable.name, name, eventing
+            ));
+        }
+        format!(
+            "/// Event URL of the service, see `{eventing}::subscribe_request`.\n\
+             pub const EVENT_URL: &str = {url:?};\n\n\
+             /// Evented state variables of the service, sent in notifications to subscribers of `EVENT_URL`.\n\
+             /// A field is `None` unless the notification has the variable.\n\
+             #[derive(Debug, Default, Clone, PartialEq)]\n\
+             pub struct Event {{\n{fields}}}\n\n\
+             impl Event {{\n\
+             \t/// Takes the variables of the service from the properties of a notification, see\n\
+             \t/// `{eventing}::Notification`. Other variables are ignored.\n\
+             \tpub fn from_properties(properties: &[(String, String)]) -> Result<Event, String> {{\n\
+             \t\tlet mut event = Event::default();\n\
+             \t\tfor (name, value) in properties {{\n\
+             \t\t\t{assignments}\n\
+             \t\t}}\n\
+             \t\tOk(event)\n\
+             \t}}\n\
+             }}\n\n",
+            eventing = eventing,
+            url = service.event_sub_url,
+            fields = fields,
+            assignments = assignments.join(" else "),
+        )
+    }
+
+    /// Returns the test of `action` for the `tests` module of the request file. It calls the request
+    /// function of the action with example values, checks that the body is well-formed XML with the
+    /// action in the namespace of the service and the arguments in the order of the SCPD, and
+    /// deserializes an example response into the response struct in `response_module`.
+    /// `responses` is the module of the response folder.
+    fn test_code(
+        &self,
+        names: &Names,
+        service: &Service,
+        action: &Action,
+        request_function: &RequestFunction,
+        response_module: &str,
+        responses: &str,
+    ) -> String {
+        let mut arguments = String::new();
+        let mut elements = String::new();
+        let in_arguments = action
+            .argument_list
+            .argument
+            .iter()
+            .filter(|argument| argument.direction == "in");
+        for (argument, parameter) in in_arguments.zip(&request_function.parameter) {
+            let value = example_value(self.state_variable(&argument.related_state_variable));
+            let (expression, text) = example_argument(&parameter.type_name, &value);
+            arguments.push_str(&format!("{}, ", expression));
+            elements.push_str(&format!("<{0}>{1}</{0}>", argument.name, text));
+        }
+        // The request functions put the service instance `id` in place of the version.
+        let namespace = format!("{}:1", service_item(service));
+        let element = format!(
+            "<u:{0} xmlns:u=\"{1}\">{2}</u:{0}>",
+            action.name, namespace, elements
+        );
+        let response = example_response(&service.service_type, self, action, &[]);
+        format!(
+            "\t#[test]\n\
+             \tfn {name}() {{\n\
+             \t\tlet (_, _, body) = super::generate_{name}_request({arguments}None);\n\
+             \t\tfor event in serde_xml_rs::EventReader::from_str(&body) {{\n\
+             \t\t\tevent.unwrap();\n\
+             \t\t}}\n\
+             \t\tassert!(body.contains({element:?}), \"{{}}\", body);\n\
+             \t\tlet _response: super::{root}super::{responses}::{prefix}multi_use::Envelope<super::{module}::{response_struct}> =\n\
+             \t\t\tserde_xml_rs::from_str({response:?}).unwrap();\n\
+             \t}}\n",
+            name = request_function.name_rusty,
+            arguments = arguments,
+            element = element,
+            root = request_function.root,
+            responses = responses,
+            prefix = request_function.prefix,
+            module = response_module,
+            response_struct = names.response_struct(service, action),
+            response = response,
+        )
+    }
+
+    /// Describes `argument` for the rustdoc of generated items: the original name, the related
+    /// state variable with its data type, default value and allowed values.
+    fn document_argument(&self, argument: &Argument) -> String {
+        let mut result = format!(
+            "{}, related state variable {}",
+            code_span(&argument.name),
+            code_span(&argument.related_state_variable)
+        );
+        if let Some(variable) = self.state_variable(&argument.related_state_variable) {
+            result.push_str(format!(" of type {}.", code_span(&variable.data_type)).as_str());
+            if !variable.default_value.is_empty() {
+                result.push_str(
+                    format!(" Default value: {}.", code_span(&variable.default_value)).as_str(),
+                );
+            }
+            if !variable.allowed_value_list.allowed_value.is_empty() {
+                let allowed_values: Vec<String> = variable
+                    .allowed_value_list
+                    .allowed_value
+                    .iter()
+                    .map(|value| code_span(value))
+                    .collect();
+                result
+                    .push_str(format!(" Allowed values: {}.", allowed_values.join(", ")).as_str());
+            }
+        } else {
+            result.push('.');
+        }
+        result
+    }
+
     /// Searches for the requested variable and returns the corresponding type.
     /// If you encounter a panic here, please open a ticket with the output of `_ => print!("{}", variable.data_type.as_str()),`
-    fn search_state_variable_type(&self, state_variable_name: &str) -> String {
+    /// `dateTime` and `uuid` are mapped according to `date_time_mapping` and `uuid_mapping`,
+    /// `bin.base64` and `bin.hex` both to `Vec<u8>`.
+    fn search_state_variable_type(
+        &self,
+        state_variable_name: &str,
+        date_time_mapping: DateTimeMapping,
+        uuid_mapping: UuidMapping,
+    ) -> String {
         for variable in &self.service_state_table.state_variable {
             if state_variable_name.eq(&variable.name) {
-                match variable.data_type.as_str() {
-                    "boolean" => return String::from("bool"),
-                    "ui1" => return String::from("u32"),
-                    "ui2" => return String::from("u32"),
-                    "ui4" => return String::from("u32"),
-                    "i1" => return String::from("i32"),
-                    "i2" => return String::from("i32"),
-                    "i4" => return String::from("i32"),
-                    "string" => return String::from("String"),
-                    "uuid" => return String::from("String"),
-                    "dateTime" => return String::from("String"),
-                    _ => print!("{}", variable.data_type.as_str()),
+                match rust_type(&variable.data_type, date_time_mapping, uuid_mapping) {
+                    Some(rust_type) => return String::from(rust_type),
+                    None => print!("{}", variable.data_type.as_str()),
                 };
             }
         }
@@ -155,86 +454,270 @@ impl ApiDesc {
         panic!("variable Type not implemented, please open a ticket")
     }
 
-    /// Modifies the supplied `input` to generate proper snake case.
-    fn rustify_string(&self, input: &str) -> String {
-        input
-            .replace("NewX_AVM-DE_", "newXAvmDe")
-            .replace("NewX_AVM_DE_", "newXAvmDe")
-            .replace("X_AVM-DE_", "XAvmDe")
-            .replace("X_", "x")
-            .replace("_", "")
-            .replace("NATRSIP", "NatRsip")
-            .replace("NAT", "Nat")
-            .replace("RSIP", "Rsip")
-            .replace("FCS", "Fcs")
-            .replace("ATM", "Atm")
-            .replace("DAV", "Dav")
-            .replace("PPP", "Ppp")
-            .replace("WAN", "Wan")
-            .replace("MAC", "Mac")
-            .replace("AIN", "Ain")
-            .replace("DDNS", "Ddns")
-            .replace("DNS", "Dns")
-            .replace("IPTVo", "IptvO")
-            .replace("IPTV", "Iptv")
-            .replace("US", "Us")
-            .replace("VoIP", "Voip")
-            .replace("AVM", "Avm")
-            .replace("URL", "Url")
-            .replace("ATUC", "Atuc")
-            .replace("CHECK", "Check")
-            .replace("DSL", "Dsl")
-            .replace("DS", "Ds")
-            .replace("SNRG", "Snrg_")
-            .replace("SNRMT", "Snrmt_")
-            .replace("SNR", "Snr_")
-            .replace("LATN", "Latn_")
-            .replace("HEC", "Hec")
-            .replace("TAM", "Tam")
-            .replace("OKZ", "Okz")
-            .replace("LKZ", "Lkz")
-            .replace("OKZ", "Okz")
-            .replace("STUN", "Stun")
-            .replace("UPnP", "Upnp")
-            .replace("FTP", "Ftp")
-            .replace("SSL", "Ssl")
-            .replace("SMB", "Smb")
-            .replace("CGI", "Cgi")
-            .replace("NTP", "Ntp")
-            .replace("TR069", "Tr069")
-            .replace("BSSID", "Bssid")
-            .replace("SSID", "Ssid")
-            .replace("SID", "Sid")
-            .replace("UUID", "Uuid")
-            .replace("OUI", "Oui")
-            .replace("ATUR", "Atur")
-            .replace("FEC", "Fec")
-            .replace("CRC", "Crc")
-            .replace("PSK", "Psk")
-            .replace("WEP", "Wep")
-            .replace("WPA", "Wpa")
-            .replace("WLAN", "Wlan")
-            .replace("LAN", "Lan")
-            .replace("AP", "Ap")
-            .replace("WPS", "Wps")
-            .replace("RX", "Rx")
-            .replace("WOL", "Wol")
-            .replace("DHCP", "Dhcp")
-            .replace("ID", "Id")
-            .replace("IP", "Ip")
-            .chars()
-            .enumerate()
-            .map(|x| {
-                if x.1.is_uppercase() {
-                    if x.0 == 0 {
-                        x.1.to_lowercase().to_string()
-                    } else {
-                        format!("_{}", x.1.to_lowercase())
-                    }
-                } else {
-                    x.1.to_string()
-                }
-            })
-            .collect()
+    /// Returns the UPnP data type of the requested variable, e.g. `bin.base64`.
+    pub fn search_state_variable_data_type(&self, state_variable_name: &str) -> &str {
+        self.state_variable(state_variable_name)
+            .map(|variable| variable.data_type.as_str())
+            .unwrap_or_default()
+    }
+
+    /// Returns the state variable called `name`.
+    pub fn state_variable(&self, name: &str) -> Option<&StateVariable> {
+        self.service_state_table
+            .state_variable
+            .iter()
+            .find(|variable| variable.name == name)
+    }
+}
+
+/// Returns `text` from a description as inline code for a `///` line: whitespace runs, line
+/// breaks included, become one space and the code span is delimited by more backticks than `text`
+/// contains in a row, so backticks in `text` don't end it.
+pub fn code_span(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let longest_run = text
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default();
+    let delimiter = "`".repeat(longest_run + 1);
+    if longest_run == 0 {
+        format!("{}{}{}", delimiter, text, delimiter)
+    } else {
+        format!("{} {} {}", delimiter, text, delimiter)
+    }
+}
+
+/// Returns the expression passing the example `value` as parameter of the Rust type `type_name`
+/// to a request function, and the escaped text of the value in the request body. Dates and UUIDs of the
+/// `chrono`, `time` and `uuid` types are always the start of 1970 and the nil UUID.
+fn example_argument(type_name: &str, value: &str) -> (String, String) {
+    match type_name {
+        "bool" => {
+            let value = matches!(value, "1" | "true" | "yes");
+            (
+                format!("&{}", value),
+                if value { "1" } else { "0" }.to_string(),
+            )
+        }
+        "u32" | "i32" => {
+            let value: i64 = value.trim().parse().unwrap_or_default();
+            (format!("&{}", value), value.to_string())
+        }
+        "Vec<u8>" => ("&[]".to_string(), String::new()),
+        "chrono::NaiveDateTime" => (
+            "&chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap()"
+                .to_string(),
+            "1970-01-01T00:00:00".to_string(),
+        ),
+        "time::PrimitiveDateTime" => (
+            "&time::Date::from_calendar_date(1970, time::Month::January, 1).unwrap().midnight()"
+                .to_string(),
+            "1970-01-01T00:00:00".to_string(),
+        ),
+        "uuid::Uuid" => (
+            "&uuid::Uuid::nil()".to_string(),
+            "00000000-0000-0000-0000-000000000000".to_string(),
+        ),
+        _ => (format!("{:?}", value), escape_xml(value)),
+    }
+}
+
+/// Returns the Rust type of the UPnP `data_type`, `None` if it isn't supported.
+pub fn rust_type(
+    data_type: &str,
+    date_time_mapping: DateTimeMapping,
+    uuid_mapping: UuidMapping,
+) -> Option<&'static str> {
+    match data_type {
+        "boolean" => Some("bool"),
+        "ui1" | "ui2" | "ui4" => Some("u32"),
+        "i1" | "i2" | "i4" => Some("i32"),
+        "string" => Some("String"),
+        "uuid" => Some(uuid_mapping.rust_type()),
+        "dateTime" => Some(date_time_mapping.rust_type()),
+        "bin.base64" | "bin.hex" => Some("Vec<u8>"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_handling::description_set::fixtures::{action, scpd, set, variable};
+    use crate::api_handling::naming::{assign_names, NameOverrides};
+
+    #[test]
+    fn argument_documentation() {
+        let xml = scpd(
+            &action("SetMode", &[("NewMode", "in", "Mode")]),
+            &variable("Mode", "string", &["a`b", "line\n   break", "plain"]),
+        );
+        let mut description = set(&[("Hosts", &xml)]).services.remove(0).scpd;
+        description.service_state_table.state_variable[0].default_value =
+            "  first\n\tsecond ".to_string();
+        let argument = &description.action_list.action[0].argument_list.argument[0];
+        assert_eq!(
+            description.document_argument(argument),
+            "`NewMode`, related state variable `Mode` of type `string`. \
+             Default value: `first second`. Allowed values: `` a`b ``, `line break`, `plain`."
+        );
+        assert_eq!(code_span("``x"), "``` ``x ```");
+        assert_eq!(code_span(""), "``");
+    }
+
+    #[test]
+    fn round_trip_tests() {
+        let xml = scpd(
+            &action(
+                "SetConfig",
+                &[
+                    ("NewName", "in", "Name"),
+                    ("NewEnable", "in", "Enable"),
+                    ("NewPort", "in", "Port"),
+                    ("NewKey", "in", "Key"),
+                    ("NewTime", "in", "Time"),
+                    ("NewResult", "out", "Name"),
+                ],
+            ),
+            &[
+                variable("Name", "string", &["A&amp;B"]),
+                variable("Enable", "boolean", &[]),
+                variable("Port", "ui2", &["443"]),
+                variable("Key", "bin.base64", &[]),
+                variable("Time", "dateTime", &[]),
+            ]
+            .concat(),
+        );
+        let set = set(&[("Hosts", &xml)]);
+        let names = assign_names(&set.services, &NameOverrides::new(), &[]).unwrap();
+        let mut output_files = OutputFiles::new();
+        output_files.prefix = "tr064_".to_string();
+        output_files.date_time_mapping = DateTimeMapping::Chrono;
+        output_files.tests_responses_module = "tr064_responses".to_string();
+        let description = &set.services[0];
+        description.scpd.fill_output_files(
+            &mut output_files,
+            &names,
+            &description.service,
+            &Availability::new(),
+        );
+        let request_file = &output_files.request_files[0];
+        assert!(request_file.request_functions[0]
+            .create_function()
+            .contains("<NewName>{}</NewName>"));
+        assert!(request_file.request_functions[0]
+            .create_function()
+            .contains(",super::tr064_serde_helpers::escape_xml(new_name),"));
+        assert_eq!(
+            request_file.tests,
+            vec![concat!(
+                "\t#[test]\n",
+                "\tfn set_config() {\n",
+                "\t\tlet (_, _, body) = super::generate_set_config_request(\"A&B\", &false, &443, &[], &chrono::NaiveDate::from_ymd_opt(1970, 1, 1).unwrap().and_hms_opt(0, 0, 0).unwrap(), None);\n",
+                "\t\tfor event in serde_xml_rs::EventReader::from_str(&body) {\n",
+                "\t\t\tevent.unwrap();\n",
+                "\t\t}\n",
+                "\t\tassert!(body.contains(\"<u:SetConfig xmlns:u=\\\"urn:dslforum-org:service:Hosts:1\\\"><NewName>A&amp;B</NewName><NewEnable>0</NewEnable><NewPort>443</NewPort><NewKey></NewKey><NewTime>1970-01-01T00:00:00</NewTime></u:SetConfig>\"), \"{}\", body);\n",
+                "\t\tlet _response: super::super::super::tr064_responses::tr064_multi_use::Envelope<super::super::super::tr064_responses::tr064_hosts::SetConfigResponse> =\n",
+                "\t\t\tserde_xml_rs::from_str(\"<?xml version=\\\"1.0\\\"?>\\n<s:Envelope xmlns:s=\\\"http://schemas.xmlsoap.org/soap/envelope/\\\" s:encodingStyle=\\\"http://schemas.xmlsoap.org/soap/encoding/\\\">\\n<s:Body>\\n<u:SetConfigResponse xmlns:u=\\\"urn:dslforum-org:service:Hosts:1\\\"><NewResult>A&amp;B</NewResult></u:SetConfigResponse>\\n</s:Body>\\n</s:Envelope>\").unwrap();\n",
+                "\t}\n",
+            )]
+        );
+    }
+
+    #[test]
+    fn events() {
+        let evented = scpd(
+            &action("GetInfo", &[("NewStatus", "out", "Status")]),
+            &[
+                "<stateVariable sendEvents=\"yes\"><name>PhysicalLinkStatus</name>\
+                 <dataType>string</dataType></stateVariable>",
+                "<stateVariable sendEvents=\"yes\"><name>X_AVM-DE_Enabled</name>\
+                 <dataType>boolean</dataType></stateVariable>",
+                "<stateVariable sendEvents=\"no\"><name>TotalBytesSent</name>\
+                 <dataType>ui4</dataType></stateVariable>",
+                &variable("Status", "string", &[]),
+            ]
+            .concat(),
+        );
+        let quiet = scpd(
+            &action("GetInfo", &[("NewStatus", "out", "Status")]),
+            &variable("Status", "string", &[]),
+        );
+        let set = set(&[("WANCommonInterfaceConfig", &evented), ("Hosts", &quiet)]);
+        let variables = &set.services[0].scpd.service_state_table.state_variable;
+        let evented: Vec<bool> = variables.iter().map(StateVariable::is_evented).collect();
+        assert_eq!(evented, [true, true, false, false]);
+
+        let names = assign_names(&set.services, &NameOverrides::new(), &[]).unwrap();
+        let service = &set.services[0].service;
+        assert_eq!(names.variable(service, &variables[1]), "x_avm_de_enabled");
+        let code = set.services[0]
+            .scpd
+            .event_code(&names, service, "super::eventing");
+        assert_eq!(
+            code,
+            concat!(
+                "/// Event URL of the service, see `super::eventing::subscribe_request`.\n",
+                "pub const EVENT_URL: &str = \"/upnp/control/WANCommonInterfaceConfig\";\n",
+                "\n",
+                "/// Evented state variables of the service, sent in notifications to subscribers of `EVENT_URL`.\n",
+                "/// A field is `None` unless the notification has the variable.\n",
+                "#[derive(Debug, Default, Clone, PartialEq)]\n",
+                "pub struct Event {\n",
+                "\t/// `PhysicalLinkStatus` of type `string`.\n",
+                "\tpub physical_link_status: Option<String>,\n",
+                "\t/// `X_AVM-DE_Enabled` of type `boolean`.\n",
+                "\tpub x_avm_de_enabled: Option<bool>,\n",
+                "}\n",
+                "\n",
+                "impl Event {\n",
+                "\t/// Takes the variables of the service from the properties of a notification, see\n",
+                "\t/// `super::eventing::Notification`. Other variables are ignored.\n",
+                "\tpub fn from_properties(properties: &[(String, String)]) -> Result<Event, String> {\n",
+                "\t\tlet mut event = Event::default();\n",
+                "\t\tfor (name, value) in properties {\n",
+                "\t\t\tif name == \"PhysicalLinkStatus\" {\n",
+                "\t\t\t\tevent.physical_link_status = Some(super::eventing::EventValue::parse_event_value(value).map_err(|e| format!(\"{}: {}\", name, e))?);\n",
+                "\t\t\t} else if name == \"X_AVM-DE_Enabled\" {\n",
+                "\t\t\t\tevent.x_avm_de_enabled = Some(super::eventing::EventValue::parse_event_value(value).map_err(|e| format!(\"{}: {}\", name, e))?);\n",
+                "\t\t\t}\n",
+                "\t\t}\n",
+                "\t\tOk(event)\n",
+                "\t}\n",
+                "}\n",
+                "\n",
+            )
+        );
+        assert_eq!(
+            set.services[1]
+                .scpd
+                .event_code(&names, &set.services[1].service, "super::eventing"),
+            ""
+        );
+        let mut quoted = service.clone();
+        quoted.event_sub_url = "/upnp/\"event\"\\1".to_string();
+        assert!(set.services[0]
+            .scpd
+            .event_code(&names, &quoted, "super::eventing")
+            .contains("pub const EVENT_URL: &str = \"/upnp/\\\"event\\\"\\\\1\";\n"));
+
+        let mut output_files = OutputFiles::new();
+        output_files.eventing = true;
+        for description in &set.services {
+            description.scpd.fill_output_files(
+                &mut output_files,
+                &names,
+                &description.service,
+                &Availability::new(),
+            );
+        }
+        let events: Vec<&str> = output_files
+            .request_files
+            .iter()
+            .map(|request_file| request_file.event.as_str())
+            .collect();
+        assert_eq!(events, [code.as_str(), ""]);
     }
 }
@@ -1,16 +1,40 @@
-use crate::api_handling::api_desc::ApiDesc;
+use crate::api_handling::api_desc::{code_span, ApiDesc};
+use crate::api_handling::cache::DescriptionCache;
+use crate::api_handling::capabilities_module::capabilities_module;
+use crate::api_handling::device_metadata_module::{
+    device_metadata_module, icon_files, string_constants,
+};
+use crate::api_handling::discovery_module::discovery_module;
+use crate::api_handling::fetch::Fetcher;
+use crate::api_handling::filter::{check_read_only, Filter};
+use crate::api_handling::identifier::module_name;
+use crate::api_handling::merge::Availability;
+use crate::api_handling::naming::{assign_names, service_item};
+use crate::api_handling::options::{
+    DateTimeMapping, FieldMode, GeneratorOptions, Profile, UuidMapping,
+};
+use crate::api_handling::serde_helpers;
+use crate::api_handling::url::{origin, resolve};
 use std::fs;
 use std::fs::File;
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 
 ///Struct to deserialize the response from "fritz.box/tr64desc.xml" into.
 #[derive(Deserialize, Debug, Default)]
 pub struct ApiDescDir {
+    // Mirrors the XML, the generator doesn't read it.
+    #[allow(dead_code)]
     #[serde(rename = "specVersion")]
     pub spec_version: SpecVersion,
     #[serde(rename = "systemVersion")]
     #[serde(default)]
     pub system_version: SystemVersion,
+    #[serde(rename = "URLBase")]
+    #[serde(default)]
+    pub url_base: String,
     pub device: Device,
 }
 ///Struct to deserialize the device part of the response from "fritz.box/tr64desc.xml" into.
@@ -34,6 +58,7 @@ pub struct Device {
     pub model_url: String,
     #[serde(rename = "UDN")]
     pub udn: String,
+    #[allow(dead_code)]
     #[serde(rename = "UPC")]
     #[serde(default)]
     pub upc: String,
@@ -84,7 +109,7 @@ pub struct ServiceList {
     pub service: Vec<Service>,
 }
 ///Struct to deserialize the service part of the response from "fritz.box/tr64desc.xml" into.
-#[derive(Deserialize, Debug, Default)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct Service {
     #[serde(rename = "serviceType")]
     pub service_type: String,
@@ -96,8 +121,19 @@ pub struct Service {
     pub event_sub_url: String,
     #[serde(rename = "SCPDURL")]
     pub scpd_url: String,
+    /// Indices into the device lists from the root device to the device declaring the service,
+    /// empty for services of the root device. Set by `ApiDescDir::services`.
+    #[serde(skip)]
+    pub device_path: Vec<usize>,
+    /// Versions and control URLs of the instances of the service, e.g. `("2",
+    /// "/upnp/control/wlanconfig2")`, the first one is the service itself. Set by
+    /// `ApiDescDir::services`.
+    #[serde(skip)]
+    pub instances: Vec<(String, String)>,
 }
 ///Struct to deserialize the system version part of the response from "fritz.box/tr64desc.xml" into.
+/// Only `buildnumber` and `display` are used, the other fields mirror the XML.
+#[allow(dead_code)]
 #[derive(Deserialize, Debug, Default)]
 pub struct SystemVersion {
     #[serde(rename = "HW")]
@@ -114,16 +150,152 @@ pub struct SystemVersion {
     pub display: String,
 }
 ///Struct to deserialize the spec version part of the response from "fritz.box/tr64desc.xml" into.
-#[derive(Deserialize, Debug, Default)]
+#[allow(dead_code)]
+#[derive(Deserialize, Debug, Default, Clone)]
 pub struct SpecVersion {
     pub major: u32,
     pub minor: u32,
 }
 
+impl Device {
+    /// Returns the service types of this device and all contained devices `filter` selects, without duplicates.
+    pub fn service_types(&self, filter: &Filter) -> Vec<String> {
+        let mut service_types: Vec<String> = vec![];
+        for service in &self.service_list.service {
+            if filter.selects_service(service) && !service_types.contains(&service.service_type) {
+                service_types.push(service.service_type.clone());
+            }
+        }
+        for device in &self.device_list.device {
+            for service_type in device.service_types(filter) {
+                if !service_types.contains(&service_type) {
+                    service_types.push(service_type);
+                }
+            }
+        }
+        service_types
+    }
+}
+
+/// A module of the generated files mirroring a device, see `GeneratorOptions::nest_modules`.
+#[derive(Debug)]
+pub struct DeviceModule {
+    /// Indices into the device lists from the root device to the device, see `Service::device_path`.
+    pub device_path: Vec<usize>,
+    /// Module names from the output folder to the module of the device, without prefix,
+    /// e.g. `internet_gateway_device` and `lan_device`.
+    pub module_path: Vec<String>,
+    /// Module names of the contained devices.
+    pub children: Vec<String>,
+    /// Documentation and constants with the metadata of the device, the start of its request `mod.rs`.
+    pub metadata: String,
+}
+
+impl Device {
+    /// Returns the modules of this device and all contained devices, named after their device
+    /// type. Devices of the same type in one device list are numbered, e.g. `wan_device_2`.
+    pub fn modules(&self) -> Vec<DeviceModule> {
+        let mut modules = vec![];
+        self.collect_modules(
+            vec![],
+            vec![device_module_name(&self.device_type)],
+            &mut modules,
+        );
+        modules
+    }
+
+    fn collect_modules(
+        &self,
+        device_path: Vec<usize>,
+        module_path: Vec<String>,
+        modules: &mut Vec<DeviceModule>,
+    ) {
+        let mut children: Vec<String> = vec![];
+        for device in &self.device_list.device {
+            let base = device_module_name(&device.device_type);
+            let mut name = base.clone();
+            let mut number = 1;
+            while children.contains(&name) {
+                number += 1;
+                name = format!("{}_{}", base, number);
+            }
+            children.push(name);
+        }
+        modules.push(DeviceModule {
+            device_path: device_path.clone(),
+            module_path: module_path.clone(),
+            children: children.clone(),
+            metadata: self.metadata(),
+        });
+        for (index, (device, name)) in self.device_list.device.iter().zip(children).enumerate() {
+            let mut child_device_path = device_path.clone();
+            child_device_path.push(index);
+            let mut child_module_path = module_path.clone();
+            child_module_path.push(name);
+            device.collect_modules(child_device_path, child_module_path, modules);
+        }
+    }
+
+    /// Returns the documentation of the module of the device and constants with its type,
+    /// `UDN` and model.
+    fn metadata(&self) -> String {
+        let mut result = format!("//! Device `{}`.\n\n", self.device_type);
+        result.push_str(&string_constants(&[
+            (
+                "`deviceType` of the device.",
+                "DEVICE_TYPE",
+                self.device_type.as_str(),
+            ),
+            (
+                "`UDN` of the device the bindings were generated from, it differs between boxes.",
+                "UDN",
+                self.udn.as_str(),
+            ),
+            (
+                "`modelName` of the device.",
+                "MODEL_NAME",
+                self.model_name.as_str(),
+            ),
+            (
+                "`modelNumber` of the device.",
+                "MODEL_NUMBER",
+                self.model_number.as_str(),
+            ),
+            (
+                "`modelDescription` of the device.",
+                "MODEL_DESCRIPTION",
+                self.model_description.as_str(),
+            ),
+        ]));
+        result.push('\n');
+        result
+    }
+}
+
+/// Returns the module name of a device of the type `device_type`, e.g. `lan_device` for
+/// `urn:dslforum-org:device:LANDevice:1`.
+fn device_module_name(device_type: &str) -> String {
+    match module_name(device_type.split(':').nth(3).unwrap_or_default()) {
+        name if name.is_empty() => "device".to_string(),
+        name => name,
+    }
+}
+
+/// A service together with its parsed SCPD.
+#[derive(Debug, Clone)]
+pub struct ServiceDescription {
+    pub service: Service,
+    pub scpd: ApiDesc,
+    /// The SCPD as fetched.
+    pub scpd_xml: String,
+}
+
 /// Struct to build response files. File parts get pushed into `content` and assembled later.
 #[derive(Debug)]
 pub struct ResponseFile {
     pub name: String,
+    /// Module path of the device, see `Names::folder`.
+    pub folder: Vec<String>,
     pub content: Vec<String>,
 }
 
@@ -131,6 +303,7 @@ impl ResponseFile {
     pub fn new() -> Self {
         ResponseFile {
             name: "".to_string(),
+            folder: vec![],
             content: vec![],
         }
     }
@@ -142,6 +315,8 @@ pub struct ParameterAndType {
     pub parameter_name: String,
     pub parameter_name_rusty: String,
     pub type_name: String,
+    pub data_type: String,
+    pub documentation: String,
 }
 
 impl ParameterAndType {
@@ -150,6 +325,34 @@ impl ParameterAndType {
             parameter_name: "".to_string(),
             parameter_name_rusty: "".to_string(),
             type_name: "".to_string(),
+            data_type: "".to_string(),
+            documentation: "".to_string(),
+        }
+    }
+}
+
+impl ParameterAndType {
+    /// Returns the expression formatting the parameter for the request body in UPnP encoding.
+    /// Booleans are sent as `1` and `0`, date times as `YYYY-MM-DDThh:mm:ss`. Strings are escaped
+    /// and binary data is encoded with the helpers in `<prefix>serde_helpers.rs` of the request
+    /// folder, `helpers` is the path of the helper modules including the prefix, e.g. `super::tr064_`.
+    pub fn value_for_body(&self, helpers: &str) -> String {
+        let name = &self.parameter_name_rusty;
+        match self.data_type.as_str() {
+            "bin.base64" => return format!("{}serde_helpers::encode_base64({})", helpers, name),
+            "bin.hex" => return format!("{}serde_helpers::encode_hex({})", helpers, name),
+            _ => {}
+        }
+        match self.type_name.as_str() {
+            "bool" => format!("if *{} {{ \"1\" }} else {{ \"0\" }}", name),
+            "String" => format!("{}serde_helpers::escape_xml({})", helpers, name),
+            "chrono::NaiveDateTime" => format!("{}.format(\"%Y-%m-%dT%H:%M:%S\")", name),
+            "time::PrimitiveDateTime" => format!(
+                "format!(\"{{:04}}-{{:02}}-{{:02}}T{{:02}}:{{:02}}:{{:02}}\", {0}.year(), \
+                 u8::from({0}.month()), {0}.day(), {0}.hour(), {0}.minute(), {0}.second())",
+                name
+            ),
+            _ => name.to_string(),
         }
     }
 }
@@ -158,18 +361,49 @@ impl ParameterAndType {
 #[derive(Debug)]
 pub struct RequestFile {
     pub name: String,
+    /// Module path of the device, see `Names::folder`.
+    pub folder: Vec<String>,
+    pub documentation: String,
+    /// Models and firmware versions declaring the service, only set for merged description sets.
+    pub models: Vec<(String, String)>,
+    /// `EVENT_URL` and `Event` struct if eventing is generated and the service has evented variables.
+    pub event: String,
     pub request_functions: Vec<RequestFunction>,
+    /// Test functions of the `tests` module, only set if tests are generated.
+    pub tests: Vec<String>,
 }
 
 impl RequestFile {
     pub fn new() -> Self {
         RequestFile {
             name: "".to_string(),
+            folder: vec![],
+            documentation: "".to_string(),
+            models: vec![],
+            event: "".to_string(),
             request_functions: vec![],
+            tests: vec![],
         }
     }
 }
 
+/// Returns the definition of the constant `name` listing `models`, empty if there are no `models`.
+fn models_constant(name: &str, documentation: &str, models: &[(String, String)]) -> String {
+    if models.is_empty() {
+        return String::new();
+    }
+    let models: Vec<String> = models
+        .iter()
+        .map(|(model, version)| format!("({:?}, {:?})", model, version))
+        .collect();
+    format!(
+        "/// {}\npub const {}: &[(&str, &str)] = &[{}];\n\n",
+        documentation,
+        name,
+        models.join(", ")
+    )
+}
+
 /// Represents a request function, `name` is taken directly from the API, `name_rusty` is the same name in proper snake case.
 /// `service_type`, `action_name` and `control_type` are directly taken from the API.
 #[derive(Debug)]
@@ -180,6 +414,18 @@ pub struct RequestFunction {
     pub service_type: String,
     pub action_name: String,
     pub control_url: String,
+    /// Versions and control URLs of the instances of the service, see `Service::instances`.
+    pub instances: Vec<(String, String)>,
+    pub prefix: String,
+    /// Path from the request file to the folder with the helper modules, `super::` unless the
+    /// modules mirror the device tree.
+    pub root: String,
+    /// Rustdoc paragraph naming the models declaring the action, only set for merged description sets.
+    pub availability: String,
+    /// Models and firmware versions declaring the action, only set for merged description sets.
+    pub models: Vec<(String, String)>,
+    /// Name of the marker type for `Capabilities::supports`, only set if the capabilities module is generated.
+    pub marker: String,
 }
 
 impl RequestFunction {
@@ -191,6 +437,12 @@ impl RequestFunction {
             service_type: "".to_string(),
             action_name: "".to_string(),
             control_url: "".to_string(),
+            instances: vec![],
+            prefix: "".to_string(),
+            root: "super::".to_string(),
+            availability: "".to_string(),
+            models: vec![],
+            marker: "".to_string(),
         }
     }
 
@@ -199,13 +451,26 @@ impl RequestFunction {
         let service_type_parts: Vec<&str> = self.service_type.split(':').collect();
 
         let function_print = format!(
-            "pub fn generate_{}_request({} id: Option<&str>) -> (String, String, String)\
+            "{}pub fn generate_{}_request({} id: Option<&str>) -> (String, String, String)\
              {{ \n\tlet id = id.unwrap_or(\"1\");\n",
+            self.documentation(),
             self.name_rusty,
             self.parameter_for_function()
         );
 
-        let uri_print = format!("\tlet uri = \"{}\";\n", self.control_url);
+        let other_instances = self.other_instances();
+        let uri_print = if other_instances.is_empty() {
+            format!("\tlet uri = \"{}\";\n", self.control_url)
+        } else {
+            let arms: String = other_instances
+                .iter()
+                .map(|(version, control_url)| format!("\t\t{:?} => {:?},\n", version, control_url))
+                .collect();
+            format!(
+                "\tlet uri = match id {{\n{}\t\t_ => {:?},\n\t}};\n",
+                arms, self.control_url
+            )
+        };
 
         let header = format!(
             "\tlet header = format!(\"{}:{}:{}:{}:{{}}#{}\", id);\n",
@@ -218,7 +483,7 @@ impl RequestFunction {
 
         let body_print = format!(
             "\tlet body = format!(\"<?xml version=\\\"1.0\\\"?><s:Envelope \
-            xmlns:s=\\\"http://schemas.xmlsoap.org/soap/envelope/\\\"s:encodingStyle=\
+            xmlns:s=\\\"http://schemas.xmlsoap.org/soap/envelope/\\\" s:encodingStyle=\
             \\\"http://schemas.xmlsoap.org/soap/encoding/\\\"><s:Body><u:{} xmlns:u=\
             \\\"{}:{}:{}:{}:{{}}\\\">{}</u:{}></s:Body></s:Envelope>\",id {});\n",
             self.action_name,
@@ -230,12 +495,108 @@ impl RequestFunction {
             self.action_name,
             self.parameter_for_code()
         );
+        let models = models_constant(
+            &format!(
+                "{}_MODELS",
+                self.name_rusty.trim_start_matches("r#").to_uppercase()
+            ),
+            &format!(
+                "Models and firmware versions declaring `{}`, see `generate_{}_request`.",
+                self.action_name, self.name_rusty
+            ),
+            &self.models,
+        );
         format!(
-            "{}{}{}{}\t(uri.to_string(), header, body) \n}}\n",
-            function_print, uri_print, header, body_print
+            "{}{}{}{}{}{}\t(uri.to_string(), header, body) \n}}\n",
+            self.marker(),
+            models,
+            function_print,
+            uri_print,
+            header,
+            body_print
         )
     }
 
+    /// Returns the versions and control URLs of the instances with another version and control
+    /// URL than the service itself, which `id` selects.
+    fn other_instances(&self) -> Vec<&(String, String)> {
+        let mut result: Vec<&(String, String)> = vec![];
+        let own_version = self.instances.first().map(|(version, _)| version);
+        for instance in self.instances.iter().skip(1) {
+            if instance.1 != self.control_url
+                && Some(&instance.0) != own_version
+                && !result.iter().any(|(version, _)| *version == instance.0)
+            {
+                result.push(instance);
+            }
+        }
+        result
+    }
+
+    /// Returns the marker type of the action and its `Action` implementation, empty without `marker`.
+    fn marker(&self) -> String {
+        if self.marker.is_empty() {
+            return String::new();
+        }
+        format!(
+            "/// Marker of the `{}` action, see `Capabilities::supports`.\n\
+             pub struct {};\n\n\
+             impl {}{}capabilities::Action for {} {{\n\
+             \tconst SERVICE_TYPE: &'static str = \"{}\";\n\
+             \tconst NAME: &'static str = \"{}\";\n}}\n\n",
+            self.action_name,
+            self.marker,
+            self.root,
+            self.prefix,
+            self.marker,
+            self.service_type,
+            self.action_name
+        )
+    }
+
+    /// Helper function for `create_function`. Creates the rustdoc comment of the request function.
+    pub fn documentation(&self) -> String {
+        let other_instances: String = self
+            .other_instances()
+            .iter()
+            .map(|(version, control_url)| {
+                format!(
+                    ", {} for `id` {}",
+                    code_span(control_url),
+                    code_span(version)
+                )
+            })
+            .collect();
+        let mut result = format!(
+            "/// Creates a `{}` request for service `{}`.\n{}///\n/// Control URL: {}{}\n///\n\
+             /// # Arguments\n",
+            self.action_name,
+            self.service_type,
+            self.availability,
+            code_span(&self.control_url),
+            other_instances
+        );
+        for parameter in &self.parameter {
+            result.push_str(
+                format!(
+                    "/// * `{}` - {}\n",
+                    parameter.parameter_name_rusty, parameter.documentation
+                )
+                .as_str(),
+            );
+        }
+        result.push_str(
+            format!(
+                "/// * `id` - Number of the service instance, `1` if `None`.\n///\n\
+                 /// Returns the uri, the SOAPAction header and the body of the request.\n\
+                 /// The uri is relative to the base URL of the device, see `{}{}url::resolve`.\n",
+                self.root, self.prefix
+            )
+            .as_str(),
+        );
+        result
+    }
+
     /// Helper function for `create_function`. Creates a String containing the parameter for the request function signature.
     pub fn parameter_for_function(&self) -> String {
         if self.parameter.is_empty() {
@@ -245,6 +606,8 @@ impl RequestFunction {
         for parameter in &self.parameter {
             if parameter.type_name == "String" {
                 result.push_str(format!("{}: &str,", parameter.parameter_name_rusty).as_str());
+            } else if parameter.type_name == "Vec<u8>" {
+                result.push_str(format!("{}: &[u8],", parameter.parameter_name_rusty).as_str());
             } else {
                 result.push_str(
                     format!(
@@ -277,7 +640,13 @@ impl RequestFunction {
     pub fn parameter_for_code(&self) -> String {
         let mut result = String::new();
         for parameter in &self.parameter {
-            result.push_str(format!(",{}", parameter.parameter_name_rusty).as_str());
+            result.push_str(
+                format!(
+                    ",{}",
+                    parameter.value_for_body(&format!("{}{}", self.root, self.prefix))
+                )
+                .as_str(),
+            );
         }
         result
     }
@@ -287,6 +656,22 @@ impl RequestFunction {
 #[derive(Debug)]
 pub struct OutputFiles {
     pub annotation_string: Vec<String>,
+    pub firmware_version: String,
+    pub field_mode: FieldMode,
+    pub date_time_mapping: DateTimeMapping,
+    pub uuid_mapping: UuidMapping,
+    pub response_helpers: Vec<&'static str>,
+    pub request_helpers: Vec<&'static str>,
+    pub discovery_module: String,
+    pub capabilities_module: String,
+    pub device_metadata_module: String,
+    /// Write `<prefix>eventing.rs` and the event structs of the services.
+    pub eventing: bool,
+    /// Name of the module of the response folder the `tests` modules of the request files take the
+    /// response structs from, e.g. `tr064_responses`, empty if no tests are generated.
+    pub tests_responses_module: String,
+    /// Modules of all devices if the modules mirror the device tree, otherwise empty.
+    pub device_modules: Vec<DeviceModule>,
     pub response_files: Vec<ResponseFile>,
     pub request_files: Vec<RequestFile>,
     pub response_output_folder: String,
@@ -297,6 +682,18 @@ impl OutputFiles {
     pub fn new() -> Self {
         OutputFiles {
             annotation_string: vec![],
+            firmware_version: "".to_string(),
+            field_mode: FieldMode::Strict,
+            date_time_mapping: DateTimeMapping::String,
+            uuid_mapping: UuidMapping::String,
+            response_helpers: vec![],
+            request_helpers: vec![],
+            discovery_module: "".to_string(),
+            capabilities_module: "".to_string(),
+            device_metadata_module: "".to_string(),
+            eventing: false,
+            tests_responses_module: "".to_string(),
+            device_modules: vec![],
             response_files: vec![],
             request_files: vec![],
             response_output_folder: "response_output".to_string(),
@@ -305,28 +702,58 @@ impl OutputFiles {
         }
     }
 
-    /// Calls all functions needed to actually create the output files.
-    fn create_files(&self) {
+    /// Calls all functions needed to actually create the output files. Fails without writing
+    /// anything if two modules of a `mod.rs` would get the same name.
+    fn create_files(&self) -> Result<(), String> {
+        let (responses_mod, requests_mod) = self.mod_files()?;
         self.create_output_folders();
         self.write_annotation_file();
-        self.write_mod_files();
+        self.write_helpers_file();
+        self.write_discovery_file();
+        self.write_capabilities_file();
+        self.write_device_metadata_file();
+        self.write_eventing_file();
+        self.write_url_file();
+        self.write_mod_files(&responses_mod, &requests_mod);
+        self.write_device_mod_files();
         self.write_requests_files();
         self.write_responses_files();
+        Ok(())
     }
     /// Creates output folders. If they are already present, do nothing. On every other error, panic.
     fn create_output_folders(&self) {
         if let Err(e) = fs::create_dir_all(&self.response_output_folder) {
             if e.kind() != std::io::ErrorKind::AlreadyExists {
-                println!("{}", e.to_string());
+                println!("{}", e);
                 panic!();
             }
         };
         if let Err(e) = fs::create_dir_all(&self.request_output_folder) {
             if e.kind() != std::io::ErrorKind::AlreadyExists {
-                println!("{}", e.to_string());
+                println!("{}", e);
                 panic!();
             }
         };
+        for device in &self.device_modules {
+            for output_folder in &[&self.response_output_folder, &self.request_output_folder] {
+                fs::create_dir_all(self.device_folder(output_folder, &device.module_path)).unwrap();
+            }
+        }
+    }
+
+    /// Returns the folder of the device module `module_path` in `output_folder`. The module of
+    /// the root device gets the prefix, like the other modules in `output_folder`.
+    fn device_folder(&self, output_folder: &str, module_path: &[String]) -> String {
+        format!("{}/{}{}", output_folder, self.prefix, module_path.join("/"))
+    }
+
+    /// Returns the file of the module `name` of a service of the device module `folder` in `output_folder`.
+    fn module_file(&self, output_folder: &str, folder: &[String], name: &str) -> String {
+        if folder.is_empty() {
+            format!("{}/{}{}.rs", output_folder, self.prefix, name)
+        } else {
+            format!("{}/{}.rs", self.device_folder(output_folder, folder), name)
+        }
     }
 
     /// Writes the annotation file to the disk.
@@ -348,12 +775,120 @@ impl OutputFiles {
         .unwrap();
     }
 
+    /// Writes the (de)serialization helpers used by the response and request files to the disk, if any.
+    fn write_helpers_file(&self) {
+        if !self.response_helpers.is_empty() {
+            let mut file = File::create(format!(
+                "{}/{}serde_helpers.rs",
+                &self.response_output_folder, self.prefix
+            ))
+            .unwrap();
+            file.write_all(serde_helpers::HEADER.as_bytes()).unwrap();
+            for helper in &self.response_helpers {
+                file.write_all(helper.as_bytes()).unwrap();
+            }
+        }
+        if !self.request_helpers.is_empty() {
+            let mut file = File::create(format!(
+                "{}/{}serde_helpers.rs",
+                &self.request_output_folder, self.prefix
+            ))
+            .unwrap();
+            for helper in &self.request_helpers {
+                file.write_all(helper.as_bytes()).unwrap();
+            }
+        }
+    }
+
+    /// Adds `helper` to the deserialization helpers written next to the response files.
+    pub fn add_response_helper(&mut self, helper: &'static str) {
+        if !self.response_helpers.contains(&helper) {
+            self.response_helpers.push(helper);
+        }
+    }
+
+    /// Writes the discovery module to the disk, if it was generated.
+    fn write_discovery_file(&self) {
+        if self.discovery_module.is_empty() {
+            return;
+        }
+        let mut file = File::create(format!(
+            "{}/{}discovery.rs",
+            &self.request_output_folder, self.prefix
+        ))
+        .unwrap();
+        file.write_all(self.discovery_module.as_bytes()).unwrap();
+    }
+
+    /// Writes the capabilities module to the disk, if it was generated.
+    fn write_capabilities_file(&self) {
+        if self.capabilities_module.is_empty() {
+            return;
+        }
+        let mut file = File::create(format!(
+            "{}/{}capabilities.rs",
+            &self.request_output_folder, self.prefix
+        ))
+        .unwrap();
+        file.write_all(self.capabilities_module.as_bytes()).unwrap();
+    }
+
+    /// Writes the device metadata module to the disk, if it was generated.
+    fn write_device_metadata_file(&self) {
+        if self.device_metadata_module.is_empty() {
+            return;
+        }
+        let mut file = File::create(format!(
+            "{}/{}device_metadata.rs",
+            &self.request_output_folder, self.prefix
+        ))
+        .unwrap();
+        file.write_all(self.device_metadata_module.as_bytes())
+            .unwrap();
+    }
+
+    /// Writes the GENA subscription requests and the notification parser to the disk, if eventing is generated.
+    fn write_eventing_file(&self) {
+        if !self.eventing {
+            return;
+        }
+        let source = include_str!("eventing.rs").replace("\r\n", "\n");
+        let source = &source[..source.find("\n#[cfg(test)]").unwrap_or(source.len())];
+        let source = source.replace("super::url::", &format!("super::{}url::", self.prefix));
+        let mut file = File::create(format!(
+            "{}/{}eventing.rs",
+            &self.request_output_folder, self.prefix
+        ))
+        .unwrap();
+        file.write_all(source.as_bytes()).unwrap();
+    }
+
+    /// Writes the URL resolution functions for the `uri` of the request functions to the disk.
+    fn write_url_file(&self) {
+        let source = include_str!("url.rs").replace("\r\n", "\n");
+        let source = &source[..source.find("\n#[cfg(test)]").unwrap_or(source.len())];
+        let mut file = File::create(format!(
+            "{}/{}url.rs",
+            &self.request_output_folder, self.prefix
+        ))
+        .unwrap();
+        file.write_all(source.as_bytes()).unwrap();
+    }
+
+    /// Adds `helper` to the serialization helpers written next to the request files.
+    pub fn add_request_helper(&mut self, helper: &'static str) {
+        if !self.request_helpers.contains(&helper) {
+            self.request_helpers.push(helper);
+        }
+    }
+
     /// Writes all the response files to the disk.
     fn write_responses_files(&self) {
         for response_file in &self.response_files {
-            let mut file = File::create(format!(
-                "{}/{}{}.rs",
-                &self.response_output_folder, self.prefix, response_file.name
+            let mut file = File::create(self.module_file(
+                &self.response_output_folder,
+                &response_file.folder,
+                &response_file.name,
             ))
             .unwrap();
             file.write_all(response_file.content.join("").as_bytes())
@@ -362,85 +897,574 @@ impl OutputFiles {
     }
 
     /// Writes the mod.rs files into the two folders.
-    fn write_mod_files(&self) {
+    fn write_mod_files(&self, responses_mod: &str, requests_mod: &str) {
         let mut file = File::create(format!("{}/mod.rs", &self.response_output_folder)).unwrap();
+        file.write_all(responses_mod.as_bytes()).unwrap();
+        let mut file = File::create(format!("{}/mod.rs", &self.request_output_folder)).unwrap();
+        file.write_all(requests_mod.as_bytes()).unwrap();
+    }
+
+    /// Returns the content of the `mod.rs` files of the response and the request folder.
+    fn mod_files(&self) -> Result<(String, String), String> {
         let mut file_name_vec = vec![format!("pub mod {}multi_use;\n", self.prefix)];
+        if !self.response_helpers.is_empty() {
+            file_name_vec.push(format!("pub mod {}serde_helpers;\n", self.prefix));
+        }
         for response_file in &self.response_files {
-            file_name_vec.push(format!("pub mod {}{};\n", self.prefix, response_file.name));
+            if response_file.folder.is_empty() {
+                file_name_vec.push(format!("pub mod {}{};\n", self.prefix, response_file.name));
+            }
         }
-        file_name_vec.sort();
-        file_name_vec.dedup();
-        file.write_all(file_name_vec.join("").as_bytes()).unwrap();
+        if let Some(root) = self.device_modules.first() {
+            file_name_vec.push(format!("pub mod {}{};\n", self.prefix, root.module_path[0]));
+        }
+        let responses_mod = mod_file(&self.response_output_folder, file_name_vec)?;
 
-        file_name_vec.clear();
-        let mut file = File::create(format!("{}/mod.rs", &self.request_output_folder)).unwrap();
+        let mut file_name_vec = vec![];
+        file_name_vec.push(format!("pub mod {}url;\n", self.prefix));
+        if !self.discovery_module.is_empty() {
+            file_name_vec.push(format!("pub mod {}discovery;\n", self.prefix));
+        }
+        if !self.capabilities_module.is_empty() {
+            file_name_vec.push(format!("pub mod {}capabilities;\n", self.prefix));
+        }
+        if !self.device_metadata_module.is_empty() {
+            file_name_vec.push(format!("pub mod {}device_metadata;\n", self.prefix));
+        }
+        if self.eventing {
+            file_name_vec.push(format!("pub mod {}eventing;\n", self.prefix));
+        }
+        if !self.request_helpers.is_empty() {
+            file_name_vec.push(format!("pub mod {}serde_helpers;\n", self.prefix));
+        }
         for request_file in &self.request_files {
-            file_name_vec.push(format!("pub mod {}{};\n", self.prefix, request_file.name));
+            if request_file.folder.is_empty() {
+                file_name_vec.push(format!("pub mod {}{};\n", self.prefix, request_file.name));
+            }
+        }
+        if let Some(root) = self.device_modules.first() {
+            file_name_vec.push(format!("pub mod {}{};\n", self.prefix, root.module_path[0]));
+        }
+        let requests_mod = mod_file(&self.request_output_folder, file_name_vec)?;
+        Ok((responses_mod, requests_mod))
+    }
+
+    /// Writes the mod.rs files of the device modules into the two folders, if the modules mirror
+    /// the device tree. The request `mod.rs` starts with the metadata of the device.
+    fn write_device_mod_files(&self) {
+        for device in &self.device_modules {
+            let mut modules: Vec<String> = device
+                .children
+                .iter()
+                .map(|child| format!("pub mod {};\n", child))
+                .collect();
+            let mut response_modules = modules.clone();
+            for response_file in &self.response_files {
+                if response_file.folder == device.module_path {
+                    response_modules.push(format!("pub mod {};\n", response_file.name));
+                }
+            }
+            response_modules.sort();
+            let mut file = File::create(format!(
+                "{}/mod.rs",
+                self.device_folder(&self.response_output_folder, &device.module_path)
+            ))
+            .unwrap();
+            file.write_all(response_modules.join("").as_bytes())
+                .unwrap();
+
+            for request_file in &self.request_files {
+                if request_file.folder == device.module_path {
+                    modules.push(format!("pub mod {};\n", request_file.name));
+                }
+            }
+            modules.sort();
+            let mut file = File::create(format!(
+                "{}/mod.rs",
+                self.device_folder(&self.request_output_folder, &device.module_path)
+            ))
+            .unwrap();
+            file.write_all(device.metadata.as_bytes()).unwrap();
+            file.write_all(modules.join("").as_bytes()).unwrap();
         }
-        file_name_vec.sort();
-        file_name_vec.dedup();
-        file.write_all(file_name_vec.join("").as_bytes()).unwrap();
     }
 
     /// Writes all request files to disk.
     fn write_requests_files(&self) {
         for request_file in &self.request_files {
-            let mut file = File::create(format!(
-                "{}/{}{}.rs",
-                &self.request_output_folder, self.prefix, request_file.name
+            let mut file = File::create(self.module_file(
+                &self.request_output_folder,
+                &request_file.folder,
+                &request_file.name,
             ))
             .unwrap();
+            file.write_all(request_file.documentation.as_bytes())
+                .unwrap();
+            let models = models_constant(
+                "MODELS",
+                "Models and firmware versions declaring this service.",
+                &request_file.models,
+            );
+            file.write_all(models.as_bytes()).unwrap();
+            file.write_all(request_file.event.as_bytes()).unwrap();
             for function in &request_file.request_functions {
                 file.write_all(function.create_function().as_bytes())
                     .unwrap();
             }
+            if !request_file.tests.is_empty() {
+                file.write_all(
+                    format!(
+                        "\n/// Round trips of the requests and responses of the service with example values.\n\
+                         #[cfg(test)]\nmod tests {{\n{}}}\n",
+                        request_file.tests.join("\n")
+                    )
+                    .as_bytes(),
+                )
+                .unwrap();
+            }
         }
     }
 }
 
 impl ApiDescDir {
-    /// Generates request and response files from the FritzBox TR-064 API.
+    /// Generates request and response files from the FritzBox TR-064 API for `services`, the
+    /// result of `fetch_services`. For merged description sets, `availability` is documented and
+    /// listed in `MODELS` constants, otherwise it is empty.
+    /// Fails without writing anything if two items would get the same Rust name, or if the
+    /// read-only profile is selected and `services` have actions changing the device.
     pub fn generate_files(
         &self,
-        address: &str,
+        services: &[ServiceDescription],
+        availability: &Availability,
         responses_output_folder: String,
         request_output_folder: String,
         prefix: Option<String>,
-    ) {
+        options: &GeneratorOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if options.profile == Profile::ReadOnly {
+            check_read_only(services, &options.filter.allowlist)?;
+        }
         let mut output_files = OutputFiles::new();
-        let prefix = if prefix.is_some() {
-            format!("{}_", prefix.unwrap())
+        let prefix = if let Some(prefix) = prefix {
+            format!("{}_", prefix)
         } else {
             String::from("")
         };
-        output_files.response_output_folder =
-            format!("output/{}{}", prefix, responses_output_folder);
-        output_files.request_output_folder = format!("output/{}{}", prefix, request_output_folder);
+        output_files.response_output_folder = format!(
+            "{}/{}{}",
+            options.output_folder, prefix, responses_output_folder
+        );
+        output_files.request_output_folder = format!(
+            "{}/{}{}",
+            options.output_folder, prefix, request_output_folder
+        );
         output_files.prefix = prefix;
+        output_files.firmware_version = self.system_version.display.clone();
+        output_files.field_mode = options.field_mode;
+        output_files.date_time_mapping = options.date_time_mapping;
+        output_files.uuid_mapping = options.uuid_mapping;
+        if options.generate_discovery {
+            output_files.discovery_module = discovery_module(
+                &self.device.service_types(&options.filter),
+                &output_files.prefix,
+            );
+        }
+        if options.generate_capabilities {
+            output_files.capabilities_module = capabilities_module(&output_files.prefix);
+        }
+        // The device metadata of merged sets would only describe the first model.
+        if options.generate_device_metadata && !availability.is_merged() {
+            output_files.device_metadata_module =
+                device_metadata_module(self, &format!("{}icons", output_files.prefix));
+        }
+        output_files.eventing = options.generate_eventing;
+        if options.generate_tests {
+            output_files.tests_responses_module =
+                format!("{}{}", output_files.prefix, responses_output_folder);
+        }
+        if options.nest_modules {
+            output_files.device_modules = self.device.modules();
+        }
 
-        self.handle_device(&self.device, address, &mut output_files);
+        // Without device modules a service declared by several devices can only be written once.
+        let services = if options.nest_modules {
+            services.to_vec()
+        } else {
+            first_per_service(services)
+        };
+        let names = assign_names(
+            &services,
+            &options.name_overrides,
+            &output_files.device_modules,
+        )?;
+        for service in &services {
+            service.scpd.fill_output_files(
+                &mut output_files,
+                &names,
+                &service.service,
+                availability,
+            );
+        }
 
-        output_files.create_files();
+        output_files.create_files()?;
+        Ok(())
     }
 
-    /// Handles all services of a device and all contained devices.
-    fn handle_device(&self, device: &Device, address: &str, mut output_files: &mut OutputFiles) {
-        for service in &device.service_list.service {
-            let resp = reqwest::blocking::get(format!("{}{}", address, service.scpd_url).as_str())
-                .unwrap()
-                .text()
-                .unwrap();
-            let scdp: ApiDesc = serde_xml_rs::from_str(&*resp).unwrap();
-            service.service_type.split(':').nth(3).unwrap();
-            scdp.fill_output_files(
-                &mut output_files,
-                service.service_type.split(':').nth(3).unwrap(),
-                &service.control_url,
-                &service.service_type,
+    /// Returns the base URL to resolve the URLs of the description against, `URLBase` if the
+    /// description has one, otherwise `location`, the URL the description was fetched from.
+    pub fn base<'a>(&'a self, location: &'a str) -> &'a str {
+        if self.url_base.is_empty() {
+            location
+        } else {
+            self.url_base.as_str()
+        }
+    }
+
+    /// Downloads the icons of all devices of the description fetched from `location` into
+    /// `folder`, named like in the device metadata module, see `icon_files`. Returns the number of icons.
+    pub fn download_icons(
+        &self,
+        fetcher: &Fetcher,
+        location: &str,
+        folder: &str,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let icons = icon_files(&self.device);
+        if icons.is_empty() {
+            return Ok(0);
+        }
+        fs::create_dir_all(folder)?;
+        for (file, icon) in &icons {
+            let icon = fetcher.get_bytes(&resolve(self.base(location), &icon.url))?;
+            fs::write(format!("{}/{}", folder, file), icon)?;
+        }
+        Ok(icons.len())
+    }
+
+    /// Returns the services of all devices `filter` selects, see `handle_device`.
+    pub fn services(&self, base: &str, filter: &Filter) -> Vec<Service> {
+        let mut services = vec![];
+        self.handle_device(&self.device, &[], base, filter, &mut services);
+        services
+    }
+
+    /// Fetches the SCPDs of all services `filter` selects of the description fetched from `location`.
+    /// Relative URLs are resolved against `URLBase` or, if there is none, against `location`.
+    pub fn fetch_services(
+        &self,
+        fetcher: &Fetcher,
+        location: &str,
+        filter: &Filter,
+    ) -> Result<Vec<ServiceDescription>, Box<dyn std::error::Error>> {
+        let base = self.base(location);
+        let services = self.services(base, filter);
+        // Without a firmware version there is nothing to invalidate the cache with.
+        let cache = if self.system_version.buildnumber != 0 && !self.device.udn.is_empty() {
+            let version = format!(
+                "{}-{}",
+                self.system_version.display, self.system_version.buildnumber
             );
+            fetcher.open_cache(&self.device.udn, &version)?
+        } else {
+            None
+        };
+        fetch_descriptions(fetcher, cache.as_ref(), base, services)
+    }
+
+    /// Collects the services `filter` selects of a device and all contained devices.
+    /// Services of a device which only differ in their version (e.g. `WLANConfiguration:1` to `:3`)
+    /// and share an SCPD are only added once, with their control URLs in `instances`, the `id`
+    /// parameter of the request functions selects between them. Services declared by several
+    /// devices are added once per device.
+    /// The URLs of the added services are resolved against `base` and made relative to its origin,
+    /// so the generated code can resolve them against any base at runtime.
+    fn handle_device(
+        &self,
+        device: &Device,
+        device_path: &[usize],
+        base: &str,
+        filter: &Filter,
+        services: &mut Vec<Service>,
+    ) {
+        for service in &device.service_list.service {
+            if !filter.selects_service(service) {
+                continue;
+            }
+            let mut service = service.clone();
+            service.scpd_url = origin_relative(base, &service.scpd_url);
+            service.control_url = origin_relative(base, &service.control_url);
+            service.event_sub_url = origin_relative(base, &service.event_sub_url);
+            service.device_path = device_path.to_vec();
+            let version = match service.service_type.rsplit_once(':') {
+                Some((_, version)) => version.to_string(),
+                None => String::new(),
+            };
+            let instance = (version, service.control_url.clone());
+            match services.iter_mut().find(|known| {
+                known.device_path == service.device_path
+                    && service_item(known) == service_item(&service)
+                    && known.scpd_url == service.scpd_url
+            }) {
+                Some(known) => known.instances.push(instance),
+                None => {
+                    service.instances = vec![instance];
+                    services.push(service);
+                }
+            }
+        }
+        for (index, local_device) in device.device_list.device.iter().enumerate() {
+            let mut local_device_path = device_path.to_vec();
+            local_device_path.push(index);
+            self.handle_device(local_device, &local_device_path, base, filter, services);
+        }
+    }
+}
+
+/// Returns the sorted `pub mod` lines `modules` of the `mod.rs` in `folder`. Fails if a module is
+/// declared twice, as its file would have been written twice.
+fn mod_file(folder: &str, mut modules: Vec<String>) -> Result<String, String> {
+    modules.sort();
+    if let Some(pair) = modules.windows(2).find(|pair| pair[0] == pair[1]) {
+        return Err(format!(
+            "{}/mod.rs would declare `{}` twice",
+            folder,
+            pair[0].trim_end()
+        ));
+    }
+    Ok(modules.concat())
+}
+
+/// Returns `services` without the services another device declared before, with the same type
+/// and SCPD.
+fn first_per_service(services: &[ServiceDescription]) -> Vec<ServiceDescription> {
+    let mut result: Vec<ServiceDescription> = vec![];
+    for description in services {
+        if !result.iter().any(|known| {
+            service_item(&known.service) == service_item(&description.service)
+                && known.service.scpd_url == description.service.scpd_url
+        }) {
+            result.push(description.clone());
+        }
+    }
+    result
+}
+
+/// A parsed SCPD with its source, or why fetching or parsing it failed.
+type FetchedScpd = Result<(ApiDesc, String), String>;
+
+/// Fetches and parses the SCPDs of `services`, resolved against `base`, with up to
+/// `fetcher.concurrency()` requests at a time or from `cache`, and prints the progress per
+/// service to stderr.
+/// No further SCPDs are fetched after the first failure. The result keeps the order of `services`.
+fn fetch_descriptions(
+    fetcher: &Fetcher,
+    cache: Option<&DescriptionCache>,
+    base: &str,
+    services: Vec<Service>,
+) -> Result<Vec<ServiceDescription>, Box<dyn std::error::Error>> {
+    let total = services.len();
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results: Mutex<Vec<Option<FetchedScpd>>> =
+        Mutex::new(services.iter().map(|_| None).collect());
+    thread::scope(|scope| {
+        for _ in 0..fetcher.concurrency().min(total) {
+            scope.spawn(|| {
+                while !failed.load(Ordering::SeqCst) {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let service = match services.get(index) {
+                        Some(service) => service,
+                        None => break,
+                    };
+                    let url = resolve(base, &service.scpd_url);
+                    let result = fetcher
+                        .get_cached(&url, cache)
+                        .map_err(|e| e.to_string())
+                        .and_then(|resp| match parse_scpd(&url, &resp) {
+                            Ok(scpd) => Ok((scpd, resp)),
+                            Err(e) => {
+                                // Fetched again next time instead of failing from the cache.
+                                if let Some(Err(e)) = cache.map(|cache| cache.remove(&url)) {
+                                    eprintln!("removing {} from the cache failed: {}", url, e);
+                                }
+                                Err(e)
+                            }
+                        });
+                    failed.fetch_or(result.is_err(), Ordering::SeqCst);
+                    eprintln!(
+                        "[{}/{}] {} {}",
+                        finished.fetch_add(1, Ordering::SeqCst) + 1,
+                        total,
+                        service.service_type,
+                        if result.is_ok() { "fetched" } else { "failed" }
+                    );
+                    results.lock().expect("no fetch panicked")[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    let mut descriptions = vec![];
+    let results = results.into_inner().expect("no fetch panicked");
+    for (service, result) in services.into_iter().zip(results) {
+        match result {
+            Some(Ok((scpd, scpd_xml))) => descriptions.push(ServiceDescription {
+                service,
+                scpd,
+                scpd_xml,
+            }),
+            Some(Err(e)) => return Err(e.into()),
+            None => {}
         }
-        for local_device in &device.device_list.device {
-            self.handle_device(local_device, address, &mut output_files);
+    }
+    Ok(descriptions)
+}
+
+/// Parses the SCPD fetched from or stored for `url`.
+pub fn parse_scpd(url: &str, xml: &str) -> Result<ApiDesc, String> {
+    serde_xml_rs::from_str(xml).map_err(|e| format!("parsing {} failed: {}", url, e))
+}
+
+/// Resolves `url` against `base` and returns it without the origin if it is the origin of `base`,
+/// e.g. `/upnp/control/deviceinfo`. URLs on other hosts or ports stay absolute.
+fn origin_relative(base: &str, url: &str) -> String {
+    if url.trim().is_empty() {
+        return String::new();
+    }
+    let url = resolve(base, url);
+    match (origin(&url), origin(base)) {
+        (Some(url_origin), Some(base_origin)) if url_origin.eq_ignore_ascii_case(base_origin) => {
+            match &url[url_origin.len()..] {
+                "" => "/".to_string(),
+                path => path.to_string(),
+            }
         }
+        _ => url,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_handling::description_set::fixtures::{description, device};
+    use crate::api_handling::fetch::FetchOptions;
+    use std::io::Read;
+    use std::net::TcpListener;
+
+    #[test]
+    fn unparsable_scpds_are_not_cached() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0u8; 1024];
+            let _ = stream.read(&mut buffer).unwrap();
+            let body = "<scpd><actionList>";
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+        let root = std::env::temp_dir().join(format!("scpd_cache_{}", std::process::id()));
+        let root = root.to_str().unwrap();
+        let cache = DescriptionCache::open(root, "uuid:1", "154.07.29").unwrap();
+        let service = Service {
+            service_type: "urn:dslforum-org:service:Hosts:1".to_string(),
+            scpd_url: "/hostsSCPD.xml".to_string(),
+            ..Service::default()
+        };
+        let fetcher = Fetcher::new(&FetchOptions::new()).unwrap();
+
+        let error = fetch_descriptions(&fetcher, Some(&cache), &base, vec![service]).unwrap_err();
+        assert!(error.to_string().contains("parsing"), "{}", error);
+        assert_eq!(cache.load(&format!("{}hostsSCPD.xml", base)), None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn services_per_device() {
+        let lan = device(
+            "LANDevice",
+            &[
+                ("Hosts", "/upnp/control/hosts"),
+                ("Hosts", "/upnp/control/hosts2"),
+            ],
+            &[],
+        );
+        let wan = device("WANDevice", &[("Hosts", "/upnp/control/wanhosts")], &[]);
+        let root = device(
+            "InternetGatewayDevice",
+            &[("DeviceInfo", "/upnp/control/deviceinfo")],
+            &[lan, wan],
+        );
+        let services = description(&root).services("http://fritz.box:49000/", &Filter::new());
+        let services: Vec<(&str, &[usize])> = services
+            .iter()
+            .map(|service| (service.control_url.as_str(), service.device_path.as_slice()))
+            .collect();
+        assert_eq!(
+            services,
+            vec![
+                ("/upnp/control/deviceinfo", &[][..]),
+                ("/upnp/control/hosts", &[0][..]),
+                ("/upnp/control/wanhosts", &[1][..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn models_constants() {
+        assert_eq!(models_constant("MODELS", "Models.", &[]), "");
+        let models = [
+            ("FRITZ!Box 7590".to_string(), "154.07.29".to_string()),
+            ("FRITZ!Box \"7490\" \\ AX".to_string(), String::new()),
+        ];
+        assert_eq!(
+            models_constant("MODELS", "Models.", &models),
+            "/// Models.\npub const MODELS: &[(&str, &str)] = &[(\"FRITZ!Box 7590\", \"154.07.29\"), \
+             (\"FRITZ!Box \\\"7490\\\" \\\\ AX\", \"\")];\n\n"
+        );
+    }
+
+    #[test]
+    fn control_url_per_instance() {
+        let root = device(
+            "InternetGatewayDevice",
+            &[
+                ("WLANConfiguration:1", "/upnp/control/wlanconfig1"),
+                ("WLANConfiguration:2", "/upnp/control/wlanconfig2"),
+                ("WLANConfiguration:3", "/upnp/control/wlanconfig3"),
+                ("Hosts", "/upnp/control/hosts"),
+                ("Hosts", "/upnp/control/hosts2"),
+            ],
+            &[],
+        );
+        let services = description(&root).services("http://fritz.box:49000/", &Filter::new());
+        assert_eq!(services.len(), 2);
+        let mut function = RequestFunction::new();
+        function.name_rusty = "get_info".to_string();
+        function.action_name = "GetInfo".to_string();
+        function.service_type = services[0].service_type.clone();
+        function.control_url = services[0].control_url.clone();
+        function.instances = services[0].instances.clone();
+        let code = function.create_function();
+        assert!(code.contains(
+            "\tlet uri = match id {\n\
+             \t\t\"2\" => \"/upnp/control/wlanconfig2\",\n\
+             \t\t\"3\" => \"/upnp/control/wlanconfig3\",\n\
+             \t\t_ => \"/upnp/control/wlanconfig1\",\n\t};\n"
+        ));
+        assert!(code.contains("`/upnp/control/wlanconfig3` for `id` `3`"));
+
+        function.service_type = services[1].service_type.clone();
+        function.control_url = services[1].control_url.clone();
+        function.instances = services[1].instances.clone();
+        assert!(function
+            .create_function()
+            .contains("\tlet uri = \"/upnp/control/hosts\";\n"));
     }
 }
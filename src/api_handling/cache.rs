@@ -0,0 +1,169 @@
+//! On-disk cache of service descriptions. SCPDs only change with the firmware, so they are kept
+//! per box (`UDN`) and firmware version and revalidated with `ETag`/`Last-Modified` if the box sends them.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// A cached description and the validators the box sent with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedDescription {
+    pub body: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// The cached descriptions of one box with one firmware version.
+#[derive(Debug)]
+pub struct DescriptionCache {
+    folder: PathBuf,
+}
+
+impl DescriptionCache {
+    /// Opens the cache in `root` for the box `udn` with the firmware `version`, e.g. `141.07.20-84539`.
+    /// Descriptions cached for other firmware versions of the box are removed.
+    pub fn open(root: &str, udn: &str, version: &str) -> io::Result<Self> {
+        let box_folder = PathBuf::from(root).join(file_name(udn));
+        let folder = box_folder.join(file_name(version));
+        fs::create_dir_all(&folder)?;
+        for entry in fs::read_dir(&box_folder)? {
+            let path = entry?.path();
+            if path != folder && path.is_dir() {
+                fs::remove_dir_all(path)?;
+            }
+        }
+        Ok(DescriptionCache { folder })
+    }
+
+    /// Returns the cached description of `url`, `None` if there is none or it can't be read.
+    pub fn load(&self, url: &str) -> Option<CachedDescription> {
+        let (body_path, validators_path) = self.paths(url);
+        let body = fs::read_to_string(body_path).ok()?;
+        let validators = fs::read_to_string(validators_path).ok()?;
+        let mut description = CachedDescription {
+            body,
+            etag: None,
+            last_modified: None,
+        };
+        for line in validators.lines() {
+            if let Some(etag) = line.strip_prefix("ETag: ") {
+                description.etag = Some(etag.to_string());
+            } else if let Some(last_modified) = line.strip_prefix("Last-Modified: ") {
+                description.last_modified = Some(last_modified.to_string());
+            }
+        }
+        Some(description)
+    }
+
+    /// Stores the description of `url`, replacing an older one.
+    pub fn store(&self, url: &str, description: &CachedDescription) -> io::Result<()> {
+        let (body_path, validators_path) = self.paths(url);
+        let mut validators = String::new();
+        if let Some(etag) = &description.etag {
+            validators.push_str(format!("ETag: {}\n", etag).as_str());
+        }
+        if let Some(last_modified) = &description.last_modified {
+            validators.push_str(format!("Last-Modified: {}\n", last_modified).as_str());
+        }
+        fs::write(body_path, &description.body)?;
+        // Written last, `load` ignores a body without validators file.
+        fs::write(validators_path, validators)
+    }
+
+    /// Removes the description of `url`, e.g. because it can't be parsed.
+    pub fn remove(&self, url: &str) -> io::Result<()> {
+        let (body_path, validators_path) = self.paths(url);
+        // Removed first, `load` ignores a body without validators file.
+        for path in [validators_path, body_path] {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn paths(&self, url: &str) -> (PathBuf, PathBuf) {
+        let name = cache_file_name(url);
+        (
+            self.folder.join(format!("{}.xml", name)),
+            self.folder.join(format!("{}.validators", name)),
+        )
+    }
+}
+
+/// Replaces everything but ASCII letters, digits, `.` and `-` in `key` with `_`.
+pub fn file_name(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    match name.trim_start_matches('.') {
+        "" => "_".to_string(),
+        name => name.to_string(),
+    }
+}
+
+/// Returns the name of the cache files of `url`: `file_name` of it for people looking into the
+/// folder and the MD5 hash of it, as `file_name` maps e.g. `/a/b_c.xml` and `/a/b/c.xml` to
+/// the same name.
+fn cache_file_name(url: &str) -> String {
+    format!("{}-{:x}", file_name(url), md5::compute(url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_load_and_invalidate() {
+        let root = std::env::temp_dir().join(format!("description_cache_{}", std::process::id()));
+        let root = root.to_str().unwrap();
+        let udn = "uuid:739f2409-bccb-40e7-8e6c-9CA2F4A45E3C";
+        let url = "http://fritz.box:49000/deviceinfoSCPD.xml";
+        let description = CachedDescription {
+            body: "<scpd/>".to_string(),
+            etag: Some("\"1a-2b\"".to_string()),
+            last_modified: None,
+        };
+
+        let cache = DescriptionCache::open(root, udn, "141.07.20-84539").unwrap();
+        assert_eq!(cache.load(url), None);
+        cache.store(url, &description).unwrap();
+        assert_eq!(cache.load(url), Some(description.clone()));
+        assert_eq!(cache.load("http://fritz.box:49000/hostsSCPD.xml"), None);
+        cache.remove(url).unwrap();
+        assert_eq!(cache.load(url), None);
+        cache.remove(url).unwrap();
+        cache.store(url, &description).unwrap();
+
+        let cache = DescriptionCache::open(root, udn, "141.07.20-84539").unwrap();
+        assert_eq!(cache.load(url), Some(description));
+        let cache = DescriptionCache::open(root, udn, "141.07.21-85000").unwrap();
+        assert_eq!(cache.load(url), None);
+        let cache = DescriptionCache::open(root, udn, "141.07.20-84539").unwrap();
+        assert_eq!(cache.load(url), None);
+
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn file_names() {
+        assert_eq!(
+            file_name("http://fritz.box:49000/tr64desc.xml"),
+            "http___fritz.box_49000_tr64desc.xml"
+        );
+        assert_eq!(file_name(".."), "_");
+        assert_eq!(
+            cache_file_name("/a/b.xml"),
+            format!("_a_b.xml-{:x}", md5::compute("/a/b.xml"))
+        );
+        assert_ne!(cache_file_name("/a/b_c.xml"), cache_file_name("/a/b/c.xml"));
+    }
+}
@@ -0,0 +1,106 @@
+//! Source code of the optional `<prefix>capabilities.rs` module written next to the request files.
+//! It reads the descriptions of the connected device, so code using the bindings can check whether
+//! an action exists before calling it. Like the response files, it needs `serde` and `serde-xml-rs`.
+
+/// The module documentation, the rest is `capabilities_module/capabilities.rs` and
+/// `capabilities_module/without_version.rs`, which are also compiled by the tests.
+const CODE: &str = concat!(
+    "//! Which services and actions the connected device offers, read from its descriptions at runtime.\n",
+    "//! Every request file has a marker type per action, e.g. `GetInfo` for `generate_get_info_request`,\n",
+    "//! to ask `Capabilities::supports` about it.\n",
+    include_str!("capabilities_module/capabilities.rs"),
+    include_str!("capabilities_module/without_version.rs"),
+);
+
+/// Returns the capabilities module, which uses `<prefix>url.rs`.
+pub fn capabilities_module(prefix: &str) -> String {
+    CODE.replace("PREFIX_", prefix)
+}
+
+/// Returns the name of the marker type of an action with the response struct `response_struct`,
+/// e.g. `GetInfo` for `GetInfoResponse`. It lives in the request file, so it can't collide with
+/// the response struct.
+pub fn marker_type(response_struct: &str) -> &str {
+    match response_struct.strip_suffix("Response") {
+        Some(name) if !name.is_empty() => name,
+        _ => response_struct,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::marker_type;
+    use crate::api_handling::description_set::fixtures::{action, scpd, set, variable};
+
+    #[allow(non_snake_case)]
+    mod PREFIX_url {
+        pub use crate::api_handling::url::resolve;
+    }
+
+    /// The shipped module, with `PREFIX_url` as its URL module.
+    #[allow(dead_code, clippy::all)]
+    mod capabilities {
+        include!("capabilities_module/capabilities.rs");
+        include!("capabilities_module/without_version.rs");
+
+        #[test]
+        fn without_versions() {
+            assert_eq!(
+                without_version("urn:dslforum-org:service:Hosts:1"),
+                "urn:dslforum-org:service:Hosts"
+            );
+            assert_eq!(
+                without_version("urn:dslforum-org:service:Hosts"),
+                "urn:dslforum-org:service:Hosts"
+            );
+            assert_eq!(
+                without_version("urn:dslforum-org:service:X_AVM-DE_OnTel:v1"),
+                "urn:dslforum-org:service:X_AVM-DE_OnTel:v1"
+            );
+            assert_eq!(without_version("Hosts"), "Hosts");
+        }
+    }
+
+    struct GetSpecificHostEntry;
+
+    impl capabilities::Action for GetSpecificHostEntry {
+        const SERVICE_TYPE: &'static str = "urn:dslforum-org:service:Hosts:2";
+        const NAME: &'static str = "GetSpecificHostEntry";
+    }
+
+    #[test]
+    fn fetch() {
+        let hosts = scpd(
+            &action(
+                "GetSpecificHostEntry",
+                &[("NewMACAddress", "in", "MACAddress")],
+            ),
+            &variable("MACAddress", "string", &[]),
+        );
+        let set = set(&[("Hosts", &hosts)]);
+        let found = capabilities::Capabilities::fetch(
+            "http://fritz.box:49000/tr64desc.xml",
+            |url| match url {
+                "http://fritz.box:49000/tr64desc.xml" => Ok(set.description_xml.clone()),
+                "http://fritz.box:49000/Hosts.xml" => Ok(set.services[0].scpd_xml.clone()),
+                _ => Err(format!("unexpected {}", url)),
+            },
+        )
+        .unwrap();
+        assert!(found.supports::<GetSpecificHostEntry>());
+        assert!(found.supports_action("urn:dslforum-org:service:Hosts", "GetSpecificHostEntry"));
+        assert!(
+            !found.supports_action("urn:dslforum-org:service:Hosts:1", "GetHostNumberOfEntries")
+        );
+        assert!(found.supports_service("urn:dslforum-org:service:Hosts:1"));
+        assert!(!found.supports_service("urn:dslforum-org:service:DeviceInfo:1"));
+    }
+
+    #[test]
+    fn marker_types() {
+        assert_eq!(marker_type("GetInfoResponse"), "GetInfo");
+        assert_eq!(marker_type("GetInfo"), "GetInfo");
+        assert_eq!(marker_type("Response"), "Response");
+        assert_eq!(marker_type("ResponseResponse"), "Response");
+    }
+}
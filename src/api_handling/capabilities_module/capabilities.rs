@@ -0,0 +1,137 @@
+
+use std::collections::HashMap;
+use std::error::Error;
+
+/// An action of a service, implemented by the marker types of the request files.
+pub trait Action {
+    /// Service type the bindings were generated from, e.g. `urn:dslforum-org:service:Hosts:1`.
+    const SERVICE_TYPE: &'static str;
+    /// Name of the action, e.g. `GetSpecificHostEntry`.
+    const NAME: &'static str;
+}
+
+/// The services and actions of a device.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Capabilities {
+    /// Action names by service type without version, e.g. `urn:dslforum-org:service:Hosts`.
+    actions: HashMap<String, Vec<String>>,
+}
+
+impl Capabilities {
+    /// Reads the root description at `location` and the SCPDs of all its services with `get`,
+    /// which returns the body of a URL, e.g. using the HTTP client sending the requests.
+    pub fn fetch<F, E>(location: &str, mut get: F) -> Result<Capabilities, Box<dyn Error>>
+    where
+        F: FnMut(&str) -> Result<String, E>,
+        E: Into<Box<dyn Error>>,
+    {
+        let description: Root = serde_xml_rs::from_str(&get(location).map_err(Into::into)?)
+            .map_err(|e| format!("parsing {} failed: {}", location, e))?;
+        let base = if description.url_base.is_empty() {
+            location
+        } else {
+            description.url_base.as_str()
+        };
+        let mut services = vec![];
+        collect_services(&description.device, &mut services);
+
+        let mut capabilities = Capabilities::default();
+        for service in services {
+            let url = super::PREFIX_url::resolve(base, &service.scpd_url);
+            let scpd: Scpd = serde_xml_rs::from_str(&get(&url).map_err(Into::into)?)
+                .map_err(|e| format!("parsing {} failed: {}", url, e))?;
+            let actions = capabilities
+                .actions
+                .entry(without_version(&service.service_type).to_string())
+                .or_default();
+            for action in scpd.action_list.action {
+                if !actions.contains(&action.name) {
+                    actions.push(action.name);
+                }
+            }
+        }
+        Ok(capabilities)
+    }
+
+    /// Whether the device offers the action `A`, e.g. `capabilities.supports::<GetInfo>()`.
+    /// Any version of the service counts, later versions keep the actions of earlier ones.
+    pub fn supports<A: Action>(&self) -> bool {
+        self.supports_action(A::SERVICE_TYPE, A::NAME)
+    }
+
+    /// Whether the device offers the action `action` of the service `service_type`.
+    pub fn supports_action(&self, service_type: &str, action: &str) -> bool {
+        self.actions
+            .get(without_version(service_type))
+            .map_or(false, |actions| actions.iter().any(|known| known == action))
+    }
+
+    /// Whether the device offers the service `service_type`, in any version.
+    pub fn supports_service(&self, service_type: &str) -> bool {
+        self.actions.contains_key(without_version(service_type))
+    }
+}
+
+/// Collects the services of `device` and all contained devices.
+fn collect_services<'a>(device: &'a Device, services: &mut Vec<&'a Service>) {
+    services.extend(device.service_list.service.iter());
+    for device in &device.device_list.device {
+        collect_services(device, services);
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Root {
+    #[serde(rename = "URLBase")]
+    #[serde(default)]
+    url_base: String,
+    device: Device,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Device {
+    #[serde(rename = "serviceList")]
+    #[serde(default)]
+    service_list: ServiceList,
+    #[serde(rename = "deviceList")]
+    #[serde(default)]
+    device_list: DeviceList,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct DeviceList {
+    #[serde(default)]
+    device: Vec<Device>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ServiceList {
+    #[serde(default)]
+    service: Vec<Service>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Service {
+    #[serde(rename = "serviceType")]
+    service_type: String,
+    #[serde(rename = "SCPDURL")]
+    scpd_url: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Scpd {
+    #[serde(rename = "actionList")]
+    #[serde(default)]
+    action_list: ActionList,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ActionList {
+    #[serde(default)]
+    action: Vec<ScpdAction>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct ScpdAction {
+    name: String,
+}
@@ -0,0 +1,9 @@
+
+/// Returns `service_type` without the version, e.g. `urn:dslforum-org:service:Hosts` for
+/// `urn:dslforum-org:service:Hosts:1`.
+fn without_version(service_type: &str) -> &str {
+    match service_type.rsplit_once(':') {
+        Some((name, version)) if version.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => service_type,
+    }
+}
@@ -0,0 +1,311 @@
+//! A root description with the SCPDs of all its services, fetched from a box or loaded from a
+//! snapshot folder, e.g. to compare two firmware versions.
+
+use crate::api_handling::api_desc_dir::{parse_scpd, ApiDescDir, ServiceDescription};
+use crate::api_handling::cache::file_name;
+use crate::api_handling::fetch::Fetcher;
+use crate::api_handling::filter::Filter;
+use crate::api_handling::url::origin;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Base URL the URLs of a snapshot are resolved against if it has no `URLBase`. The SCPDs of a
+/// snapshot are found by the path of their URL, so only the path of the base matters.
+const SNAPSHOT_BASE: &str = "http://snapshot/";
+
+/// A root description and the SCPDs of all its services.
+#[derive(Debug)]
+pub struct DescriptionSet {
+    /// The URL or the file the root description was read from.
+    pub source: String,
+    pub description: ApiDescDir,
+    /// The root description as fetched.
+    pub description_xml: String,
+    pub services: Vec<ServiceDescription>,
+}
+
+impl DescriptionSet {
+    /// Fetches the root description at `location` and the SCPDs of all its services, with the
+    /// services and actions `filter` selects.
+    pub fn fetch(
+        fetcher: &Fetcher,
+        location: &str,
+        filter: &Filter,
+    ) -> Result<Self, Box<dyn Error>> {
+        let description_xml = fetcher.get_text(location)?;
+        let description = parse_description(location, &description_xml)?;
+        let services = filter.select(description.fetch_services(fetcher, location, filter)?);
+        Ok(DescriptionSet {
+            source: location.to_string(),
+            description,
+            description_xml,
+            services,
+        })
+    }
+
+    /// Loads a snapshot written by `save`, `path` is the root description file in the snapshot
+    /// folder. Like `fetch`, only with the services and actions `filter` selects.
+    pub fn load(path: &str, filter: &Filter) -> Result<Self, Box<dyn Error>> {
+        let description_xml =
+            fs::read_to_string(path).map_err(|e| format!("reading {} failed: {}", path, e))?;
+        let description = parse_description(path, &description_xml)?;
+        let folder = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+        let mut services = vec![];
+        for service in description.services(description.base(SNAPSHOT_BASE), filter) {
+            let file = folder.join(snapshot_path(&service.scpd_url));
+            let file = file.to_string_lossy();
+            let scpd_xml = fs::read_to_string(file.as_ref())
+                .map_err(|e| format!("reading {} failed: {}", file, e))?;
+            let scpd = parse_scpd(&file, &scpd_xml)?;
+            services.push(ServiceDescription {
+                service,
+                scpd,
+                scpd_xml,
+            });
+        }
+        Ok(DescriptionSet {
+            source: path.to_string(),
+            description,
+            description_xml,
+            services: filter.select(services),
+        })
+    }
+
+    /// Fetches `source` if it is an `http` or `https` URL, otherwise loads the snapshot with the
+    /// root description file `source`.
+    pub fn open(fetcher: &Fetcher, source: &str, filter: &Filter) -> Result<Self, Box<dyn Error>> {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            DescriptionSet::fetch(fetcher, source, filter)
+        } else {
+            DescriptionSet::load(source, filter)
+        }
+    }
+
+    /// Writes the set into `folder` as a snapshot: the root description under the file name of
+    /// its source, e.g. `tr64desc.xml`, and every SCPD under the path of its URL.
+    /// Returns the path of the root description file, which `load` takes.
+    pub fn save(&self, folder: &str) -> Result<String, Box<dyn Error>> {
+        let root_name = self
+            .source
+            .rsplit(['/', '\\'])
+            .next()
+            .filter(|name| !name.is_empty())
+            .unwrap_or("description.xml");
+        let root_path = Path::new(folder).join(file_name(root_name));
+        write(&root_path, &self.description_xml)?;
+        for service in &self.services {
+            let path = Path::new(folder).join(snapshot_path(&service.service.scpd_url));
+            write(&path, &service.scpd_xml)?;
+        }
+        Ok(root_path.to_string_lossy().into_owned())
+    }
+
+    /// Describes the set for reports, the firmware version if the description has one and the source.
+    pub fn label(&self) -> String {
+        if self.description.system_version.display.is_empty() {
+            self.source.clone()
+        } else {
+            format!(
+                "FRITZ!OS {} ({})",
+                self.description.system_version.display, self.source
+            )
+        }
+    }
+}
+
+fn parse_description(source: &str, xml: &str) -> Result<ApiDescDir, String> {
+    serde_xml_rs::from_str(xml).map_err(|e| format!("parsing {} failed: {}", source, e))
+}
+
+fn write(path: &Path, content: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("creating {} failed: {}", parent.display(), e))?;
+    }
+    fs::write(path, content).map_err(|e| format!("writing {} failed: {}", path.display(), e))
+}
+
+/// Returns the path an SCPD is saved under in a snapshot folder: the path of its origin-relative
+/// URL, e.g. `deviceinfoSCPD.xml` for `/deviceinfoSCPD.xml`. SCPDs on other hosts are saved in a
+/// folder named after their origin. Query, fragment, `.` and `..` segments are left out.
+fn snapshot_path(url: &str) -> PathBuf {
+    let mut result = PathBuf::new();
+    let mut path = url;
+    if let Some(url_origin) = origin(url) {
+        result.push(file_name(url_origin));
+        path = &url[url_origin.len()..];
+    }
+    let path = &path[..path.find(['?', '#']).unwrap_or(path.len())];
+    for segment in path.split('/') {
+        if !segment.is_empty() && segment != "." && segment != ".." {
+            result.push(file_name(segment));
+        }
+    }
+    result
+}
+
+/// Description sets built from inline XML for tests.
+#[cfg(test)]
+pub mod fixtures {
+    use super::*;
+
+    const DESCRIPTION: &str = r#"<root><specVersion><major>1</major><minor>0</minor></specVersion>VERSION
+<device><deviceType>urn:dslforum-org:device:InternetGatewayDevice:1</deviceType>
+<friendlyName>Box</friendlyName><manufacturer>AVM</manufacturer><manufacturerURL>x</manufacturerURL>
+<modelDescription>Box</modelDescription><modelName>MODEL</modelName><modelNumber>-</modelNumber>
+<modelURL>x</modelURL><UDN>uuid:1</UDN>
+<serviceList>SERVICES</serviceList></device></root>"#;
+
+    /// `NAME` is replaced with the service name.
+    const SERVICE: &str = "<service><serviceType>urn:dslforum-org:service:NAME:1</serviceType>
+<serviceId>urn:NAME-com:serviceId:NAME1</serviceId><controlURL>/upnp/control/NAME</controlURL>
+<eventSubURL>/upnp/control/NAME</eventSubURL><SCPDURL>/NAME.xml</SCPDURL></service>";
+
+    /// `NAME` is replaced with the device type, `SERVICES` and `DEVICES` with its services and
+    /// embedded devices.
+    const DEVICE: &str = "<device><deviceType>urn:dslforum-org:device:NAME:1</deviceType>
+<friendlyName>Box</friendlyName><manufacturer>AVM</manufacturer><manufacturerURL>x</manufacturerURL>
+<modelDescription>Box</modelDescription><modelName>Box</modelName><modelNumber>-</modelNumber>
+<modelURL>x</modelURL><UDN>uuid:NAME</UDN>
+<serviceList>SERVICES</serviceList>DEVICES</device>";
+
+    /// A device of the type `name` with the services `(name, control URL)` and the embedded
+    /// `devices`, see `description`. Names may have a version, e.g. `WLANConfiguration:2`.
+    pub fn device(name: &str, services: &[(&str, &str)], devices: &[String]) -> String {
+        let service_list: String = services
+            .iter()
+            .map(|(service, control_url)| {
+                let (service, version) = service.split_once(':').unwrap_or((service, "1"));
+                SERVICE
+                    .replace("NAME:1", &format!("{}:{}", service, version))
+                    .replace("NAME", service)
+                    .replace(&format!("/upnp/control/{}", service), control_url)
+            })
+            .collect();
+        let device_list = if devices.is_empty() {
+            String::new()
+        } else {
+            format!("<deviceList>{}</deviceList>", devices.concat())
+        };
+        DEVICE
+            .replace("NAME", name)
+            .replace("SERVICES", &service_list)
+            .replace("DEVICES", &device_list)
+    }
+
+    /// A root description with the root device `device`.
+    pub fn description(device: &str) -> ApiDescDir {
+        let description_xml = format!(
+            "<root><specVersion><major>1</major><minor>0</minor></specVersion>{}</root>",
+            device
+        );
+        serde_xml_rs::from_str(&description_xml).unwrap()
+    }
+
+    /// A set of a box without firmware version with the services `(name, SCPD)`.
+    pub fn set(services: &[(&str, &str)]) -> DescriptionSet {
+        model_set("Box", "", services)
+    }
+
+    /// A set of the box `model` with the firmware `version`, e.g. `154.07.29`.
+    pub fn model_set(model: &str, version: &str, services: &[(&str, &str)]) -> DescriptionSet {
+        let system_version = if version.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "<systemVersion><HW>1</HW><Major>1</Major><Minor>1</Minor><Patch>1</Patch>\
+                 <Buildnumber>1</Buildnumber><Display>{}</Display></systemVersion>",
+                version
+            )
+        };
+        let service_list: String = services
+            .iter()
+            .map(|(name, _)| SERVICE.replace("NAME", name))
+            .collect();
+        let description_xml = DESCRIPTION
+            .replace("VERSION", &system_version)
+            .replace("MODEL", model)
+            .replace("SERVICES", &service_list);
+        let description: ApiDescDir = serde_xml_rs::from_str(&description_xml).unwrap();
+        let services = description
+            .services("http://fritz.box:49000/tr64desc.xml", &Filter::new())
+            .into_iter()
+            .zip(services)
+            .map(|(service, (_, scpd_xml))| ServiceDescription {
+                scpd: parse_scpd(&service.scpd_url, scpd_xml).unwrap(),
+                scpd_xml: scpd_xml.to_string(),
+                service,
+            })
+            .collect();
+        DescriptionSet {
+            source: format!("{}.xml", model),
+            description,
+            description_xml,
+            services,
+        }
+    }
+
+    pub fn scpd(actions: &str, variables: &str) -> String {
+        format!(
+            "<scpd><specVersion><major>1</major><minor>0</minor></specVersion>\
+             <actionList>{}</actionList><serviceStateTable>{}</serviceStateTable></scpd>",
+            actions, variables
+        )
+    }
+
+    pub fn action(name: &str, arguments: &[(&str, &str, &str)]) -> String {
+        let mut argument_list: String = arguments
+            .iter()
+            .map(|(name, direction, variable)| {
+                format!(
+                    "<argument><name>{}</name><direction>{}</direction>\
+                     <relatedStateVariable>{}</relatedStateVariable></argument>",
+                    name, direction, variable
+                )
+            })
+            .collect();
+        if !argument_list.is_empty() {
+            argument_list = format!("<argumentList>{}</argumentList>", argument_list);
+        }
+        format!("<action><name>{}</name>{}</action>", name, argument_list)
+    }
+
+    pub fn variable(name: &str, data_type: &str, allowed_values: &[&str]) -> String {
+        let mut allowed_value_list: String = allowed_values
+            .iter()
+            .map(|value| format!("<allowedValue>{}</allowedValue>", value))
+            .collect();
+        if !allowed_value_list.is_empty() {
+            allowed_value_list = format!(
+                "<allowedValueList>{}</allowedValueList>",
+                allowed_value_list
+            );
+        }
+        format!(
+            "<stateVariable><name>{}</name><dataType>{}</dataType>{}</stateVariable>",
+            name, data_type, allowed_value_list
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_paths() {
+        assert_eq!(
+            snapshot_path("/deviceinfoSCPD.xml"),
+            PathBuf::from("deviceinfoSCPD.xml")
+        );
+        assert_eq!(
+            snapshot_path("/upnp/../scpd/igd.xml?x=1"),
+            PathBuf::from("upnp/scpd/igd.xml")
+        );
+        assert_eq!(
+            snapshot_path("http://10.0.0.1:5000/ctl/IPConn.xml"),
+            PathBuf::from("http___10.0.0.1_5000/ctl/IPConn.xml")
+        );
+    }
+}
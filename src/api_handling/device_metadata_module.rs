@@ -0,0 +1,223 @@
+//! Source code of the optional `<prefix>device_metadata.rs` module written next to the request files.
+//! It has the data of the root device description as constants and only uses `std`.
+
+use crate::api_handling::api_desc_dir::{ApiDescDir, Device, Icon};
+
+/// Documentation, always the start of the module.
+const HEADER: &str =
+    "//! Data of the device the bindings were generated from, e.g. to check that they match the
+//! connected box: compare `MODEL_NAME` and `FIRMWARE_VERSION` with `NewModelName` and
+//! `NewSoftwareVersion` of `GetInfo` of the `DeviceInfo` service.
+
+";
+
+/// Everything after the `ICONS` list. `ICONS_FOLDER` is replaced with the folder of the icons.
+const CODE: &str = "
+/// An icon of the device or one of its embedded devices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Icon {
+    pub mimetype: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    /// URL of the icon, usually relative to the base URL of the device.
+    pub url: &'static str,
+    /// Name of the icon in the `ICONS_FOLDER` folder of the output, if the generator downloaded the icons.
+    pub file: &'static str,
+}
+";
+
+/// Returns the device metadata module of `description`, with the icons saved in `icons_folder`.
+pub fn device_metadata_module(description: &ApiDescDir, icons_folder: &str) -> String {
+    let device = &description.device;
+    let mut result = HEADER.to_string();
+    result.push_str(&string_constants(&[
+        (
+            "`deviceType` of the root device.",
+            "DEVICE_TYPE",
+            device.device_type.as_str(),
+        ),
+        (
+            "`friendlyName` of the root device.",
+            "FRIENDLY_NAME",
+            device.friendly_name.as_str(),
+        ),
+        (
+            "`manufacturer` of the root device.",
+            "MANUFACTURER",
+            device.manufacturer.as_str(),
+        ),
+        (
+            "`manufacturerURL` of the root device.",
+            "MANUFACTURER_URL",
+            device.manufacturer_url.as_str(),
+        ),
+        (
+            "`modelName` of the root device.",
+            "MODEL_NAME",
+            device.model_name.as_str(),
+        ),
+        (
+            "`modelNumber` of the root device.",
+            "MODEL_NUMBER",
+            device.model_number.as_str(),
+        ),
+        (
+            "`modelDescription` of the root device.",
+            "MODEL_DESCRIPTION",
+            device.model_description.as_str(),
+        ),
+        (
+            "`modelURL` of the root device.",
+            "MODEL_URL",
+            device.model_url.as_str(),
+        ),
+        (
+            "`UDN` of the root device, it differs between boxes of the same model.",
+            "UDN",
+            device.udn.as_str(),
+        ),
+        (
+            "`presentationURL` of the root device, empty if there is none.",
+            "PRESENTATION_URL",
+            device.presentation_url.as_str(),
+        ),
+        (
+            "Firmware version, e.g. `154.07.29`, empty if the description doesn't state it.",
+            "FIRMWARE_VERSION",
+            description.system_version.display.as_str(),
+        ),
+    ]));
+
+    result.push_str(
+        "\n/// Icons listed in the description, see `Icon`.\npub const ICONS: &[Icon] = &[\n",
+    );
+    for (file, icon) in icon_files(device) {
+        result.push_str(&format!(
+            "    Icon {{\n        mimetype: {:?},\n        width: {},\n        height: {},\n        \
+             depth: {},\n        url: {:?},\n        file: {:?},\n    }},\n",
+            icon.mimetype, icon.width, icon.height, icon.depth, icon.url, file
+        ));
+    }
+    result.push_str("];\n");
+    result.push_str(&CODE.replace("ICONS_FOLDER", icons_folder));
+    result
+}
+
+/// Returns a documented `&str` constant per `(documentation, name, value)`, e.g.
+/// `/// \`UDN\` of the device.\npub const UDN: &str = "uuid:1";`.
+pub fn string_constants(constants: &[(&str, &str, &str)]) -> String {
+    constants
+        .iter()
+        .map(|(documentation, name, value)| {
+            format!(
+                "/// {}\npub const {}: &str = {:?};\n",
+                documentation, name, value
+            )
+        })
+        .collect()
+}
+
+/// Returns the icons of `device` and all contained devices with the names to save them under,
+/// the last segment of their URL. Icons with the same URL are listed once, names used twice are
+/// numbered, e.g. `icon_2.png`.
+pub fn icon_files(device: &Device) -> Vec<(String, &Icon)> {
+    let mut icons: Vec<&Icon> = vec![];
+    collect_icons(device, &mut icons);
+    let mut files: Vec<(String, &Icon)> = vec![];
+    for icon in icons {
+        if files.iter().any(|(_, known)| known.url == icon.url) {
+            continue;
+        }
+        let path = &icon.url[..icon.url.find(['?', '#']).unwrap_or(icon.url.len())];
+        let name: String = path
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let name = match name.trim_start_matches('.') {
+            "" => "icon".to_string(),
+            name => name.to_string(),
+        };
+        let (stem, extension) = match name.rfind('.') {
+            Some(position) => name.split_at(position),
+            None => (name.as_str(), ""),
+        };
+        let mut file = name.clone();
+        let mut number = 1;
+        while files.iter().any(|(known, _)| *known == file) {
+            number += 1;
+            file = format!("{}_{}{}", stem, number, extension);
+        }
+        files.push((file, icon));
+    }
+    files
+}
+
+fn collect_icons<'a>(device: &'a Device, icons: &mut Vec<&'a Icon>) {
+    icons.extend(device.icon_list.icon.iter());
+    for device in &device.device_list.device {
+        collect_icons(device, icons);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_handling::description_set::fixtures::{description, device};
+
+    fn icon(mimetype: &str, size: u32, depth: u32, url: &str) -> Icon {
+        Icon {
+            mimetype: mimetype.to_string(),
+            width: size,
+            height: size,
+            depth,
+            url: url.to_string(),
+        }
+    }
+
+    #[test]
+    fn constants_and_icons() {
+        let lan_device = device("LANDevice", &[("Hosts", "/upnp/control/hosts")], &[]);
+        let mut description = description(&device(
+            "InternetGatewayDevice",
+            &[("Hosts", "/upnp/control/hosts")],
+            &[lan_device],
+        ));
+        description.system_version.display = "154.07.29".to_string();
+        let root = &mut description.device;
+        root.friendly_name = "FRITZ!Box \"Home\"".to_string();
+        root.presentation_url = "http://fritz.box".to_string();
+        root.icon_list.icon = vec![
+            icon("image/gif", 118, 8, "/ligd.gif"),
+            icon("image/png", 48, 24, "/icons/lan/ligd.gif?size=48"),
+        ];
+        root.device_list.device[0].icon_list.icon = vec![
+            icon("image/gif", 118, 8, "/ligd.gif"),
+            icon("image/png", 16, 8, "/"),
+        ];
+
+        let files: Vec<String> = icon_files(&description.device)
+            .into_iter()
+            .map(|(file, _)| file)
+            .collect();
+        assert_eq!(files, vec!["ligd.gif", "ligd_2.gif", "icon"]);
+
+        let module = device_metadata_module(&description, "tr064_icons");
+        assert!(module.contains("pub const FRIENDLY_NAME: &str = \"FRITZ!Box \\\"Home\\\"\";"));
+        assert!(module.contains("pub const FIRMWARE_VERSION: &str = \"154.07.29\";"));
+        assert!(module.contains("pub const PRESENTATION_URL: &str = \"http://fritz.box\";"));
+        assert!(
+            module.contains("url: \"/icons/lan/ligd.gif?size=48\",\n        file: \"ligd_2.gif\",")
+        );
+        assert!(module.contains("in the `tr064_icons` folder"));
+    }
+}
@@ -0,0 +1,603 @@
+//! Semantic comparison of two description sets, e.g. of the same box before and after a firmware
+//! update, with the generated items each change breaks.
+
+use crate::api_handling::api_desc::{rust_type, Action, Argument};
+use crate::api_handling::api_desc_dir::ServiceDescription;
+use crate::api_handling::description_set::DescriptionSet;
+use crate::api_handling::naming::{
+    action_item, argument_item, assign_names, service_item, Names, NamingError,
+};
+use crate::api_handling::options::GeneratorOptions;
+use std::fmt;
+
+/// What changed about a service, action or argument.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    ServiceAdded,
+    ServiceRemoved,
+    ActionAdded,
+    ActionRemoved,
+    /// `new` is the direction of the argument.
+    ArgumentAdded,
+    /// `old` is the direction of the argument.
+    ArgumentRemoved,
+    ArgumentDirectionChanged,
+    /// The `in` arguments, and so the parameters of the request function, have another order.
+    ArgumentsReordered,
+    /// The data type of the related state variable changed.
+    DataTypeChanged,
+    /// The allowed values of the related state variable changed.
+    AllowedValuesChanged,
+}
+
+/// A change between two description sets.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    /// Item path of the service, action or argument, see `naming::argument_item`.
+    pub item: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<String>,
+    /// Generated items, named as generated from the old set, which are gone or have another
+    /// type or signature when generating from the new set. Empty if the change is compatible.
+    pub breaks: Vec<String>,
+}
+
+/// The changes from one description set to another.
+#[derive(Debug, Serialize)]
+pub struct ApiDiff {
+    pub old: String,
+    pub new: String,
+    pub changes: Vec<Change>,
+}
+
+impl ApiDiff {
+    /// Compares `old` with `new`. The generated items are named according to `options`.
+    /// Services are matched by their service type without version, actions and arguments by name.
+    pub fn new(
+        old: &DescriptionSet,
+        new: &DescriptionSet,
+        options: &GeneratorOptions,
+    ) -> Result<Self, NamingError> {
+        let names = assign_names(&old.services, &options.name_overrides, &[])?;
+        let comparison = Comparison {
+            names: &names,
+            options,
+        };
+        let mut changes = vec![];
+        let old_services = keyed(&old.services);
+        let new_services = keyed(&new.services);
+        for (key, old_service) in &old_services {
+            match new_services.iter().find(|(new_key, _)| new_key == key) {
+                Some((_, new_service)) => {
+                    comparison.compare_services(old_service, new_service, &mut changes)
+                }
+                None => changes.push(Change {
+                    kind: ChangeKind::ServiceRemoved,
+                    item: key.clone(),
+                    old: None,
+                    new: None,
+                    breaks: vec![format!("module {}", names.module(&old_service.service))],
+                }),
+            }
+        }
+        for (key, _) in &new_services {
+            if !old_services.iter().any(|(old_key, _)| old_key == key) {
+                changes.push(Change {
+                    kind: ChangeKind::ServiceAdded,
+                    item: key.clone(),
+                    old: None,
+                    new: None,
+                    breaks: vec![],
+                });
+            }
+        }
+        Ok(ApiDiff {
+            old: old.label(),
+            new: new.label(),
+            changes,
+        })
+    }
+
+    /// Number of changes which break generated items.
+    pub fn breaking(&self) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| !change.breaks.is_empty())
+            .count()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("the diff consists of strings")
+    }
+}
+
+impl fmt::Display for ApiDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Changes from {} to {}:", self.old, self.new)?;
+        for change in &self.changes {
+            let values = || {
+                format!(
+                    "{} -> {}",
+                    change.old.as_deref().unwrap_or_default(),
+                    change.new.as_deref().unwrap_or_default()
+                )
+            };
+            match change.kind {
+                ChangeKind::ServiceAdded => writeln!(f, "+ service {}", change.item)?,
+                ChangeKind::ServiceRemoved => writeln!(f, "- service {}", change.item)?,
+                ChangeKind::ActionAdded => writeln!(f, "+ action {}", change.item)?,
+                ChangeKind::ActionRemoved => writeln!(f, "- action {}", change.item)?,
+                ChangeKind::ArgumentAdded => writeln!(
+                    f,
+                    "+ {} argument {}",
+                    change.new.as_deref().unwrap_or_default(),
+                    change.item
+                )?,
+                ChangeKind::ArgumentRemoved => writeln!(
+                    f,
+                    "- {} argument {}",
+                    change.old.as_deref().unwrap_or_default(),
+                    change.item
+                )?,
+                ChangeKind::ArgumentDirectionChanged => {
+                    writeln!(f, "~ direction of {}: {}", change.item, values())?
+                }
+                ChangeKind::ArgumentsReordered => writeln!(
+                    f,
+                    "~ order of the in arguments of {}: {}",
+                    change.item,
+                    values()
+                )?,
+                ChangeKind::DataTypeChanged => {
+                    writeln!(f, "~ data type of {}: {}", change.item, values())?
+                }
+                ChangeKind::AllowedValuesChanged => {
+                    writeln!(f, "~ allowed values of {}: {}", change.item, values())?
+                }
+            }
+            for item in &change.breaks {
+                writeln!(f, "    breaks {}", item)?;
+            }
+        }
+        write!(
+            f,
+            "{} changes, {} breaking",
+            self.changes.len(),
+            self.breaking()
+        )
+    }
+}
+
+/// Returns the services keyed by their item path. Services sharing an item path, which only
+/// happens if their versions have different SCPDs, are told apart by the SCPD URL.
+fn keyed(services: &[ServiceDescription]) -> Vec<(String, &ServiceDescription)> {
+    services
+        .iter()
+        .map(|description| {
+            let item = service_item(&description.service);
+            let shared = services
+                .iter()
+                .filter(|other| service_item(&other.service) == item)
+                .count()
+                > 1;
+            if shared {
+                (
+                    format!("{} ({})", item, description.service.scpd_url),
+                    description,
+                )
+            } else {
+                (item, description)
+            }
+        })
+        .collect()
+}
+
+/// Compares services of the old and the new set, naming broken items after the old set.
+struct Comparison<'a> {
+    names: &'a Names,
+    options: &'a GeneratorOptions,
+}
+
+impl Comparison<'_> {
+    fn compare_services(
+        &self,
+        old: &ServiceDescription,
+        new: &ServiceDescription,
+        changes: &mut Vec<Change>,
+    ) {
+        for old_action in &old.scpd.action_list.action {
+            match new
+                .scpd
+                .action_list
+                .action
+                .iter()
+                .find(|action| action.name == old_action.name)
+            {
+                Some(new_action) => self.compare_actions(old, old_action, new, new_action, changes),
+                None => changes.push(Change {
+                    kind: ChangeKind::ActionRemoved,
+                    item: action_item(&old.service, old_action),
+                    old: None,
+                    new: None,
+                    breaks: vec![
+                        self.function(old, old_action),
+                        self.response_struct(old, old_action),
+                    ],
+                }),
+            }
+        }
+        for new_action in &new.scpd.action_list.action {
+            if !old
+                .scpd
+                .action_list
+                .action
+                .iter()
+                .any(|action| action.name == new_action.name)
+            {
+                changes.push(Change {
+                    kind: ChangeKind::ActionAdded,
+                    item: action_item(&old.service, new_action),
+                    old: None,
+                    new: None,
+                    breaks: vec![],
+                });
+            }
+        }
+    }
+
+    fn compare_actions(
+        &self,
+        old: &ServiceDescription,
+        old_action: &Action,
+        new: &ServiceDescription,
+        new_action: &Action,
+        changes: &mut Vec<Change>,
+    ) {
+        let old_arguments = &old_action.argument_list.argument;
+        let new_arguments = &new_action.argument_list.argument;
+        for old_argument in old_arguments {
+            let item = argument_item(&old.service, old_action, old_argument);
+            let new_argument = match new_arguments
+                .iter()
+                .find(|argument| argument.name == old_argument.name)
+            {
+                Some(new_argument) => new_argument,
+                None => {
+                    changes.push(Change {
+                        kind: ChangeKind::ArgumentRemoved,
+                        item,
+                        old: Some(old_argument.direction.clone()),
+                        new: None,
+                        breaks: vec![self.generated_argument(old, old_action, old_argument)],
+                    });
+                    continue;
+                }
+            };
+            if old_argument.direction != new_argument.direction {
+                changes.push(Change {
+                    kind: ChangeKind::ArgumentDirectionChanged,
+                    item,
+                    old: Some(old_argument.direction.clone()),
+                    new: Some(new_argument.direction.clone()),
+                    breaks: vec![self.generated_argument(old, old_action, old_argument)],
+                });
+                continue;
+            }
+
+            let old_variable = old
+                .scpd
+                .state_variable(&old_argument.related_state_variable);
+            let new_variable = new
+                .scpd
+                .state_variable(&new_argument.related_state_variable);
+            let old_type = old_variable
+                .map(|v| v.data_type.as_str())
+                .unwrap_or_default();
+            let new_type = new_variable
+                .map(|v| v.data_type.as_str())
+                .unwrap_or_default();
+            if old_type != new_type {
+                let breaks = if self.rust_type(old_type) == self.rust_type(new_type) {
+                    vec![]
+                } else {
+                    vec![self.generated_argument(old, old_action, old_argument)]
+                };
+                changes.push(Change {
+                    kind: ChangeKind::DataTypeChanged,
+                    item: item.clone(),
+                    old: Some(old_type.to_string()),
+                    new: Some(new_type.to_string()),
+                    breaks,
+                });
+            }
+            let old_values = old_variable
+                .map(|v| v.allowed_value_list.allowed_value.join(", "))
+                .unwrap_or_default();
+            let new_values = new_variable
+                .map(|v| v.allowed_value_list.allowed_value.join(", "))
+                .unwrap_or_default();
+            if old_values != new_values {
+                changes.push(Change {
+                    kind: ChangeKind::AllowedValuesChanged,
+                    item,
+                    old: Some(old_values),
+                    new: Some(new_values),
+                    breaks: vec![],
+                });
+            }
+        }
+        for new_argument in new_arguments {
+            if !old_arguments
+                .iter()
+                .any(|argument| argument.name == new_argument.name)
+            {
+                // A new parameter changes the signature of the request function, a new field
+                // of the response struct doesn't break code using the struct.
+                let breaks = if new_argument.direction == "in" {
+                    vec![self.function(old, old_action)]
+                } else {
+                    vec![]
+                };
+                changes.push(Change {
+                    kind: ChangeKind::ArgumentAdded,
+                    item: argument_item(&old.service, old_action, new_argument),
+                    old: None,
+                    new: Some(new_argument.direction.clone()),
+                    breaks,
+                });
+            }
+        }
+
+        // Parameters are positional, so the order of the remaining `in` arguments matters.
+        let in_order = |arguments: &[Argument], others: &[Argument]| -> Vec<String> {
+            arguments
+                .iter()
+                .filter(|argument| {
+                    argument.direction == "in"
+                        && others.iter().any(|other| {
+                            other.name == argument.name && other.direction == argument.direction
+                        })
+                })
+                .map(|argument| argument.name.clone())
+                .collect()
+        };
+        let old_order = in_order(old_arguments, new_arguments);
+        let new_order = in_order(new_arguments, old_arguments);
+        if old_order != new_order {
+            changes.push(Change {
+                kind: ChangeKind::ArgumentsReordered,
+                item: action_item(&old.service, old_action),
+                old: Some(old_order.join(", ")),
+                new: Some(new_order.join(", ")),
+                breaks: vec![self.function(old, old_action)],
+            });
+        }
+    }
+
+    fn rust_type(&self, data_type: &str) -> Option<&'static str> {
+        rust_type(
+            data_type,
+            self.options.date_time_mapping,
+            self.options.uuid_mapping,
+        )
+    }
+
+    fn function(&self, service: &ServiceDescription, action: &Action) -> String {
+        format!(
+            "fn {}::generate_{}_request",
+            self.names.module(&service.service),
+            self.names.function(&service.service, action)
+        )
+    }
+
+    fn response_struct(&self, service: &ServiceDescription, action: &Action) -> String {
+        format!(
+            "struct {}::{}",
+            self.names.module(&service.service),
+            self.names.response_struct(&service.service, action)
+        )
+    }
+
+    /// The request function for `in` arguments, the response struct field for `out` arguments.
+    fn generated_argument(
+        &self,
+        service: &ServiceDescription,
+        action: &Action,
+        argument: &Argument,
+    ) -> String {
+        if argument.direction == "in" {
+            self.function(service, action)
+        } else {
+            format!(
+                "field {}::{}::{}",
+                self.names.module(&service.service),
+                self.names.response_struct(&service.service, action),
+                self.names.argument(&service.service, action, argument)
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_handling::description_set::fixtures::{action, scpd, set, variable};
+
+    #[test]
+    fn changes_and_breaks() {
+        let old_hosts = scpd(
+            &[
+                action(
+                    "GetSpecificHostEntry",
+                    &[
+                        ("NewMACAddress", "in", "MACAddress"),
+                        ("NewIPAddress", "out", "IPAddress"),
+                        ("NewLeaseTimeRemaining", "out", "LeaseTimeRemaining"),
+                        ("NewInterfaceType", "out", "InterfaceType"),
+                    ],
+                ),
+                action("GetChangeCounter", &[("NewX", "out", "ChangeCounter")]),
+            ]
+            .concat(),
+            &[
+                variable("MACAddress", "string", &[]),
+                variable("IPAddress", "string", &[]),
+                variable("LeaseTimeRemaining", "ui2", &[]),
+                variable("InterfaceType", "string", &["Ethernet", "802.11"]),
+                variable("ChangeCounter", "ui4", &[]),
+            ]
+            .concat(),
+        );
+        let new_hosts = scpd(
+            &[
+                action(
+                    "GetSpecificHostEntry",
+                    &[
+                        ("NewMACAddress", "in", "MACAddress"),
+                        ("NewLeaseTimeRemaining", "out", "LeaseTimeRemaining"),
+                        ("NewInterfaceType", "out", "InterfaceType"),
+                        ("NewActive", "out", "Active"),
+                    ],
+                ),
+                action("GetHostListPath", &[("NewPath", "out", "Path")]),
+            ]
+            .concat(),
+            &[
+                variable("MACAddress", "string", &[]),
+                variable("LeaseTimeRemaining", "ui4", &[]),
+                variable(
+                    "InterfaceType",
+                    "string",
+                    &["Ethernet", "802.11", "HomePlug"],
+                ),
+                variable("Active", "boolean", &[]),
+                variable("Path", "string", &[]),
+            ]
+            .concat(),
+        );
+        let time = scpd(
+            &action("GetInfo", &[("NewNTPServer1", "out", "NTPServer")]),
+            &variable("NTPServer", "string", &[]),
+        );
+        let old = set(&[("Hosts", &old_hosts), ("Time", &time)]);
+        let new = set(&[("Hosts", &new_hosts), ("WANPPPConnection", &time)]);
+
+        let diff = ApiDiff::new(&old, &new, &GeneratorOptions::new()).unwrap();
+        let hosts = "urn:dslforum-org:service:Hosts";
+        let summary: Vec<(ChangeKind, &str, Vec<&str>)> = diff
+            .changes
+            .iter()
+            .map(|change| {
+                (
+                    change.kind,
+                    change.item.as_str(),
+                    change.breaks.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    ChangeKind::ArgumentRemoved,
+                    &*format!("{}#GetSpecificHostEntry.NewIPAddress", hosts),
+                    vec!["field hosts::GetSpecificHostEntryResponse::new_ip_address"]
+                ),
+                (
+                    ChangeKind::DataTypeChanged,
+                    &*format!("{}#GetSpecificHostEntry.NewLeaseTimeRemaining", hosts),
+                    vec![]
+                ),
+                (
+                    ChangeKind::AllowedValuesChanged,
+                    &*format!("{}#GetSpecificHostEntry.NewInterfaceType", hosts),
+                    vec![]
+                ),
+                (
+                    ChangeKind::ArgumentAdded,
+                    &*format!("{}#GetSpecificHostEntry.NewActive", hosts),
+                    vec![]
+                ),
+                (
+                    ChangeKind::ActionRemoved,
+                    &*format!("{}#GetChangeCounter", hosts),
+                    vec![
+                        "fn hosts::generate_get_change_counter_request",
+                        "struct hosts::GetChangeCounterResponse"
+                    ]
+                ),
+                (
+                    ChangeKind::ActionAdded,
+                    &*format!("{}#GetHostListPath", hosts),
+                    vec![]
+                ),
+                (
+                    ChangeKind::ServiceRemoved,
+                    "urn:dslforum-org:service:Time",
+                    vec!["module time"]
+                ),
+                (
+                    ChangeKind::ServiceAdded,
+                    "urn:dslforum-org:service:WANPPPConnection",
+                    vec![]
+                ),
+            ]
+        );
+        assert_eq!(diff.breaking(), 3);
+        assert!(diff
+            .to_string()
+            .contains("~ data type of urn:dslforum-org:service:Hosts#GetSpecificHostEntry.NewLeaseTimeRemaining: ui2 -> ui4"));
+        assert!(diff.to_json().contains("\"kind\": \"service_removed\""));
+    }
+
+    #[test]
+    fn parameter_changes_break_the_request_function() {
+        let old = scpd(
+            &action(
+                "SetConfig",
+                &[
+                    ("NewA", "in", "A"),
+                    ("NewB", "in", "B"),
+                    ("NewC", "in", "A"),
+                ],
+            ),
+            &[variable("A", "string", &[]), variable("B", "ui2", &[])].concat(),
+        );
+        let new = scpd(
+            &action(
+                "SetConfig",
+                &[
+                    ("NewB", "in", "B"),
+                    ("NewA", "in", "A"),
+                    ("NewC", "out", "A"),
+                ],
+            ),
+            &[variable("A", "string", &[]), variable("B", "string", &[])].concat(),
+        );
+        let diff = ApiDiff::new(
+            &set(&[("Time", &old)]),
+            &set(&[("Time", &new)]),
+            &GeneratorOptions::new(),
+        )
+        .unwrap();
+        let kinds: Vec<ChangeKind> = diff.changes.iter().map(|change| change.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ChangeKind::DataTypeChanged,
+                ChangeKind::ArgumentDirectionChanged,
+                ChangeKind::ArgumentsReordered
+            ]
+        );
+        assert_eq!(diff.breaking(), 3);
+        assert_eq!(diff.changes[2].old.as_deref(), Some("NewA, NewB"));
+        assert_eq!(
+            diff.changes[2].breaks,
+            vec!["fn time::generate_set_config_request"]
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! HTTP digest access authentication (RFC 7616) as used by the FRITZ!Box, `MD5` and `SHA-256`
+//! with and without `-sess` and the `auth` quality of protection.
+
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// A `Digest` challenge from a `WWW-Authenticate` header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Challenge {
+    pub realm: String,
+    pub nonce: String,
+    pub opaque: Option<String>,
+    /// `MD5` if the challenge names no algorithm.
+    pub algorithm: String,
+    /// Whether the server offers the `auth` quality of protection.
+    pub qop_auth: bool,
+}
+
+impl Challenge {
+    /// Parses the `Digest` challenge of a `WWW-Authenticate` header value. Returns `None` for
+    /// other schemes and for challenges with unsupported algorithms or quality of protection.
+    pub fn parse(header: &str) -> Option<Self> {
+        let header = header.trim();
+        if header.len() < 6 || !header[..6].eq_ignore_ascii_case("digest") {
+            return None;
+        }
+        let mut challenge = Challenge {
+            realm: String::new(),
+            nonce: String::new(),
+            opaque: None,
+            algorithm: "MD5".to_string(),
+            qop_auth: false,
+        };
+        let mut qop = None;
+        for (name, value) in parameters(&header[6..]) {
+            match name.to_ascii_lowercase().as_str() {
+                "realm" => challenge.realm = value,
+                "nonce" => challenge.nonce = value,
+                "opaque" => challenge.opaque = Some(value),
+                "algorithm" => challenge.algorithm = value.to_ascii_uppercase(),
+                "qop" => qop = Some(value),
+                _ => {}
+            }
+        }
+        if let Some(qop) = qop {
+            challenge.qop_auth = qop.split(',').any(|option| option.trim() == "auth");
+            if !challenge.qop_auth {
+                return None;
+            }
+        }
+        match challenge.algorithm.as_str() {
+            "MD5" | "MD5-SESS" | "SHA-256" | "SHA-256-SESS" => {}
+            _ => return None,
+        }
+        if challenge.nonce.is_empty() {
+            return None;
+        }
+        Some(challenge)
+    }
+
+    /// Returns the `Authorization` header value for a `method` request of `uri` (path and query),
+    /// using a fresh client nonce.
+    pub fn authorization(&self, username: &str, password: &str, method: &str, uri: &str) -> String {
+        let mut bytes = [0u8; 8];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .expect("the system random number generator works");
+        let cnonce: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        self.authorization_with_cnonce(username, password, method, uri, &cnonce)
+    }
+
+    fn authorization_with_cnonce(
+        &self,
+        username: &str,
+        password: &str,
+        method: &str,
+        uri: &str,
+        cnonce: &str,
+    ) -> String {
+        let nc = "00000001";
+        let mut ha1 = self.hash(&format!("{}:{}:{}", username, self.realm, password));
+        if self.algorithm.ends_with("-SESS") {
+            ha1 = self.hash(&format!("{}:{}:{}", ha1, self.nonce, cnonce));
+        }
+        let ha2 = self.hash(&format!("{}:{}", method, uri));
+        let response = if self.qop_auth {
+            self.hash(&format!(
+                "{}:{}:{}:{}:auth:{}",
+                ha1, self.nonce, nc, cnonce, ha2
+            ))
+        } else {
+            self.hash(&format!("{}:{}:{}", ha1, self.nonce, ha2))
+        };
+
+        let mut result = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+            username, self.realm, self.nonce, uri, self.algorithm, response
+        );
+        if let Some(opaque) = &self.opaque {
+            result.push_str(format!(", opaque=\"{}\"", opaque).as_str());
+        }
+        if self.qop_auth {
+            result.push_str(format!(", qop=auth, nc={}, cnonce=\"{}\"", nc, cnonce).as_str());
+        }
+        result
+    }
+
+    /// Hashes `input` with the algorithm of the challenge and returns it in lower case hex.
+    fn hash(&self, input: &str) -> String {
+        if self.algorithm.starts_with("SHA-256") {
+            ring::digest::digest(&ring::digest::SHA256, input.as_bytes())
+                .as_ref()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect()
+        } else {
+            format!("{:x}", md5::compute(input))
+        }
+    }
+}
+
+/// Splits the parameters of a challenge, `name=value` or `name="quoted, value"`, separated by commas.
+fn parameters(input: &str) -> Vec<(String, String)> {
+    let mut result = vec![];
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| *c == ',' || c.is_whitespace()) {
+            chars.next();
+        }
+        let name: String = chars
+            .by_ref()
+            .take_while(|c| *c != '=')
+            .collect::<String>()
+            .trim()
+            .to_string();
+        if name.is_empty() {
+            break;
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next()),
+                    '"' => break,
+                    _ => value.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.peek() {
+                if *c == ',' {
+                    break;
+                }
+                value.push(*c);
+                chars.next();
+            }
+            value = value.trim().to_string();
+        }
+        result.push((name, value));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc_2617_example() {
+        let challenge = Challenge::parse(
+            "Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+             nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"",
+        )
+        .unwrap();
+        assert_eq!(challenge.realm, "testrealm@host.com");
+        assert!(challenge.qop_auth);
+        let authorization = challenge.authorization_with_cnonce(
+            "Mufasa",
+            "Circle Of Life",
+            "GET",
+            "/dir/index.html",
+            "0a4f113b",
+        );
+        assert!(authorization.contains("response=\"6629fae49393a05397450978507c4ef1\""));
+        assert!(authorization.contains("opaque=\"5ccc069c403ebaf9f0171e9517f40e41\""));
+        assert!(authorization.contains("qop=auth, nc=00000001, cnonce=\"0a4f113b\""));
+    }
+
+    #[test]
+    fn rfc_7616_sha_256_example() {
+        let challenge = Challenge::parse(
+            "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm=SHA-256, \
+             nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+             opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"",
+        )
+        .unwrap();
+        let authorization = challenge.authorization_with_cnonce(
+            "Mufasa",
+            "Circle of Life",
+            "GET",
+            "/dir/index.html",
+            "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ",
+        );
+        assert!(authorization.contains(
+            "response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\""
+        ));
+    }
+
+    #[test]
+    fn other_challenges() {
+        assert_eq!(Challenge::parse("Basic realm=\"box\""), None);
+        assert_eq!(
+            Challenge::parse("Digest realm=\"box\", nonce=\"1\", qop=\"auth-int\""),
+            None
+        );
+        let challenge = Challenge::parse("digest realm=box, nonce=abc").unwrap();
+        assert_eq!(challenge.algorithm, "MD5");
+        assert!(!challenge.qop_auth);
+        assert!(!challenge
+            .authorization("user", "secret", "GET", "/")
+            .contains("secret"));
+    }
+}
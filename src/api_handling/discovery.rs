@@ -0,0 +1,322 @@
+use crate::api_handling::url::origin;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::net::{SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// Device type of the TR-064 description, `tr64desc.xml`.
+pub const TR064_DEVICE_TYPE: &str = "urn:dslforum-org:device:InternetGatewayDevice:1";
+/// Device type of the IGD description, `igddesc.xml`.
+pub const IGD_DEVICE_TYPE: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+/// Multicast address and port SSDP searches are sent to.
+pub const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+
+/// The headers of an answer to an SSDP M-SEARCH the generator cares about.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SsdpResponse {
+    /// URL of the device description, e.g. `http://192.168.178.1:49000/tr64desc.xml`.
+    pub location: String,
+    /// The device type which was searched for.
+    pub search_target: String,
+    pub usn: String,
+    pub server: String,
+}
+
+impl SsdpResponse {
+    /// Parses an SSDP answer. Returns `None` for anything but `200 OK` answers with a `LOCATION` header.
+    /// Header names are case insensitive.
+    pub fn parse(message: &str) -> Option<Self> {
+        let mut lines = message.lines();
+        let status = lines.next()?;
+        if !status.starts_with("HTTP/") || status.split_whitespace().nth(1) != Some("200") {
+            return None;
+        }
+        let mut response = SsdpResponse {
+            location: String::new(),
+            search_target: String::new(),
+            usn: String::new(),
+            server: String::new(),
+        };
+        for line in lines {
+            let (name, value) = match line.find(':') {
+                Some(position) => (&line[..position], line[position + 1..].trim()),
+                None => continue,
+            };
+            match name.trim().to_ascii_uppercase().as_str() {
+                "LOCATION" => response.location = value.to_string(),
+                "ST" => response.search_target = value.to_string(),
+                "USN" => response.usn = value.to_string(),
+                "SERVER" => response.server = value.to_string(),
+                _ => {}
+            }
+        }
+        if response.location.is_empty() {
+            None
+        } else {
+            Some(response)
+        }
+    }
+}
+
+/// A box which answered the discovery, with the locations of its descriptions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredBox {
+    /// Scheme, host and port of the box, e.g. `http://192.168.178.1:49000`. Answers are grouped by it.
+    pub address: String,
+    pub server: String,
+    /// Location of `tr64desc.xml`, if the box answered the TR-064 search.
+    pub tr64_location: Option<String>,
+    /// Location of `igddesc.xml`, if the box answered the IGD search.
+    pub igd_location: Option<String>,
+}
+
+impl fmt::Display for DiscoveredBox {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.address)?;
+        if !self.server.is_empty() {
+            write!(f, " ({})", self.server)?;
+        }
+        match (&self.tr64_location, &self.igd_location) {
+            (Some(_), Some(_)) => write!(f, ", TR-064 and IGD"),
+            (Some(_), None) => write!(f, ", TR-064 only"),
+            (None, Some(_)) => write!(f, ", IGD only"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// SSDP search for FRITZ!Boxes. Sends an M-SEARCH for the TR-064 and the IGD device type and
+/// collects the answers until `timeout` is over.
+#[derive(Debug)]
+pub struct Discovery {
+    /// Where the searches are sent to, `SSDP_ADDRESS` by default.
+    pub target: SocketAddr,
+    pub timeout: Duration,
+}
+
+impl Discovery {
+    pub fn new() -> Self {
+        Discovery {
+            target: SSDP_ADDRESS
+                .parse()
+                .expect("SSDP_ADDRESS is a valid address"),
+            timeout: Duration::from_secs(3),
+        }
+    }
+
+    /// Runs the search and returns the answering boxes in the order of their first answer.
+    pub fn search(&self) -> io::Result<Vec<DiscoveredBox>> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        let mx = self.timeout.as_secs().clamp(1, 5);
+        for device_type in &[TR064_DEVICE_TYPE, IGD_DEVICE_TYPE] {
+            let request = format!(
+                "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: {}\r\nST: {}\r\n\r\n",
+                SSDP_ADDRESS, mx, device_type
+            );
+            socket.send_to(request.as_bytes(), self.target)?;
+        }
+
+        let mut boxes: Vec<DiscoveredBox> = vec![];
+        let deadline = Instant::now() + self.timeout;
+        let mut buffer = [0u8; 2048];
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            socket.set_read_timeout(Some(deadline - now))?;
+            let length = match socket.recv_from(&mut buffer) {
+                Ok((length, _)) => length,
+                Err(e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    break
+                }
+                Err(e) => return Err(e),
+            };
+            if let Some(response) = SsdpResponse::parse(&String::from_utf8_lossy(&buffer[..length]))
+            {
+                add_response(&mut boxes, response);
+            }
+        }
+        Ok(boxes)
+    }
+}
+
+/// Adds the location of `response` to the box with the same address, or to a new box.
+fn add_response(boxes: &mut Vec<DiscoveredBox>, response: SsdpResponse) {
+    let address = match origin(&response.location) {
+        Some(address) => address.to_string(),
+        None => return,
+    };
+    let position = match boxes.iter().position(|found| found.address == address) {
+        Some(position) => position,
+        None => {
+            boxes.push(DiscoveredBox {
+                address,
+                server: response.server.clone(),
+                tr64_location: None,
+                igd_location: None,
+            });
+            boxes.len() - 1
+        }
+    };
+    let found = &mut boxes[position];
+    if response.search_target == TR064_DEVICE_TYPE {
+        found.tr64_location = Some(response.location);
+    } else if response.search_target == IGD_DEVICE_TYPE {
+        found.igd_location = Some(response.location);
+    }
+}
+
+/// Returns the only box of `boxes`, or lets the user pick one on `output`/`input` if several answered.
+pub fn choose_box(
+    mut boxes: Vec<DiscoveredBox>,
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> Result<DiscoveredBox, Box<dyn std::error::Error>> {
+    match boxes.len() {
+        0 => return Err("no FRITZ!Box answered the SSDP search".into()),
+        1 => return Ok(boxes.remove(0)),
+        _ => {}
+    }
+    writeln!(output, "Found {} boxes:", boxes.len())?;
+    for (number, found) in boxes.iter().enumerate() {
+        writeln!(output, "  {}: {}", number + 1, found)?;
+    }
+    loop {
+        write!(output, "Generate for box [1-{}]: ", boxes.len())?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Err("no box selected".into());
+        }
+        match line.trim().parse::<usize>() {
+            Ok(number) if number >= 1 && number <= boxes.len() => {
+                return Ok(boxes.remove(number - 1))
+            }
+            _ => writeln!(output, "`{}` is not a box number.", line.trim())?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::thread;
+
+    /// Answers every M-SEARCH with the locations `answers` holds for its search target.
+    fn responder(answers: Vec<(&'static str, &'static str)>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buffer = [0u8; 2048];
+            for _ in 0..2 {
+                let (length, sender) = socket.recv_from(&mut buffer).unwrap();
+                let request = String::from_utf8_lossy(&buffer[..length]).to_string();
+                assert!(request.starts_with("M-SEARCH * HTTP/1.1\r\n"));
+                assert!(request.contains("MAN: \"ssdp:discover\"\r\n"));
+                for (search_target, location) in &answers {
+                    if request.contains(&format!("ST: {}\r\n", search_target)) {
+                        let answer = format!(
+                            "HTTP/1.1 200 OK\r\nCACHE-CONTROL: max-age=1800\r\nLOCATION: {}\r\n\
+                             SERVER: FRITZ!Box 7590 UPnP/1.0 AVM FRITZ!Box 7590 154.07.29\r\n\
+                             ST: {}\r\nUSN: uuid:123::{}\r\n\r\n",
+                            location, search_target, search_target
+                        );
+                        socket.send_to(answer.as_bytes(), sender).unwrap();
+                    }
+                }
+            }
+        });
+        address
+    }
+
+    fn discovery(target: SocketAddr) -> Discovery {
+        let mut discovery = Discovery::new();
+        discovery.target = target;
+        discovery.timeout = Duration::from_millis(500);
+        discovery
+    }
+
+    #[test]
+    fn parse_response() {
+        let response = SsdpResponse::parse(
+            "HTTP/1.1 200 OK\r\nlocation: http://192.168.178.1:49000/igddesc.xml\r\n\
+             st:urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(response.location, "http://192.168.178.1:49000/igddesc.xml");
+        assert_eq!(response.search_target, IGD_DEVICE_TYPE);
+        assert_eq!(
+            SsdpResponse::parse("NOTIFY * HTTP/1.1\r\nLOCATION: http://a/b\r\n\r\n"),
+            None
+        );
+        assert_eq!(
+            SsdpResponse::parse("HTTP/1.1 200 OK\r\nST: x\r\n\r\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn search_groups_descriptions_by_box() {
+        let target = responder(vec![
+            (TR064_DEVICE_TYPE, "http://127.0.0.1:49000/tr64desc.xml"),
+            (IGD_DEVICE_TYPE, "http://127.0.0.1:49000/igddesc.xml"),
+            (TR064_DEVICE_TYPE, "http://127.0.0.2:49000/tr64desc.xml"),
+        ]);
+        let boxes = discovery(target).search().unwrap();
+        assert_eq!(boxes.len(), 2);
+        assert_eq!(boxes[0].address, "http://127.0.0.1:49000");
+        assert_eq!(
+            boxes[0].tr64_location.as_deref(),
+            Some("http://127.0.0.1:49000/tr64desc.xml")
+        );
+        assert_eq!(
+            boxes[0].igd_location.as_deref(),
+            Some("http://127.0.0.1:49000/igddesc.xml")
+        );
+        assert_eq!(boxes[1].address, "http://127.0.0.2:49000");
+        assert_eq!(boxes[1].igd_location, None);
+        assert!(boxes[1].server.contains("FRITZ!Box 7590"));
+    }
+
+    #[test]
+    fn search_without_answers() {
+        let target = responder(vec![]);
+        assert_eq!(discovery(target).search().unwrap(), vec![]);
+    }
+
+    #[test]
+    fn choose_between_boxes() {
+        let boxes = vec![
+            DiscoveredBox {
+                address: "http://192.168.178.1:49000".to_string(),
+                server: String::new(),
+                tr64_location: Some("http://192.168.178.1:49000/tr64desc.xml".to_string()),
+                igd_location: None,
+            },
+            DiscoveredBox {
+                address: "http://192.168.178.2:49000".to_string(),
+                server: String::new(),
+                tr64_location: None,
+                igd_location: Some("http://192.168.178.2:49000/igddesc.xml".to_string()),
+            },
+        ];
+        assert!(choose_box(vec![], Cursor::new(""), vec![]).is_err());
+        assert_eq!(
+            choose_box(boxes[..1].to_vec(), Cursor::new(""), vec![]).unwrap(),
+            boxes[0]
+        );
+
+        let mut output = vec![];
+        let chosen = choose_box(boxes.clone(), Cursor::new("3\nx\n2\n"), &mut output).unwrap();
+        assert_eq!(chosen, boxes[1]);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("1: http://192.168.178.1:49000, TR-064 only"));
+        assert!(output.contains("`3` is not a box number."));
+        assert!(choose_box(boxes, Cursor::new(""), vec![]).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! Source code of the optional `<prefix>discovery.rs` module written next to the request files.
+//! It only uses `std`, so the bindings don't need an HTTP client to find a device.
+
+/// Imports, always the start of the module.
+const HEADER: &str = concat!(
+    "//! Finds devices on the local network which offer the services of these bindings, using SSDP.\n",
+    include_str!("discovery_module/header.rs"),
+);
+
+/// Everything after the `SERVICE_TYPES` list.
+const CODE: &str = include_str!("discovery_module/code.rs");
+
+/// Returns the discovery module searching for `service_types`, which uses `<prefix>url.rs`.
+pub fn discovery_module(service_types: &[String], prefix: &str) -> String {
+    let mut result = String::from(HEADER);
+    result
+        .push_str("\n/// Service types of the bindings, devices offering any of them are found.\n");
+    result.push_str("pub const SERVICE_TYPES: &[&str] = &[\n");
+    for service_type in service_types {
+        result.push_str(format!("    \"{}\",\n", service_type).as_str());
+    }
+    result.push_str("];\n");
+    result.push_str(CODE.replace("PREFIX_", prefix).as_str());
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    #[allow(non_snake_case)]
+    mod PREFIX_url {
+        pub use crate::api_handling::url::origin;
+    }
+
+    /// The shipped module, searching for the `Hosts` service.
+    #[allow(dead_code, clippy::all)]
+    mod discovery {
+        include!("discovery_module/header.rs");
+        pub const SERVICE_TYPES: &[&str] = &["urn:dslforum-org:service:Hosts:1"];
+        include!("discovery_module/code.rs");
+
+        /// An answer to `M-SEARCH` as sent by a FRITZ!Box.
+        const ANSWER: &str = "HTTP/1.1 200 OK\r\n\
+            LOCATION: http://192.168.178.1:49000/tr64desc.xml\r\n\
+            SERVER: FRITZ!Box 7590 UPnP/1.0 AVM FRITZ!Box 7590 154.07.29\r\n\
+            CACHE-CONTROL: max-age=1800\r\n\
+            EXT:\r\n\
+            ST: urn:dslforum-org:service:Hosts:1\r\n\
+            USN: uuid:739f2409-bccb-40e7-8e6c-9CA2F4A45E3C::urn:dslforum-org:service:Hosts:1\r\n\r\n";
+
+        #[test]
+        fn answers_and_descriptions() {
+            assert_eq!(
+                header(ANSWER, "location").as_deref(),
+                Some("http://192.168.178.1:49000/tr64desc.xml")
+            );
+            assert_eq!(header(ANSWER, "EXT").as_deref(), Some(""));
+            assert_eq!(header(ANSWER, "HTTP/1.1 200 OK"), None);
+            assert_eq!(header(ANSWER, "NT"), None);
+
+            let description = "<root><device><friendlyName> FRITZ!Box &amp; &quot;Home&quot; </friendlyName>\
+                <deviceList><device><friendlyName>LAN</friendlyName></device></deviceList></device></root>";
+            assert_eq!(element(description, "friendlyName"), "FRITZ!Box & \"Home\"");
+            assert_eq!(element(description, "UDN"), "");
+        }
+
+        /// Answers the searches to a local socket like a box, with its description on a local
+        /// `TcpListener`.
+        #[test]
+        fn discovers_local_box() {
+            let description = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            let location = format!("http://{}/tr64desc.xml", description.local_addr().unwrap());
+            let searches = UdpSocket::bind("127.0.0.1:0").unwrap();
+            let target = searches.local_addr().unwrap().to_string();
+            let answers = [
+                "HTTP/1.1 404 Not Found\r\nLOCATION: http://127.0.0.1:9/other.xml\r\n\r\n"
+                    .to_string(),
+                ANSWER.replace(
+                    "LOCATION: http://192.168.178.1:49000/tr64desc.xml",
+                    &format!("location:{}", location),
+                ),
+                ANSWER.replace("http://192.168.178.1:49000/tr64desc.xml", &location),
+                ANSWER.replace("http://", "https://"),
+            ];
+            std::thread::spawn(move || {
+                let mut buffer = [0u8; 1024];
+                let (length, searcher) = searches.recv_from(&mut buffer).unwrap();
+                let search = String::from_utf8_lossy(&buffer[..length]).to_string();
+                assert!(search.starts_with("M-SEARCH * HTTP/1.1\r\n"), "{}", search);
+                assert!(
+                    search.contains("\r\nST: urn:dslforum-org:service:Hosts:1\r\n"),
+                    "{}",
+                    search
+                );
+                for answer in &answers {
+                    searches.send_to(answer.as_bytes(), searcher).unwrap();
+                }
+                let (mut stream, _) = description.accept().unwrap();
+                let mut request = vec![];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let length = stream.read(&mut buffer).unwrap();
+                    if length == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buffer[..length]);
+                }
+                write!(
+                    stream,
+                    "HTTP/1.0 200 OK\r\n\r\n<root><device><friendlyName>Box</friendlyName>\
+                     <modelName>FRITZ!Box 7590</modelName><UDN>uuid:1</UDN></device></root>"
+                )
+                .unwrap();
+            });
+
+            let devices = discover_at(&target, Duration::from_millis(500)).unwrap();
+            assert_eq!(
+                devices,
+                vec![DiscoveredDevice {
+                    base_url: location.trim_end_matches("/tr64desc.xml").to_string(),
+                    location,
+                    udn: "uuid:1".to_string(),
+                    friendly_name: "Box".to_string(),
+                    model_name: "FRITZ!Box 7590".to_string(),
+                }]
+            );
+        }
+    }
+}
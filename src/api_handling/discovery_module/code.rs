@@ -0,0 +1,146 @@
+
+/// A device which answered the search, with data from its root device description.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiscoveredDevice {
+    /// Scheme, host and port, e.g. `http://192.168.178.1:49000`. Prefix the `uri` of requests with it.
+    pub base_url: String,
+    /// URL of the device description.
+    pub location: String,
+    pub udn: String,
+    pub friendly_name: String,
+    pub model_name: String,
+}
+
+/// Searches for devices offering any of `SERVICE_TYPES` for `timeout`.
+pub fn discover(timeout: Duration) -> io::Result<Vec<DiscoveredDevice>> {
+    discover_at(SSDP_ADDRESS, timeout)
+}
+
+/// Like `discover`, but sends the searches to `target` instead of `SSDP_ADDRESS`.
+/// Every device is returned once, devices whose description can't be fetched are skipped.
+pub fn discover_at(target: &str, timeout: Duration) -> io::Result<Vec<DiscoveredDevice>> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    for service_type in SERVICE_TYPES {
+        let request = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: {}\r\n\r\n",
+            SSDP_ADDRESS, service_type
+        );
+        socket.send_to(request.as_bytes(), target)?;
+    }
+
+    let mut locations: Vec<String> = vec![];
+    let deadline = Instant::now() + timeout;
+    let mut buffer = [0u8; 2048];
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        socket.set_read_timeout(Some(deadline - now))?;
+        let length = match socket.recv_from(&mut buffer) {
+            Ok((length, _)) => length,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {
+                break
+            }
+            Err(e) => return Err(e),
+        };
+        let message = String::from_utf8_lossy(&buffer[..length]);
+        if !message.starts_with("HTTP/1.1 200") {
+            continue;
+        }
+        if let Some(location) = header(&message, "LOCATION") {
+            if !locations.contains(&location) {
+                locations.push(location);
+            }
+        }
+    }
+
+    let mut devices: Vec<DiscoveredDevice> = vec![];
+    for location in locations {
+        let base_url = match super::PREFIX_url::origin(&location) {
+            Some(base_url) => base_url.to_string(),
+            None => continue,
+        };
+        let description = match fetch(&location) {
+            Ok(description) => description,
+            Err(_) => continue,
+        };
+        let device = DiscoveredDevice {
+            base_url,
+            location,
+            udn: element(&description, "UDN"),
+            friendly_name: element(&description, "friendlyName"),
+            model_name: element(&description, "modelName"),
+        };
+        if !devices.iter().any(|known| known.udn == device.udn) {
+            devices.push(device);
+        }
+    }
+    Ok(devices)
+}
+
+/// Returns the value of the header `name` (case insensitive) of an SSDP answer.
+fn header(message: &str, name: &str) -> Option<String> {
+    message.lines().skip(1).find_map(|line| {
+        let position = line.find(':')?;
+        if line[..position].trim().eq_ignore_ascii_case(name) {
+            Some(line[position + 1..].trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Fetches `location`, which has to be a plain `http` URL, like the description URLs of FRITZ!Boxes.
+fn fetch(location: &str) -> io::Result<String> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let rest = location
+        .strip_prefix("http://")
+        .ok_or_else(|| invalid("only http locations are supported"))?;
+    let (host, path) = match rest.find('/') {
+        Some(position) => (&rest[..position], &rest[position..]),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| invalid("host has no address"))?;
+    let mut stream = TcpStream::connect_timeout(&address, Duration::from_secs(5))?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\n\r\n", path, host)?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let body_start = response
+        .find("\r\n\r\n")
+        .ok_or_else(|| invalid("incomplete HTTP response"))?;
+    if response.split_whitespace().nth(1) != Some("200") {
+        return Err(invalid("description request failed"));
+    }
+    Ok(response[body_start + 4..].to_string())
+}
+
+/// Returns the unescaped text of the first element `name` in `xml`, the one of the root device.
+fn element(xml: &str, name: &str) -> String {
+    let start_tag = format!("<{}>", name);
+    let end_tag = format!("</{}>", name);
+    let start = match xml.find(&start_tag) {
+        Some(position) => position + start_tag.len(),
+        None => return String::new(),
+    };
+    let end = match xml[start..].find(&end_tag) {
+        Some(position) => start + position,
+        None => return String::new(),
+    };
+    xml[start..end]
+        .trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
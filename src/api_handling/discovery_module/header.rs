@@ -0,0 +1,7 @@
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// Multicast address and port SSDP searches are sent to.
+pub const SSDP_ADDRESS: &str = "239.255.255.250:1900";
@@ -0,0 +1,414 @@
+//! GENA event subscriptions, see section 4 of the UPnP Device Architecture 1.0: requests to
+//! subscribe to the events of a service, to renew and to cancel subscriptions, and parsing of the
+//! `NOTIFY` requests the device sends to the callback URL.
+//! Only uses `std`, this file is also written as `<prefix>eventing.rs` next to the request files.
+
+use super::url::{origin, resolve};
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Answer to a notification, send it back on the connection of the `NOTIFY` request.
+pub const NOTIFY_RESPONSE: &str =
+    "HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+/// A `SUBSCRIBE` or `UNSUBSCRIBE` request for the event URL of a service.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventRequest {
+    /// `SUBSCRIBE` or `UNSUBSCRIBE`.
+    pub method: &'static str,
+    /// The event URL of the service, relative to the base URL of the device like the `uri` of requests.
+    pub uri: String,
+    /// Names and values of the headers, without `HOST`.
+    pub headers: Vec<(&'static str, String)>,
+}
+
+/// Creates a request subscribing `callback`, e.g. `http://192.168.178.20:8080/events`, to the
+/// events of the service with the event URL `event_url`. `timeout_seconds` is the duration of the
+/// subscription proposed to the device, which decides on it.
+pub fn subscribe_request(
+    event_url: &str,
+    callback: &str,
+    timeout_seconds: Option<u32>,
+) -> EventRequest {
+    let mut headers = vec![
+        ("CALLBACK", format!("<{}>", callback)),
+        ("NT", "upnp:event".to_string()),
+    ];
+    if let Some(seconds) = timeout_seconds {
+        headers.push(("TIMEOUT", format!("Second-{}", seconds)));
+    }
+    EventRequest {
+        method: "SUBSCRIBE",
+        uri: event_url.to_string(),
+        headers,
+    }
+}
+
+/// Creates a request renewing the subscription `sid` before it expires.
+pub fn renew_request(event_url: &str, sid: &str, timeout_seconds: Option<u32>) -> EventRequest {
+    let mut headers = vec![("SID", sid.to_string())];
+    if let Some(seconds) = timeout_seconds {
+        headers.push(("TIMEOUT", format!("Second-{}", seconds)));
+    }
+    EventRequest {
+        method: "SUBSCRIBE",
+        uri: event_url.to_string(),
+        headers,
+    }
+}
+
+/// Creates a request cancelling the subscription `sid`.
+pub fn unsubscribe_request(event_url: &str, sid: &str) -> EventRequest {
+    EventRequest {
+        method: "UNSUBSCRIBE",
+        uri: event_url.to_string(),
+        headers: vec![("SID", sid.to_string())],
+    }
+}
+
+/// A subscription the device accepted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subscription {
+    /// Identifier of the subscription, e.g. `uuid:...`, to renew and cancel it.
+    /// Every notification of the subscription has it as well.
+    pub sid: String,
+    /// Seconds until the subscription expires unless it is renewed, `None` if it doesn't expire.
+    pub timeout_seconds: Option<u32>,
+}
+
+impl EventRequest {
+    /// Returns the request as HTTP message to the device at `base_url`, e.g. `http://192.168.178.1:49000`.
+    pub fn to_http(&self, base_url: &str) -> String {
+        let url = resolve(base_url, &self.uri);
+        let url_origin = origin(&url).unwrap_or_default();
+        let host = url_origin.split("://").nth(1).unwrap_or_default();
+        let path = match &url[url_origin.len()..] {
+            "" => "/",
+            path => path,
+        };
+        let mut message = format!("{} {} HTTP/1.1\r\nHOST: {}\r\n", self.method, path, host);
+        for (name, value) in &self.headers {
+            message.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        message.push_str("Content-Length: 0\r\nConnection: close\r\n\r\n");
+        message
+    }
+
+    /// Sends the request to the device at `base_url`, which has to be a plain `http` URL, and
+    /// returns the subscription. Fails unless the device answers `200 OK`. After `UNSUBSCRIBE`,
+    /// `sid` of the result is empty.
+    pub fn send(&self, base_url: &str) -> io::Result<Subscription> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let url = resolve(base_url, &self.uri);
+        let host = url
+            .strip_prefix("http://")
+            .and_then(|_| origin(&url))
+            .map(|url_origin| &url_origin["http://".len()..])
+            .ok_or_else(|| invalid(format!("only http URLs are supported, not {}", url)))?;
+        let address = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:80", host)
+        };
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| invalid(format!("{} has no address", host)))?;
+        let mut stream = TcpStream::connect_timeout(&address, Duration::from_secs(5))?;
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        stream.write_all(self.to_http(base_url).as_bytes())?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response)?;
+
+        let head = &response[..response.find("\r\n\r\n").unwrap_or(response.len())];
+        let status = head.split_whitespace().nth(1).unwrap_or_default();
+        if status != "200" {
+            return Err(invalid(format!(
+                "{} {} was answered with status {}",
+                self.method, url, status
+            )));
+        }
+        let sid = header(head, "SID").unwrap_or_default();
+        if sid.is_empty() && self.method == "SUBSCRIBE" {
+            return Err(invalid(format!("{} {} returned no SID", self.method, url)));
+        }
+        let timeout_seconds = header(head, "TIMEOUT").and_then(|timeout| {
+            timeout
+                .trim()
+                .strip_prefix("Second-")
+                .and_then(|seconds| seconds.parse().ok())
+        });
+        Ok(Subscription {
+            sid,
+            timeout_seconds,
+        })
+    }
+}
+
+/// A `NOTIFY` request of the device to the callback URL of a subscription.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    /// Identifier of the subscription.
+    pub sid: String,
+    /// Number of the notification: `0` for the first one with the values of all evented
+    /// variables, then counting up. A gap means a notification was lost.
+    pub seq: u32,
+    /// Names and values of the variables which changed, see `parse_property_set`.
+    pub properties: Vec<(String, String)>,
+}
+
+impl Notification {
+    /// Parses a complete `NOTIFY` request as received on the callback URL, e.g. by a `TcpListener`.
+    /// Answer it with `NOTIFY_RESPONSE`.
+    pub fn parse(request: &str) -> Result<Notification, String> {
+        let head_end = request
+            .find("\r\n\r\n")
+            .ok_or("the request has no end of the headers")?;
+        let head = &request[..head_end];
+        if !head.starts_with("NOTIFY ") {
+            return Err(format!(
+                "expected a NOTIFY request, found `{}`",
+                head.lines().next().unwrap_or_default()
+            ));
+        }
+        if header(head, "NT").as_deref() != Some("upnp:event")
+            || header(head, "NTS").as_deref() != Some("upnp:propchange")
+        {
+            return Err("the request isn't an event notification".to_string());
+        }
+        let sid = header(head, "SID").ok_or("the notification has no SID")?;
+        let seq = header(head, "SEQ")
+            .and_then(|seq| seq.parse().ok())
+            .ok_or("the notification has no valid SEQ")?;
+        Ok(Notification {
+            sid,
+            seq,
+            properties: parse_property_set(&request[head_end + 4..])?,
+        })
+    }
+}
+
+/// Returns the names and values of the variables in the `e:propertyset` body of a notification,
+/// in the order of the body.
+pub fn parse_property_set(body: &str) -> Result<Vec<(String, String)>, String> {
+    let mut properties = vec![];
+    let mut in_property_set = false;
+    let mut in_property = false;
+    let mut position = 0;
+    while let Some(start) = body[position..].find('<').map(|start| position + start) {
+        let end = start
+            + body[start..]
+                .find('>')
+                .ok_or("the property set ends within a tag")?;
+        position = end + 1;
+        let tag = &body[start + 1..end];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        let closing = tag.starts_with('/');
+        let self_closing = tag.ends_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .trim_end_matches('/')
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
+        let local_name = name.rsplit(':').next().unwrap_or_default();
+        match (local_name, closing) {
+            ("propertyset", false) => in_property_set = true,
+            ("property", false) if in_property_set => in_property = true,
+            ("property", true) => in_property = false,
+            (_, false) if in_property => {
+                if self_closing {
+                    properties.push((name.to_string(), String::new()));
+                    continue;
+                }
+                let end_tag = format!("</{}>", name);
+                let value_end = body[position..]
+                    .find(&end_tag)
+                    .map(|value_end| position + value_end)
+                    .ok_or_else(|| format!("the variable {} isn't closed", name))?;
+                properties.push((name.to_string(), unescape(&body[position..value_end])));
+                position = value_end + end_tag.len();
+            }
+            _ => {}
+        }
+    }
+    if !in_property_set {
+        return Err("the body has no property set".to_string());
+    }
+    Ok(properties)
+}
+
+/// Values of evented variables, parsed from their text in a notification.
+pub trait EventValue: Sized {
+    fn parse_event_value(value: &str) -> Result<Self, String>;
+}
+
+impl EventValue for String {
+    fn parse_event_value(value: &str) -> Result<Self, String> {
+        Ok(value.to_string())
+    }
+}
+
+impl EventValue for bool {
+    fn parse_event_value(value: &str) -> Result<Self, String> {
+        match value.trim() {
+            "1" | "true" | "yes" => Ok(true),
+            "0" | "false" | "no" => Ok(false),
+            value => Err(format!("`{}` is no boolean", value)),
+        }
+    }
+}
+
+impl EventValue for u32 {
+    fn parse_event_value(value: &str) -> Result<Self, String> {
+        value
+            .trim()
+            .parse()
+            .map_err(|e| format!("`{}` is no unsigned number: {}", value, e))
+    }
+}
+
+impl EventValue for i32 {
+    fn parse_event_value(value: &str) -> Result<Self, String> {
+        value
+            .trim()
+            .parse()
+            .map_err(|e| format!("`{}` is no number: {}", value, e))
+    }
+}
+
+/// Returns the value of the header `name` in the head of an HTTP message.
+pub(crate) fn header(head: &str, name: &str) -> Option<String> {
+    head.lines().skip(1).find_map(|line| {
+        let position = line.find(':')?;
+        if line[..position].trim().eq_ignore_ascii_case(name) {
+            Some(line[position + 1..].trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Replaces the predefined entities of XML in `text` by their characters.
+pub(crate) fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// Starts a stand-in for the device, which answers one request with `response` and passes
+    /// the request on. Returns the base URL of the stand-in.
+    fn device(response: &'static str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut buffer = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).contains("\r\n\r\n") {
+                let read = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+            sender
+                .send(String::from_utf8_lossy(&request).to_string())
+                .unwrap();
+        });
+        (base_url, receiver)
+    }
+
+    #[test]
+    fn subscriptions() {
+        let (base_url, requests) = device(
+            "HTTP/1.1 200 OK\r\nSID: uuid:5a3c-1\r\nTIMEOUT: Second-1800\r\nContent-Length: 0\r\n\r\n",
+        );
+        let subscription = subscribe_request(
+            "/igdupnp/control/WANCommonIFC1",
+            "http://192.168.178.20:8080/events",
+            Some(3600),
+        )
+        .send(&base_url)
+        .unwrap();
+        assert_eq!(
+            subscription,
+            Subscription {
+                sid: "uuid:5a3c-1".to_string(),
+                timeout_seconds: Some(1800),
+            }
+        );
+        let request = requests.recv().unwrap();
+        assert!(request.starts_with("SUBSCRIBE /igdupnp/control/WANCommonIFC1 HTTP/1.1\r\n"));
+        assert!(request.contains(&format!("HOST: {}\r\n", &base_url["http://".len()..])));
+        assert!(request.contains("CALLBACK: <http://192.168.178.20:8080/events>\r\n"));
+        assert!(request.contains("NT: upnp:event\r\nTIMEOUT: Second-3600\r\n"));
+
+        let (base_url, requests) =
+            device("HTTP/1.1 200 OK\r\nSID: uuid:5a3c-1\r\nTIMEOUT: Second-infinite\r\n\r\n");
+        let renewed = renew_request("/igdupnp/control/WANCommonIFC1", "uuid:5a3c-1", None)
+            .send(&base_url)
+            .unwrap();
+        assert_eq!(renewed.timeout_seconds, None);
+        let request = requests.recv().unwrap();
+        assert!(request.contains("SID: uuid:5a3c-1\r\n"));
+        assert!(!request.contains("CALLBACK") && !request.contains("NT:"));
+
+        let (base_url, requests) = device("HTTP/1.1 200 OK\r\n\r\n");
+        unsubscribe_request("/igdupnp/control/WANCommonIFC1", "uuid:5a3c-1")
+            .send(&base_url)
+            .unwrap();
+        assert!(requests.recv().unwrap().starts_with("UNSUBSCRIBE "));
+
+        let (base_url, _) = device("HTTP/1.1 412 Precondition Failed\r\n\r\n");
+        let error = renew_request("/igdupnp/control/WANCommonIFC1", "uuid:gone", None)
+            .send(&base_url)
+            .unwrap_err();
+        assert!(error.to_string().contains("status 412"), "{}", error);
+    }
+
+    #[test]
+    fn notifications() {
+        let body = "<?xml version=\"1.0\"?>\r\n\
+            <e:propertyset xmlns:e=\"urn:schemas-upnp-org:event-1-0\">\r\n\
+            <e:property><PhysicalLinkStatus>Up</PhysicalLinkStatus></e:property>\r\n\
+            <e:property><X_AVM-DE_Name>Tom &amp; Jerry&apos;s</X_AVM-DE_Name></e:property>\r\n\
+            <e:property><ExternalIPAddress/></e:property>\r\n\
+            </e:propertyset>";
+        let request = format!(
+            "NOTIFY /events HTTP/1.1\r\nHOST: 192.168.178.20:8080\r\nCONTENT-TYPE: text/xml\r\n\
+             NT: upnp:event\r\nNTS: upnp:propchange\r\nSID: uuid:5a3c-1\r\nSEQ: 3\r\n\r\n{}",
+            body
+        );
+        let notification = Notification::parse(&request).unwrap();
+        assert_eq!(notification.sid, "uuid:5a3c-1");
+        assert_eq!(notification.seq, 3);
+        assert_eq!(
+            notification.properties,
+            vec![
+                ("PhysicalLinkStatus".to_string(), "Up".to_string()),
+                ("X_AVM-DE_Name".to_string(), "Tom & Jerry's".to_string()),
+                ("ExternalIPAddress".to_string(), String::new()),
+            ]
+        );
+
+        assert!(Notification::parse(&request.replace("SEQ: 3\r\n", "")).is_err());
+        assert!(Notification::parse(&request.replace("NOTIFY", "POST")).is_err());
+        assert!(parse_property_set("<html></html>").is_err());
+        assert!(parse_property_set(&body.replace("</PhysicalLinkStatus>", "")).is_err());
+        assert_eq!(bool::parse_event_value("1"), Ok(true));
+        assert_eq!(u32::parse_event_value(" 42 "), Ok(42));
+        assert!(i32::parse_event_value("x").is_err());
+    }
+}
@@ -0,0 +1,305 @@
+//! Example SOAP requests and successful responses for every action of a description set, for
+//! documentation and test fixtures. Values are the default value of the related state variable,
+//! its first allowed value or a placeholder of its data type.
+
+use crate::api_handling::api_desc::{Action, ApiDesc, StateVariable};
+use crate::api_handling::cache::file_name;
+use crate::api_handling::description_set::DescriptionSet;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// Returns an example value of an argument with the state variable `variable`: the default value
+/// of the variable, its first allowed value or a value of its data type.
+pub fn example_value(variable: Option<&StateVariable>) -> String {
+    let variable = match variable {
+        Some(variable) => variable,
+        None => return String::new(),
+    };
+    if !variable.default_value.is_empty() {
+        return variable.default_value.clone();
+    }
+    if let Some(allowed) = variable.allowed_value_list.allowed_value.first() {
+        return allowed.clone();
+    }
+    match variable.data_type.as_str() {
+        "boolean" | "ui1" | "ui2" | "ui4" | "i1" | "i2" | "i4" => "0",
+        "dateTime" => "1970-01-01T00:00:00",
+        "uuid" => "00000000-0000-0000-0000-000000000000",
+        _ => "",
+    }
+    .to_string()
+}
+
+/// Returns an example call of `action` of the service `service_type` with the SCPD `scpd`, with
+/// example values for all `in` arguments.
+pub fn example_request(service_type: &str, scpd: &ApiDesc, action: &Action) -> String {
+    envelope(&format!(
+        "<u:{} xmlns:u=\"{}\">{}</u:{}>",
+        action.name,
+        service_type,
+        arguments(scpd, action, "in", &[]),
+        action.name
+    ))
+}
+
+/// Returns a successful response to `action` of the service `service_type` with the SCPD `scpd`.
+/// `out` arguments have the value given in `values`, e.g. `("NewSerialNumber", "0815")`, or an
+/// example value.
+pub fn example_response(
+    service_type: &str,
+    scpd: &ApiDesc,
+    action: &Action,
+    values: &[(String, String)],
+) -> String {
+    envelope(&format!(
+        "<u:{}Response xmlns:u=\"{}\">{}</u:{}Response>",
+        action.name,
+        service_type,
+        arguments(scpd, action, "out", values),
+        action.name
+    ))
+}
+
+/// Writes an example request and response for every action of `set` into `folder`, into a folder
+/// per service type, e.g. `DeviceInfo/GetInfo.request.xml` and `DeviceInfo/GetInfo.response.xml`.
+/// Services of the same type are written once. Returns the number of actions written.
+pub fn write_examples(set: &DescriptionSet, folder: &str) -> Result<usize, Box<dyn Error>> {
+    let mut written = 0;
+    let mut service_types: Vec<&str> = vec![];
+    for description in &set.services {
+        let service_type = description.service.service_type.as_str();
+        if service_types.contains(&service_type) {
+            continue;
+        }
+        service_types.push(service_type);
+        let service_folder = Path::new(folder).join(file_name(service_name(service_type)));
+        fs::create_dir_all(&service_folder)
+            .map_err(|e| format!("creating {} failed: {}", service_folder.display(), e))?;
+        for action in &description.scpd.action_list.action {
+            let name = file_name(&action.name);
+            write(
+                &service_folder.join(format!("{}.request.xml", name)),
+                &example_request(service_type, &description.scpd, action),
+            )?;
+            write(
+                &service_folder.join(format!("{}.response.xml", name)),
+                &example_response(service_type, &description.scpd, action, &[]),
+            )?;
+            written += 1;
+        }
+    }
+    Ok(written)
+}
+
+/// Returns the elements of the arguments of `action` in `direction`, in the order of the SCPD.
+fn arguments(
+    scpd: &ApiDesc,
+    action: &Action,
+    direction: &str,
+    values: &[(String, String)],
+) -> String {
+    let mut elements = String::new();
+    for argument in &action.argument_list.argument {
+        if argument.direction != direction {
+            continue;
+        }
+        let value = match values.iter().find(|(name, _)| *name == argument.name) {
+            Some((_, value)) => value.clone(),
+            None => example_value(scpd.state_variable(&argument.related_state_variable)),
+        };
+        elements.push_str(&format!(
+            "<{}>{}</{}>",
+            argument.name,
+            escape_xml(&value),
+            argument.name
+        ));
+    }
+    elements
+}
+
+/// Returns the name of a service type, e.g. `DeviceInfo` for `urn:dslforum-org:service:DeviceInfo:1`.
+fn service_name(service_type: &str) -> &str {
+    service_type.split(':').nth(3).unwrap_or(service_type)
+}
+
+fn write(path: &Path, content: &str) -> Result<(), String> {
+    fs::write(path, content).map_err(|e| format!("writing {} failed: {}", path.display(), e))
+}
+
+/// Wraps `content` into a SOAP envelope.
+pub fn envelope(content: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\"?>\n<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+         s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\n<s:Body>\n{}\n</s:Body>\n</s:Envelope>",
+        content
+    )
+}
+
+// The escaper the generated request functions use, so examples are escaped the same way.
+include!("serde_helpers/escape_xml.rs");
+
+/// Responses of `tests::hosts` as generated, to deserialize the examples with.
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(dead_code, clippy::all)]
+#[path = "examples_responses/mod.rs"]
+mod examples_responses;
+
+/// Requests of `tests::hosts` as generated, with their `tests` modules, so the generated tests
+/// are compiled and run too.
+#[cfg(test)]
+#[rustfmt::skip]
+#[allow(dead_code, clippy::all)]
+#[path = "examples_requests/mod.rs"]
+mod examples_requests;
+
+#[cfg(test)]
+mod tests {
+    use super::examples_responses::examples_hosts::{GetKeyResponse, GetSpecificHostEntryResponse};
+    use super::examples_responses::examples_multi_use::Envelope;
+    use super::*;
+    use crate::api_handling::description_set::fixtures::{action, scpd, set, variable};
+    use crate::api_handling::merge::Availability;
+    use crate::api_handling::mock::{MockScript, MockServer};
+    use crate::api_handling::options::GeneratorOptions;
+
+    const HOSTS: &str = "urn:dslforum-org:service:Hosts:1";
+
+    fn hosts() -> DescriptionSet {
+        let hosts = scpd(
+            &[
+                action(
+                    "GetSpecificHostEntry",
+                    &[
+                        ("NewMACAddress", "in", "MACAddress"),
+                        ("NewIPAddress", "out", "IPAddress"),
+                        ("NewLeaseTimeRemaining", "out", "LeaseTimeRemaining"),
+                        ("NewInterfaceType", "out", "InterfaceType"),
+                        ("NewActive", "out", "Active"),
+                        ("NewHostName", "out", "HostName"),
+                    ],
+                ),
+                action(
+                    "GetKey",
+                    &[("NewKey", "out", "Key"), ("NewChanged", "out", "Changed")],
+                ),
+            ]
+            .concat(),
+            &[
+                variable("MACAddress", "string", &[]),
+                variable("IPAddress", "string", &[]),
+                variable("LeaseTimeRemaining", "ui4", &[]),
+                variable("InterfaceType", "string", &["Ethernet", "802.11"]),
+                variable("Active", "boolean", &[]),
+                variable("HostName", "string", &[]),
+                variable("Key", "bin.base64", &[]),
+                variable("Changed", "dateTime", &[]),
+            ]
+            .concat(),
+        );
+        set(&[("Hosts", &hosts)])
+    }
+
+    /// The checked in requests and responses are what the generator writes for `hosts`. If the
+    /// generated code changes on purpose, replace them with the files the failing test leaves in
+    /// the temp folder.
+    #[test]
+    fn generated_files() {
+        let folder = std::env::temp_dir().join(format!("examples-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        let mut options = GeneratorOptions::new();
+        options.output_folder = folder.to_string_lossy().into_owned();
+        options.generate_tests = true;
+        let set = hosts();
+        set.description
+            .generate_files(
+                &set.services,
+                &Availability::new(),
+                "responses".to_string(),
+                "requests".to_string(),
+                Some("examples".to_string()),
+                &options,
+            )
+            .unwrap();
+        for (module, expected) in [
+            (
+                "examples_responses",
+                [
+                    "examples_hosts.rs",
+                    "examples_multi_use.rs",
+                    "examples_serde_helpers.rs",
+                    "mod.rs",
+                ],
+            ),
+            (
+                "examples_requests",
+                [
+                    "examples_hosts.rs",
+                    "examples_serde_helpers.rs",
+                    "examples_url.rs",
+                    "mod.rs",
+                ],
+            ),
+        ] {
+            let generated = folder.join(module);
+            let checked_in = Path::new("src/api_handling").join(module);
+            let mut files: Vec<_> = fs::read_dir(&generated)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name())
+                .collect();
+            files.sort();
+            assert_eq!(files, expected);
+            for file in files {
+                assert_eq!(
+                    fs::read_to_string(generated.join(&file)).unwrap(),
+                    fs::read_to_string(checked_in.join(&file)).unwrap(),
+                    "{:?} differs from the generated file",
+                    file
+                );
+            }
+        }
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn round_trip() {
+        let set = hosts();
+        let description = &set.services[0];
+        let actions = &description.scpd.action_list.action;
+        let request = example_request(HOSTS, &description.scpd, &actions[0]);
+        assert!(request.contains(
+            "<u:GetSpecificHostEntry xmlns:u=\"urn:dslforum-org:service:Hosts:1\">\
+             <NewMACAddress></NewMACAddress></u:GetSpecificHostEntry>"
+        ));
+
+        let values = [("NewHostName".to_string(), "<box>".to_string())];
+        let response = example_response(HOSTS, &description.scpd, &actions[0], &values);
+        let envelope: Envelope<GetSpecificHostEntryResponse> =
+            serde_xml_rs::from_str(&response).unwrap();
+        let response = envelope.body.response;
+        assert_eq!(response.new_ip_address, "");
+        assert_eq!(response.new_lease_time_remaining, 0);
+        assert_eq!(response.new_interface_type, "Ethernet");
+        assert!(!response.new_active);
+        assert_eq!(response.new_host_name, "<box>");
+
+        let response = example_response(HOSTS, &description.scpd, &actions[1], &[]);
+        let envelope: Envelope<GetKeyResponse> = serde_xml_rs::from_str(&response).unwrap();
+        assert!(envelope.body.response.new_key.is_empty());
+        assert_eq!(envelope.body.response.new_changed, "1970-01-01T00:00:00");
+
+        let mock = MockServer::new(vec![hosts()], MockScript::new()).unwrap();
+        let answer = mock.respond(
+            "POST",
+            "/upnp/control/Hosts",
+            Some(&format!("\"{}#GetSpecificHostEntry\"", HOSTS)),
+            &request,
+        );
+        assert_eq!(answer.status, 200);
+        assert_eq!(
+            answer.body,
+            example_response(HOSTS, &description.scpd, &actions[0], &[])
+        );
+    }
+}
@@ -0,0 +1,61 @@
+//! Service `urn:dslforum-org:service:Hosts:1`, control URL `/upnp/control/Hosts`, event URL `/upnp/control/Hosts`.
+
+/// Creates a `GetSpecificHostEntry` request for service `urn:dslforum-org:service:Hosts:1`.
+///
+/// Control URL: `/upnp/control/Hosts`
+///
+/// # Arguments
+/// * `new_mac_address` - `NewMACAddress`, related state variable `MACAddress` of type `string`.
+/// * `id` - Number of the service instance, `1` if `None`.
+///
+/// Returns the uri, the SOAPAction header and the body of the request.
+/// The uri is relative to the base URL of the device, see `super::examples_url::resolve`.
+pub fn generate_get_specific_host_entry_request(new_mac_address: &str, id: Option<&str>) -> (String, String, String){ 
+	let id = id.unwrap_or("1");
+	let uri = "/upnp/control/Hosts";
+	let header = format!("urn:dslforum-org:service:Hosts:{}#GetSpecificHostEntry", id);
+	let body = format!("<?xml version=\"1.0\"?><s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body><u:GetSpecificHostEntry xmlns:u=\"urn:dslforum-org:service:Hosts:{}\"><NewMACAddress>{}</NewMACAddress></u:GetSpecificHostEntry></s:Body></s:Envelope>",id ,super::examples_serde_helpers::escape_xml(new_mac_address));
+	(uri.to_string(), header, body) 
+}
+/// Creates a `GetKey` request for service `urn:dslforum-org:service:Hosts:1`.
+///
+/// Control URL: `/upnp/control/Hosts`
+///
+/// # Arguments
+/// * `id` - Number of the service instance, `1` if `None`.
+///
+/// Returns the uri, the SOAPAction header and the body of the request.
+/// The uri is relative to the base URL of the device, see `super::examples_url::resolve`.
+pub fn generate_get_key_request( id: Option<&str>) -> (String, String, String){ 
+	let id = id.unwrap_or("1");
+	let uri = "/upnp/control/Hosts";
+	let header = format!("urn:dslforum-org:service:Hosts:{}#GetKey", id);
+	let body = format!("<?xml version=\"1.0\"?><s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body><u:GetKey xmlns:u=\"urn:dslforum-org:service:Hosts:{}\"></u:GetKey></s:Body></s:Envelope>",id );
+	(uri.to_string(), header, body) 
+}
+
+/// Round trips of the requests and responses of the service with example values.
+#[cfg(test)]
+mod tests {
+	#[test]
+	fn get_specific_host_entry() {
+		let (_, _, body) = super::generate_get_specific_host_entry_request("", None);
+		for event in serde_xml_rs::EventReader::from_str(&body) {
+			event.unwrap();
+		}
+		assert!(body.contains("<u:GetSpecificHostEntry xmlns:u=\"urn:dslforum-org:service:Hosts:1\"><NewMACAddress></NewMACAddress></u:GetSpecificHostEntry>"), "{}", body);
+		let _response: super::super::super::examples_responses::examples_multi_use::Envelope<super::super::super::examples_responses::examples_hosts::GetSpecificHostEntryResponse> =
+			serde_xml_rs::from_str("<?xml version=\"1.0\"?>\n<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\n<s:Body>\n<u:GetSpecificHostEntryResponse xmlns:u=\"urn:dslforum-org:service:Hosts:1\"><NewIPAddress></NewIPAddress><NewLeaseTimeRemaining>0</NewLeaseTimeRemaining><NewInterfaceType>Ethernet</NewInterfaceType><NewActive>0</NewActive><NewHostName></NewHostName></u:GetSpecificHostEntryResponse>\n</s:Body>\n</s:Envelope>").unwrap();
+	}
+
+	#[test]
+	fn get_key() {
+		let (_, _, body) = super::generate_get_key_request(None);
+		for event in serde_xml_rs::EventReader::from_str(&body) {
+			event.unwrap();
+		}
+		assert!(body.contains("<u:GetKey xmlns:u=\"urn:dslforum-org:service:Hosts:1\"></u:GetKey>"), "{}", body);
+		let _response: super::super::super::examples_responses::examples_multi_use::Envelope<super::super::super::examples_responses::examples_hosts::GetKeyResponse> =
+			serde_xml_rs::from_str("<?xml version=\"1.0\"?>\n<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\">\n<s:Body>\n<u:GetKeyResponse xmlns:u=\"urn:dslforum-org:service:Hosts:1\"><NewKey></NewKey><NewChanged>1970-01-01T00:00:00</NewChanged></u:GetKeyResponse>\n</s:Body>\n</s:Envelope>").unwrap();
+	}
+}
@@ -0,0 +1,82 @@
+//! Resolution of URL references against a base URL, following RFC 3986 section 5.2.
+//! Only uses `std`, this file is also written as `<prefix>url.rs` next to the request files.
+
+/// Returns scheme and authority of `url`, e.g. `http://fritz.box:49000` of
+/// `http://fritz.box:49000/tr64desc.xml`, or `None` if `url` is a relative reference.
+pub fn origin(url: &str) -> Option<&str> {
+    let scheme_end = url.find("://")?;
+    let scheme = &url[..scheme_end];
+    if !scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        || !scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+    {
+        return None;
+    }
+    let authority_start = scheme_end + 3;
+    let authority_end = url[authority_start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |position| authority_start + position);
+    Some(&url[..authority_end])
+}
+
+/// Resolves `reference` against `base`. Absolute references are returned as they are, e.g. the
+/// control URL `/upnp/control/deviceinfo` against `https://example.com:8443/tr64desc.xml`
+/// becomes `https://example.com:8443/upnp/control/deviceinfo`.
+pub fn resolve(base: &str, reference: &str) -> String {
+    let reference = reference.trim();
+    if origin(reference).is_some() {
+        return reference.to_string();
+    }
+    let base_origin = match origin(base) {
+        Some(base_origin) => base_origin,
+        None => return reference.to_string(),
+    };
+    if reference.starts_with("//") {
+        let scheme_end = base_origin.find(':').unwrap_or_default();
+        return format!("{}:{}", &base_origin[..scheme_end], reference);
+    }
+    let base_rest = &base[base_origin.len()..];
+    let base_rest = &base_rest[..base_rest.find('#').unwrap_or(base_rest.len())];
+    let base_path = &base_rest[..base_rest.find('?').unwrap_or(base_rest.len())];
+    if reference.is_empty() || reference.starts_with('#') {
+        return format!("{}{}{}", base_origin, base_rest, reference);
+    }
+    if reference.starts_with('?') {
+        return format!("{}{}{}", base_origin, base_path, reference);
+    }
+
+    let path_end = reference.find(['?', '#']).unwrap_or(reference.len());
+    let (path, suffix) = reference.split_at(path_end);
+    let merged = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        let directory = match base_path.rfind('/') {
+            Some(position) => &base_path[..=position],
+            None => "/",
+        };
+        format!("{}{}", directory, path)
+    };
+    format!("{}{}{}", base_origin, remove_dot_segments(&merged), suffix)
+}
+
+/// Removes `.` and `..` segments from an absolute path.
+fn remove_dot_segments(path: &str) -> String {
+    let parts: Vec<&str> = path.split('/').skip(1).collect();
+    let mut segments: Vec<&str> = vec![];
+    for (index, part) in parts.iter().enumerate() {
+        let last = index + 1 == parts.len();
+        match *part {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(part),
+        }
+        // A trailing `.` or `..` still denotes a directory.
+        if last && (*part == "." || *part == "..") {
+            segments.push("");
+        }
+    }
+    format!("/{}", segments.join("/"))
+}
@@ -0,0 +1,3 @@
+pub mod examples_hosts;
+pub mod examples_serde_helpers;
+pub mod examples_url;
@@ -0,0 +1,33 @@
+//! Service `urn:dslforum-org:service:Hosts:1`, control URL `/upnp/control/Hosts`, event URL `/upnp/control/Hosts`.
+
+/// Response of a `GetSpecificHostEntry` request for service `urn:dslforum-org:service:Hosts:1`.
+#[derive(Deserialize, Debug)]
+pub struct GetSpecificHostEntryResponse{
+	/// `NewIPAddress`, related state variable `IPAddress` of type `string`.
+	#[serde(rename = "NewIPAddress", deserialize_with = "super::examples_serde_helpers::upnp")]
+	pub new_ip_address: String,
+	/// `NewLeaseTimeRemaining`, related state variable `LeaseTimeRemaining` of type `ui4`.
+	#[serde(rename = "NewLeaseTimeRemaining", deserialize_with = "super::examples_serde_helpers::upnp")]
+	pub new_lease_time_remaining: u32,
+	/// `NewInterfaceType`, related state variable `InterfaceType` of type `string`. Allowed values: `Ethernet`, `802.11`.
+	#[serde(rename = "NewInterfaceType", deserialize_with = "super::examples_serde_helpers::upnp")]
+	pub new_interface_type: String,
+	/// `NewActive`, related state variable `Active` of type `boolean`.
+	#[serde(rename = "NewActive", deserialize_with = "super::examples_serde_helpers::upnp")]
+	pub new_active: bool,
+	/// `NewHostName`, related state variable `HostName` of type `string`.
+	#[serde(rename = "NewHostName", deserialize_with = "super::examples_serde_helpers::upnp")]
+	pub new_host_name: String,
+}
+
+/// Response of a `GetKey` request for service `urn:dslforum-org:service:Hosts:1`.
+#[derive(Deserialize, Debug)]
+pub struct GetKeyResponse{
+	/// `NewKey`, related state variable `Key` of type `bin.base64`.
+	#[serde(rename = "NewKey", deserialize_with = "super::examples_serde_helpers::base64")]
+	pub new_key: Vec<u8>,
+	/// `NewChanged`, related state variable `Changed` of type `dateTime`.
+	#[serde(rename = "NewChanged", deserialize_with = "super::examples_serde_helpers::upnp")]
+	pub new_changed: String,
+}
+
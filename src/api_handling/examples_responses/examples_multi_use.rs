@@ -0,0 +1,12 @@
+#[derive(Deserialize, Debug)]
+pub struct Envelope<T> {
+	#[serde(rename = "Body")]
+	pub body: Body<T>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Body<T> { 
+	#[serde(alias = "GetSpecificHostEntryResponse")]
+	#[serde(alias = "GetKeyResponse")]
+	pub response: T,
+}
\ No newline at end of file
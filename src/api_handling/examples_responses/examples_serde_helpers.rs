@@ -0,0 +1,87 @@
+use serde::{Deserialize, Deserializer};
+
+/// Parses the text of an UPnP element into the Rust type of a response field.
+pub trait FromUpnp: Sized {
+    fn from_upnp(value: &str) -> Result<Self, String>;
+}
+
+impl FromUpnp for String {
+    fn from_upnp(value: &str) -> Result<Self, String> {
+        Ok(value.to_string())
+    }
+}
+
+/// Deserializes an UPnP element with `FromUpnp`.
+pub fn upnp<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromUpnp,
+{
+    let value = String::deserialize(deserializer)?;
+    T::from_upnp(&value).map_err(serde::de::Error::custom)
+}
+
+impl FromUpnp for u32 {
+    fn from_upnp(value: &str) -> Result<Self, String> {
+        value
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid UPnP unsigned integer `{}`: {}", value, e))
+    }
+}
+
+impl FromUpnp for bool {
+    fn from_upnp(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "1" | "true" | "yes" => Ok(true),
+            "0" | "false" | "no" => Ok(false),
+            other => Err(format!("invalid UPnP boolean `{}`", other)),
+        }
+    }
+}
+
+/// Decodes the text of a `bin.base64` element. Whitespace is ignored, the rest has to be complete
+/// groups of four characters with at most two `=` of padding at the end.
+pub fn decode_base64(value: &str) -> Result<Vec<u8>, String> {
+    let text: Vec<char> = value.chars().filter(|c| !c.is_whitespace()).collect();
+    if text.len() % 4 != 0 {
+        return Err(format!("invalid UPnP bin.base64 `{}`: incomplete group", value));
+    }
+    let padding = text.iter().rev().take_while(|c| **c == '=').count();
+    if padding > 2 {
+        return Err(format!("invalid UPnP bin.base64 `{}`: too much padding", value));
+    }
+    let mut result = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in &text[..text.len() - padding] {
+        let sextet = match *c {
+            'A'..='Z' => *c as u32 - 'A' as u32,
+            'a'..='z' => *c as u32 - 'a' as u32 + 26,
+            '0'..='9' => *c as u32 - '0' as u32 + 52,
+            '+' => 62,
+            '/' => 63,
+            _ => return Err(format!("invalid UPnP bin.base64 character `{}`", c)),
+        };
+        buffer = (buffer << 6) | sextet;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    if buffer != 0 {
+        return Err(format!("invalid UPnP bin.base64 `{}`: non-zero trailing bits", value));
+    }
+    Ok(result)
+}
+
+/// Deserializes a `bin.base64` element into its bytes.
+pub fn base64<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    decode_base64(&value).map_err(serde::de::Error::custom)
+}
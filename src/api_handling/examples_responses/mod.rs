@@ -0,0 +1,3 @@
+pub mod examples_hosts;
+pub mod examples_multi_use;
+pub mod examples_serde_helpers;
@@ -0,0 +1,829 @@
+use crate::api_handling::cache::{CachedDescription, DescriptionCache};
+use crate::api_handling::digest::Challenge;
+use crate::api_handling::url::origin;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{
+    HeaderMap, HeaderValue, AUTHORIZATION, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+    WWW_AUTHENTICATE,
+};
+use reqwest::redirect::Policy;
+use reqwest::{Proxy, StatusCode};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{Certificate, ClientConfig, RootCertStore, ServerName};
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How the certificate of the box is checked when fetching over HTTPS.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CertificateCheck {
+    /// SHA-256 fingerprint of the box certificate. Any certificate with this fingerprint is
+    /// accepted, regardless of its issuer, validity and host names. Suits the self-signed certificate of the box.
+    Fingerprint(Vec<u8>),
+    /// PEM file with the CA certificates the box certificate has to be issued by.
+    /// The certificate also has to be valid for the host name or IP address used.
+    CaFile(String),
+}
+
+impl CertificateCheck {
+    /// Parses a SHA-256 fingerprint in hex, with or without `:` between the bytes,
+    /// e.g. `3A:7F:...` as shown by browsers or `openssl x509 -fingerprint -sha256`.
+    pub fn fingerprint(input: &str) -> Result<Self, String> {
+        let digits: String = input
+            .chars()
+            .filter(|c| *c != ':' && !c.is_whitespace())
+            .collect();
+        if digits.len() != 64 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "`{}` is no SHA-256 fingerprint, expected 32 hex encoded bytes",
+                input
+            ));
+        }
+        let bytes = (0..digits.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&digits[index..index + 2], 16).unwrap())
+            .collect();
+        Ok(CertificateCheck::Fingerprint(bytes))
+    }
+}
+
+/// Username and password for descriptions which are only returned to authenticated clients.
+/// The password is never printed, not even by `Debug`.
+#[derive(Clone, PartialEq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+impl Credentials {
+    /// Reads `FRITZ_USERNAME` and `FRITZ_PASSWORD`. `None` if the password is not set,
+    /// the username may be empty as FRITZ!Boxes can be configured to only ask for a password.
+    pub fn from_env() -> Option<Self> {
+        let password = std::env::var("FRITZ_PASSWORD").ok()?;
+        Some(Credentials {
+            username: std::env::var("FRITZ_USERNAME").unwrap_or_default(),
+            password,
+        })
+    }
+
+    /// Reads the credentials from `path`. A missing file means no credentials.
+    pub fn from_file(path: &str) -> Result<Option<Self>, String> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Self::parse(&content)
+                .map(Some)
+                .map_err(|e| format!("{}: {}", path, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("{}: {}", path, e)),
+        }
+    }
+
+    /// Parses a credentials file with the lines `username = <username>` and `password = <password>`.
+    /// Empty lines and lines starting with `#` are ignored. Errors never contain the password.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut username = String::new();
+        let mut password = None;
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.find('=') {
+                Some(position) => (line[..position].trim(), line[position + 1..].trim()),
+                None => return Err(format!("line {}: expected `<key> = <value>`", number + 1)),
+            };
+            match key {
+                "username" => username = value.to_string(),
+                "password" => password = Some(value.to_string()),
+                _ => return Err(format!("line {}: unknown key `{}`", number + 1, key)),
+            }
+        }
+        match password {
+            Some(password) => Ok(Credentials { username, password }),
+            None => Err("no password given".to_string()),
+        }
+    }
+}
+
+/// How descriptions are fetched from the box.
+#[derive(Debug)]
+pub struct FetchOptions {
+    /// Fetch everything over HTTPS on this port, usually `49443`. `http` URLs are rewritten,
+    /// nothing is fetched in plain text. `None` fetches the URLs as they are.
+    pub https_port: Option<u16>,
+    /// Required with `https_port`.
+    pub certificate_check: Option<CertificateCheck>,
+    /// Used with HTTP digest authentication when the box asks for it.
+    pub credentials: Option<Credentials>,
+    /// Limit for connecting and for each whole request.
+    pub timeout: Duration,
+    /// How often a request is repeated after a connection error, a timeout or `503 Service Unavailable`.
+    pub retries: u32,
+    /// Wait before the first retry, doubled for every further one.
+    pub retry_delay: Duration,
+    /// Maximum number of descriptions fetched at the same time.
+    pub concurrency: usize,
+    /// Send all requests through this proxy, e.g. `http://proxy:3128`.
+    pub proxy: Option<String>,
+    /// Keep service descriptions in this folder, see `DescriptionCache`.
+    pub cache_folder: Option<String>,
+}
+
+impl FetchOptions {
+    pub fn new() -> Self {
+        FetchOptions {
+            https_port: None,
+            certificate_check: None,
+            credentials: None,
+            timeout: Duration::from_secs(10),
+            retries: 3,
+            retry_delay: Duration::from_millis(500),
+            concurrency: 4,
+            proxy: None,
+            cache_folder: None,
+        }
+    }
+}
+
+/// A failed fetch of a description.
+#[derive(Debug)]
+pub struct FetchError {
+    pub url: String,
+    pub reason: String,
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "fetching {} failed: {}", self.url, self.reason)
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// HTTP client for the descriptions, set up according to `FetchOptions`.
+#[derive(Debug)]
+pub struct Fetcher {
+    client: Client,
+    https_port: Option<u16>,
+    credentials: Option<Credentials>,
+    retries: u32,
+    retry_delay: Duration,
+    concurrency: usize,
+    cache_folder: Option<String>,
+}
+
+impl Fetcher {
+    pub fn new(options: &FetchOptions) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut builder = Client::builder()
+            .connect_timeout(options.timeout)
+            .timeout(options.timeout);
+        if let Some(proxy) = &options.proxy {
+            builder = builder.proxy(Proxy::all(proxy.as_str())?);
+        }
+        match (options.https_port, &options.certificate_check) {
+            (None, _) => {}
+            (Some(_), None) => {
+                return Err(
+                    "fetching over HTTPS requires a certificate fingerprint or a CA file".into(),
+                )
+            }
+            (Some(_), Some(check)) => {
+                builder = builder
+                    .use_preconfigured_tls(tls_config(check)?)
+                    .redirect(https_only_redirects())
+            }
+        }
+        if options.concurrency == 0 {
+            return Err("at least one description has to be fetched at a time".into());
+        }
+        Ok(Fetcher {
+            client: builder.build()?,
+            https_port: options.https_port,
+            credentials: options.credentials.clone(),
+            retries: options.retries,
+            retry_delay: options.retry_delay,
+            concurrency: options.concurrency,
+            cache_folder: options.cache_folder.clone(),
+        })
+    }
+
+    /// Maximum number of descriptions to fetch at the same time.
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    /// Opens the description cache for the box `udn` with the firmware `version`,
+    /// `None` if there is no cache folder.
+    pub fn open_cache(
+        &self,
+        udn: &str,
+        version: &str,
+    ) -> Result<Option<DescriptionCache>, Box<dyn std::error::Error>> {
+        match &self.cache_folder {
+            Some(folder) => Ok(Some(
+                DescriptionCache::open(folder, udn, version)
+                    .map_err(|e| format!("opening the cache in {} failed: {}", folder, e))?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Fetches `url` and returns the body. With an HTTPS port, `http` URLs are fetched over HTTPS on that port.
+    /// If the box answers `401 Unauthorized`, the request is repeated with digest authentication.
+    /// Fails if that isn't possible, so descriptions which require authentication are never silently skipped.
+    pub fn get_text(&self, url: &str) -> Result<String, FetchError> {
+        self.get_cached(url, None)
+    }
+
+    /// Fetches `url` like `get_text` and returns the body as it is, e.g. an icon.
+    pub fn get_bytes(&self, url: &str) -> Result<Vec<u8>, FetchError> {
+        let response = self.request(url, HeaderMap::new())?;
+        let body = response.bytes().map_err(|e| FetchError {
+            url: url.to_string(),
+            reason: e.to_string(),
+        })?;
+        Ok(body.to_vec())
+    }
+
+    /// Like `get_text`, but answers from `cache` if possible. Cached descriptions with an `ETag` or
+    /// `Last-Modified` validator are revalidated with a conditional request, the others are used
+    /// without asking the box. Fetched descriptions are stored in `cache`.
+    pub fn get_cached(
+        &self,
+        url: &str,
+        cache: Option<&DescriptionCache>,
+    ) -> Result<String, FetchError> {
+        let cached = cache.and_then(|cache| cache.load(url));
+        let mut headers = HeaderMap::new();
+        if let Some(cached) = &cached {
+            if cached.etag.is_none() && cached.last_modified.is_none() {
+                return Ok(cached.body.clone());
+            }
+            for (name, value) in [
+                (IF_NONE_MATCH, &cached.etag),
+                (IF_MODIFIED_SINCE, &cached.last_modified),
+            ] {
+                if let Some(value) = value.as_ref().and_then(|v| HeaderValue::from_str(v).ok()) {
+                    headers.insert(name, value);
+                }
+            }
+        }
+        let response = self.request(url, headers)?;
+        let validator = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = validator(ETAG);
+        let last_modified = validator(LAST_MODIFIED);
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(cached) = cached {
+                return Ok(cached.body);
+            }
+        }
+        let body = response.text().map_err(|e| FetchError {
+            url: url.to_string(),
+            reason: e.to_string(),
+        })?;
+        if let Some(cache) = cache {
+            let description = CachedDescription {
+                body,
+                etag,
+                last_modified,
+            };
+            if let Err(e) = cache.store(url, &description) {
+                eprintln!("caching {} failed: {}", url, e);
+            }
+            return Ok(description.body);
+        }
+        Ok(body)
+    }
+
+    /// Sends a GET request for `url` with `headers` and returns the successful or
+    /// `304 Not Modified` response, authenticating if the box asks for it.
+    fn request(&self, url: &str, headers: HeaderMap) -> Result<Response, FetchError> {
+        let url = match self.https_port {
+            Some(port) => https_url(url, port),
+            None => url.to_string(),
+        };
+        let error = |reason: String| FetchError {
+            url: url.clone(),
+            reason,
+        };
+        if self.https_port.is_some() && !url.starts_with("https://") {
+            return Err(error("only HTTPS is allowed".to_string()));
+        }
+        let mut response = self.send(&url, &headers).map_err(error)?;
+        if response.status() == StatusCode::UNAUTHORIZED {
+            let credentials = self.credentials.as_ref().ok_or_else(|| {
+                error("the box requires authentication, but no credentials are set".to_string())
+            })?;
+            let challenge = response
+                .headers()
+                .get_all(WWW_AUTHENTICATE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .find_map(Challenge::parse)
+                .ok_or_else(|| {
+                    error("the box requires an unsupported authentication scheme".to_string())
+                })?;
+            let uri = match response.url().query() {
+                Some(query) => format!("{}?{}", response.url().path(), query),
+                None => response.url().path().to_string(),
+            };
+            let authorization =
+                challenge.authorization(&credentials.username, &credentials.password, "GET", &uri);
+            let mut headers = headers;
+            headers.insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&authorization)
+                    .map_err(|_| error("the username can't be sent in a header".to_string()))?,
+            );
+            response = self.send(&url, &headers).map_err(error)?;
+            if response.status() == StatusCode::UNAUTHORIZED {
+                return Err(error("the credentials were rejected".to_string()));
+            }
+        }
+        if !response.status().is_success() && response.status() != StatusCode::NOT_MODIFIED {
+            return Err(error(format!("status {}", response.status())));
+        }
+        Ok(response)
+    }
+
+    /// Sends a GET request, repeating it with exponential backoff after connection errors,
+    /// timeouts and `503 Service Unavailable` until the retries are used up.
+    fn send(&self, url: &str, headers: &HeaderMap) -> Result<Response, String> {
+        let mut attempt = 0;
+        loop {
+            let request = self.client.get(url).headers(headers.clone());
+            let last = attempt == self.retries;
+            match request.send() {
+                Ok(response) if response.status() != StatusCode::SERVICE_UNAVAILABLE || last => {
+                    return Ok(response)
+                }
+                Err(e) if !(e.is_connect() || e.is_timeout()) => return Err(e.to_string()),
+                Err(e) if last => {
+                    return Err(format!("{} (gave up after {} retries)", e, self.retries))
+                }
+                _ => {}
+            }
+            thread::sleep(self.retry_delay * 2u32.saturating_pow(attempt));
+            attempt += 1;
+        }
+    }
+}
+
+/// Returns `url` with scheme `https` and `port` if it is an `http` URL, e.g.
+/// `https://fritz.box:49443/tr64desc.xml` for `http://fritz.box:49000/tr64desc.xml`.
+fn https_url(url: &str, port: u16) -> String {
+    let url_origin = match origin(url) {
+        Some(url_origin) if url_origin.starts_with("http://") => url_origin,
+        _ => return url.to_string(),
+    };
+    let authority = &url_origin["http://".len()..];
+    let host_end = if authority.starts_with('[') {
+        // IPv6 address, the port follows the closing bracket.
+        authority
+            .find(']')
+            .map_or(authority.len(), |position| position + 1)
+    } else {
+        authority.rfind(':').unwrap_or(authority.len())
+    };
+    format!(
+        "https://{}:{}{}",
+        &authority[..host_end],
+        port,
+        &url[url_origin.len()..]
+    )
+}
+
+/// Returns the redirect policy with an HTTPS port: redirects are followed like by default, but
+/// only to `https` URLs, so a redirect can't make the client fall back to plain text.
+fn https_only_redirects() -> Policy {
+    Policy::custom(|attempt| {
+        if attempt.url().scheme() != "https" {
+            let error = format!("redirect to {} isn't HTTPS", attempt.url());
+            attempt.error(error)
+        } else if attempt.previous().len() >= 10 {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Builds the TLS configuration checking the box certificate with `check`.
+fn tls_config(check: &CertificateCheck) -> Result<ClientConfig, Box<dyn std::error::Error>> {
+    let builder = ClientConfig::builder().with_safe_defaults();
+    let config = match check {
+        CertificateCheck::Fingerprint(fingerprint) => builder
+            .with_custom_certificate_verifier(Arc::new(FingerprintVerifier {
+                fingerprint: fingerprint.clone(),
+            }))
+            .with_no_client_auth(),
+        CertificateCheck::CaFile(path) => {
+            let mut reader =
+                BufReader::new(File::open(path).map_err(|e| format!("{}: {}", path, e))?);
+            let certificates =
+                rustls_pemfile::certs(&mut reader).map_err(|e| format!("{}: {}", path, e))?;
+            let mut roots = RootCertStore::empty();
+            let (added, _) = roots.add_parsable_certificates(&certificates);
+            if added == 0 {
+                return Err(format!("{}: no usable CA certificate found", path).into());
+            }
+            builder.with_root_certificates(roots).with_no_client_auth()
+        }
+    };
+    Ok(config)
+}
+
+/// Accepts exactly the certificate with the pinned SHA-256 fingerprint. The handshake signatures
+/// are still checked against it by rustls.
+struct FingerprintVerifier {
+    fingerprint: Vec<u8>,
+}
+
+impl ServerCertVerifier for FingerprintVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let digest = ring::digest::digest(&ring::digest::SHA256, &end_entity.0);
+        if digest.as_ref() == self.fingerprint.as_slice() {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            let presented: Vec<String> = digest
+                .as_ref()
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect();
+            Err(rustls::Error::General(format!(
+                "certificate fingerprint {} doesn't match the pinned fingerprint",
+                presented.join(":")
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    /// Answers one request per status in `statuses` and returns the URL of the server.
+    fn serve(statuses: &'static [&'static str]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/tr64desc.xml", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = [0u8; 1024];
+                let _ = stream.read(&mut buffer).unwrap();
+                let body = "<root/>";
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    /// Answers one request per raw HTTP response in `responses` and returns the URL of the
+    /// server and a receiver of the requests it got.
+    fn serve_raw(responses: Vec<String>) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/tr64desc.xml", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = [0u8; 1024];
+                let length = stream.read(&mut buffer).unwrap();
+                sender
+                    .send(String::from_utf8_lossy(&buffer[..length]).to_lowercase())
+                    .unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, receiver)
+    }
+
+    fn fast_retries(retries: u32) -> Fetcher {
+        let mut options = FetchOptions::new();
+        options.retries = retries;
+        options.retry_delay = Duration::from_millis(1);
+        Fetcher::new(&options).unwrap()
+    }
+
+    #[test]
+    fn retries_service_unavailable() {
+        let url = serve(&[
+            "503 Service Unavailable",
+            "503 Service Unavailable",
+            "200 OK",
+        ]);
+        assert_eq!(fast_retries(2).get_text(&url).unwrap(), "<root/>");
+
+        let url = serve(&["503 Service Unavailable", "503 Service Unavailable"]);
+        let error = fast_retries(1).get_text(&url).unwrap_err();
+        assert!(error.reason.contains("503"), "{}", error);
+    }
+
+    #[test]
+    fn revalidates_cached_descriptions() {
+        const LAST_MODIFIED: &str = "Mon, 01 Jan 2024 00:00:00 GMT";
+        let ok = |etag: &str, body: &str| {
+            format!(
+                "HTTP/1.1 200 OK\r\nETag: {}\r\nLast-Modified: {}\r\nContent-Length: {}\r\n\
+                 Connection: close\r\n\r\n{}",
+                etag,
+                LAST_MODIFIED,
+                body.len(),
+                body
+            )
+        };
+        let (url, requests) = serve_raw(vec![
+            ok("\"1\"", "<root>1</root>"),
+            "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string(),
+            ok("\"2\"", "<root>2</root>"),
+        ]);
+        let root = std::env::temp_dir().join(format!("fetch_cache_{}", std::process::id()));
+        let root = root.to_str().unwrap();
+        let cache = DescriptionCache::open(root, "uuid:1", "154.07.29").unwrap();
+        let fetcher = fast_retries(0);
+
+        assert_eq!(
+            fetcher.get_cached(&url, Some(&cache)).unwrap(),
+            "<root>1</root>"
+        );
+        let request = requests.recv().unwrap();
+        assert!(!request.contains("if-none-match"), "{}", request);
+        assert!(!request.contains("if-modified-since"), "{}", request);
+
+        assert_eq!(
+            fetcher.get_cached(&url, Some(&cache)).unwrap(),
+            "<root>1</root>"
+        );
+        let request = requests.recv().unwrap();
+        assert!(request.contains("if-none-match: \"1\"\r\n"), "{}", request);
+        assert!(
+            request.contains(&format!(
+                "if-modified-since: {}\r\n",
+                LAST_MODIFIED.to_lowercase()
+            )),
+            "{}",
+            request
+        );
+
+        assert_eq!(
+            fetcher.get_cached(&url, Some(&cache)).unwrap(),
+            "<root>2</root>"
+        );
+        assert!(requests
+            .recv()
+            .unwrap()
+            .contains("if-none-match: \"1\"\r\n"));
+        let cached = cache.load(&url).unwrap();
+        assert_eq!(cached.body, "<root>2</root>");
+        assert_eq!(cached.etag.as_deref(), Some("\"2\""));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn digest_authentication() {
+        let challenge = "HTTP/1.1 401 Unauthorized\r\n\
+                         WWW-Authenticate: Basic realm=\"box\"\r\n\
+                         WWW-Authenticate: Digest realm=\"box\", nonce=\"abc\", opaque=\"xyz\"\r\n\
+                         Content-Length: 0\r\nConnection: close\r\n\r\n";
+        let ok = "HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\n<root/>";
+        let (url, requests) = serve_raw(vec![challenge.to_string(), ok.to_string()]);
+        let mut options = FetchOptions::new();
+        options.credentials = Some(Credentials {
+            username: "admin".to_string(),
+            password: "secret".to_string(),
+        });
+        assert_eq!(
+            Fetcher::new(&options).unwrap().get_text(&url).unwrap(),
+            "<root/>"
+        );
+
+        assert!(!requests.recv().unwrap().contains("authorization"));
+        let ha1 = format!("{:x}", md5::compute("admin:box:secret"));
+        let ha2 = format!("{:x}", md5::compute("GET:/tr64desc.xml"));
+        let response = format!("{:x}", md5::compute(format!("{}:abc:{}", ha1, ha2)));
+        let request = requests.recv().unwrap();
+        assert!(
+            request.contains(&format!(
+                "authorization: digest username=\"admin\", realm=\"box\", nonce=\"abc\", \
+                 uri=\"/tr64desc.xml\", algorithm=md5, response=\"{}\", opaque=\"xyz\"\r\n",
+                response
+            )),
+            "{}",
+            request
+        );
+
+        let (url, _requests) = serve_raw(vec![challenge.to_string()]);
+        let error = fast_retries(0).get_text(&url).unwrap_err();
+        assert!(error.reason.contains("no credentials are set"), "{}", error);
+    }
+
+    #[test]
+    fn no_retries_for_other_errors() {
+        let url = serve(&["404 Not Found", "200 OK"]);
+        let error = fast_retries(3).get_text(&url).unwrap_err();
+        assert!(error.reason.contains("404"), "{}", error);
+    }
+
+    #[test]
+    fn fingerprints() {
+        let expected = CertificateCheck::Fingerprint((0..32).collect());
+        let hex: String = (0..32u8).map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(CertificateCheck::fingerprint(&hex), Ok(expected.clone()));
+        let colons: Vec<String> = (0..32u8).map(|byte| format!("{:02X}", byte)).collect();
+        assert_eq!(
+            CertificateCheck::fingerprint(&colons.join(":")),
+            Ok(expected)
+        );
+        assert!(CertificateCheck::fingerprint("AB:CD").is_err());
+        assert!(CertificateCheck::fingerprint(&"x".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn https_urls() {
+        assert_eq!(
+            https_url("http://fritz.box:49000/tr64desc.xml", 49443),
+            "https://fritz.box:49443/tr64desc.xml"
+        );
+        assert_eq!(
+            https_url("http://192.168.178.1/igddesc.xml", 49443),
+            "https://192.168.178.1:49443/igddesc.xml"
+        );
+        assert_eq!(
+            https_url("http://[fd00::1]:49000/tr64desc.xml", 8443),
+            "https://[fd00::1]:8443/tr64desc.xml"
+        );
+        assert_eq!(
+            https_url("https://fritz.box:49443/tr64desc.xml", 8443),
+            "https://fritz.box:49443/tr64desc.xml"
+        );
+    }
+
+    #[test]
+    fn https_requires_certificate_check() {
+        let mut options = FetchOptions::new();
+        options.https_port = Some(49443);
+        assert!(Fetcher::new(&options).is_err());
+        options.certificate_check = Some(CertificateCheck::CaFile("missing.pem".to_string()));
+        assert!(Fetcher::new(&options).is_err());
+    }
+
+    const CERTIFICATE: &str = include_str!("test_certificate/certificate.pem");
+    const KEY: &str = include_str!("test_certificate/key.pem");
+
+    /// Answers one TLS connection per raw HTTP response in `responses` with the self-signed
+    /// `CERTIFICATE` and returns the port of the server. Failed handshakes are ignored.
+    fn serve_tls(responses: Vec<String>) -> u16 {
+        let certificates = rustls_pemfile::certs(&mut CERTIFICATE.as_bytes()).unwrap();
+        let key = rustls_pemfile::pkcs8_private_keys(&mut KEY.as_bytes()).unwrap();
+        let config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(
+                certificates.into_iter().map(Certificate).collect(),
+                rustls::PrivateKey(key[0].clone()),
+            )
+            .unwrap();
+        let config = Arc::new(config);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let connection = rustls::ServerConnection::new(config.clone()).unwrap();
+                let mut stream = rustls::StreamOwned::new(connection, stream);
+                let mut request = vec![];
+                let mut buffer = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buffer) {
+                        Ok(length) if length > 0 => request.extend_from_slice(&buffer[..length]),
+                        _ => break,
+                    }
+                }
+                if request.ends_with(b"\r\n\r\n") {
+                    let _ = stream.write_all(response.as_bytes());
+                    stream.conn.send_close_notify();
+                    let _ = stream.flush();
+                }
+            }
+        });
+        port
+    }
+
+    fn https_fetcher(port: u16, fingerprint: Vec<u8>) -> Fetcher {
+        let mut options = FetchOptions::new();
+        options.https_port = Some(port);
+        options.certificate_check = Some(CertificateCheck::Fingerprint(fingerprint));
+        options.retries = 0;
+        Fetcher::new(&options).unwrap()
+    }
+
+    fn certificate_fingerprint() -> Vec<u8> {
+        let certificate = &rustls_pemfile::certs(&mut CERTIFICATE.as_bytes()).unwrap()[0];
+        ring::digest::digest(&ring::digest::SHA256, certificate)
+            .as_ref()
+            .to_vec()
+    }
+
+    #[test]
+    fn pinned_fingerprints() {
+        let ok = "HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\n<root/>";
+        let port = serve_tls(vec![ok.to_string(), ok.to_string()]);
+        let url = "http://127.0.0.1:49000/tr64desc.xml";
+        let fetcher = https_fetcher(port, certificate_fingerprint());
+        assert_eq!(fetcher.get_text(url).unwrap(), "<root/>");
+
+        let mut other = certificate_fingerprint();
+        other[0] ^= 1;
+        let error = https_fetcher(port, other).get_text(url).unwrap_err();
+        assert!(
+            error
+                .reason
+                .contains("doesn't match the pinned fingerprint"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn no_redirects_to_plain_text() {
+        let plain = TcpListener::bind("127.0.0.1:0").unwrap();
+        plain.set_nonblocking(true).unwrap();
+        let redirect = |location: String| {
+            format!(
+                "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\
+                 Connection: close\r\n\r\n",
+                location
+            )
+        };
+        let port = serve_tls(vec![
+            redirect(format!(
+                "http://{}/tr64desc.xml",
+                plain.local_addr().unwrap()
+            )),
+            redirect("/igddesc.xml".to_string()),
+            "HTTP/1.1 200 OK\r\nContent-Length: 7\r\nConnection: close\r\n\r\n<root/>".to_string(),
+        ]);
+        let fetcher = https_fetcher(port, certificate_fingerprint());
+        let error = fetcher
+            .get_text("http://127.0.0.1:49000/tr64desc.xml")
+            .unwrap_err();
+        assert!(error.reason.contains("isn't HTTPS"), "{}", error);
+        assert!(plain.accept().is_err(), "the plain text URL was requested");
+
+        // Redirects to HTTPS are still followed.
+        assert_eq!(
+            fetcher
+                .get_text("http://127.0.0.1:49000/tr64desc.xml")
+                .unwrap(),
+            "<root/>"
+        );
+    }
+
+    #[test]
+    fn credentials_file() {
+        let credentials =
+            Credentials::parse("# box\nusername = admin\n\npassword = a=b c\n").unwrap();
+        assert_eq!(credentials.username, "admin");
+        assert_eq!(credentials.password, "a=b c");
+        assert!(!format!("{:?}", credentials).contains("a=b c"));
+        assert!(Credentials::parse("username = admin").is_err());
+        assert!(Credentials::parse("user = admin\npassword = x").is_err());
+        assert_eq!(Credentials::from_file("missing.txt"), Ok(None));
+    }
+}
@@ -0,0 +1,424 @@
+//! Selection of the services and actions to generate, to keep the bindings small.
+
+use crate::api_handling::api_desc::Action;
+use crate::api_handling::api_desc_dir::{Service, ServiceDescription};
+use crate::api_handling::naming::action_item;
+use std::fs;
+use std::str::FromStr;
+
+/// Name prefixes of actions which change the device, after a vendor prefix like `X_AVM-DE_`.
+const MUTATING_PREFIXES: &[&str] = &[
+    "Set",
+    "Add",
+    "Delete",
+    "Remove",
+    "Clear",
+    "Create",
+    "Update",
+    "Reset",
+    "FactoryReset",
+    "Reboot",
+    "Reconnect",
+    "Request",
+    "Force",
+    "Enable",
+    "Disable",
+    "Start",
+    "Stop",
+    "Do",
+    "Dial",
+    "Hangup",
+    "Send",
+    "Wake",
+];
+
+/// Name prefixes of actions which are never generated for the read-only profile, even if allowlisted.
+const FORBIDDEN_PREFIXES: &[&str] = &[
+    "Set",
+    "Add",
+    "Delete",
+    "Remove",
+    "Reset",
+    "FactoryReset",
+    "Reboot",
+];
+
+/// Which actions are generated, by their effect on the device, see `is_read_only`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ActionKinds {
+    All,
+    ReadOnly,
+    Mutating,
+}
+
+impl FromStr for ActionKinds {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "all" => Ok(ActionKinds::All),
+            "read-only" => Ok(ActionKinds::ReadOnly),
+            "mutating" => Ok(ActionKinds::Mutating),
+            _ => Err(format!(
+                "unknown action kinds `{}`, expected one of `all`, `read-only` and `mutating`",
+                input
+            )),
+        }
+    }
+}
+
+/// Include and exclude patterns for services and actions, in which `*` stands for any text.
+/// Everything is selected if the include patterns are empty, exclude patterns win over include patterns.
+#[derive(Debug)]
+pub struct Filter {
+    /// Patterns matched against the service type and the service id, e.g. `*:service:Hosts:1`.
+    pub include_services: Vec<String>,
+    pub exclude_services: Vec<String>,
+    /// Patterns matched against the action name, e.g. `X_AVM-DE_*`.
+    pub include_actions: Vec<String>,
+    pub exclude_actions: Vec<String>,
+    pub action_kinds: ActionKinds,
+    /// Actions selected as read-only although `is_read_only` says otherwise.
+    pub allowlist: ReadOnlyAllowlist,
+}
+
+impl Filter {
+    /// Returns a filter selecting everything.
+    pub fn new() -> Self {
+        Filter {
+            include_services: vec![],
+            exclude_services: vec![],
+            include_actions: vec![],
+            exclude_actions: vec![],
+            action_kinds: ActionKinds::All,
+            allowlist: ReadOnlyAllowlist::new(),
+        }
+    }
+
+    pub fn selects_service(&self, service: &Service) -> bool {
+        let matches = |pattern: &String| {
+            matches(pattern, &service.service_type) || matches(pattern, &service.service_id)
+        };
+        (self.include_services.is_empty() || self.include_services.iter().any(matches))
+            && !self.exclude_services.iter().any(matches)
+    }
+
+    pub fn selects_action(&self, service: &Service, action: &Action) -> bool {
+        let matches = |pattern: &String| matches(pattern, &action.name);
+        let read_only = is_read_only(action) || self.allowlist.allows(service, action);
+        let kind = match self.action_kinds {
+            ActionKinds::All => true,
+            ActionKinds::ReadOnly => read_only,
+            ActionKinds::Mutating => !read_only,
+        };
+        kind && (self.include_actions.is_empty() || self.include_actions.iter().any(matches))
+            && !self.exclude_actions.iter().any(matches)
+    }
+
+    /// Removes the actions the filter doesn't select from the SCPDs of `services`. Services which
+    /// are left without actions are removed, so no empty modules are generated.
+    pub fn select(&self, services: Vec<ServiceDescription>) -> Vec<ServiceDescription> {
+        services
+            .into_iter()
+            .filter_map(|mut service| {
+                let info = &service.service;
+                let actions = &mut service.scpd.action_list.action;
+                let declared = actions.len();
+                actions.retain(|action| self.selects_action(info, action));
+                if declared > 0 && actions.is_empty() {
+                    None
+                } else {
+                    Some(service)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Actions the read-only profile generates although `is_read_only` says they change the device.
+/// Read from a file with one action item per line, e.g. `urn:dslforum-org:service:Hosts#X_AVM-DE_GetHostListPath`,
+/// see `naming::action_item`. Empty lines and lines starting with `#` are ignored. Actions with
+/// names like `Set*`, `Delete*` or `Reboot` can't be allowlisted.
+#[derive(Debug, Default)]
+pub struct ReadOnlyAllowlist {
+    items: Vec<String>,
+}
+
+impl ReadOnlyAllowlist {
+    pub fn new() -> Self {
+        ReadOnlyAllowlist { items: vec![] }
+    }
+
+    /// Reads the allowlist from `path`. A missing file means an empty allowlist.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(Self::parse(&content).map_err(|e| format!("{}: {}", path, e))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Parses the content of an allowlist file.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut allowlist = Self::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let action = match line.split_once('#') {
+                Some((service, action)) if !service.is_empty() && !action.is_empty() => action,
+                _ => {
+                    return Err(format!(
+                        "line {}: expected `<service type without version>#<action>`, found `{}`",
+                        number + 1,
+                        line
+                    ))
+                }
+            };
+            if is_forbidden(action) {
+                return Err(format!(
+                    "line {}: `{}` changes the device and can't be allowlisted",
+                    number + 1,
+                    action
+                ));
+            }
+            allowlist.items.push(line.to_string());
+        }
+        Ok(allowlist)
+    }
+
+    pub fn allows(&self, service: &Service, action: &Action) -> bool {
+        !is_forbidden(&action.name) && self.items.contains(&action_item(service, action))
+    }
+}
+
+/// Checks that `services` only have actions which `is_read_only` or `allowlist` allows, as the
+/// read-only profile requires. Lists the other actions otherwise.
+pub fn check_read_only(
+    services: &[ServiceDescription],
+    allowlist: &ReadOnlyAllowlist,
+) -> Result<(), String> {
+    let mut mutating = vec![];
+    for service in services {
+        for action in &service.scpd.action_list.action {
+            if !(is_read_only(action) || allowlist.allows(&service.service, action)) {
+                mutating.push(action_item(&service.service, action));
+            }
+        }
+    }
+    if mutating.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "the read-only profile can't generate the actions {}",
+            mutating.join(", ")
+        ))
+    }
+}
+
+/// Vendor prefixes of action names: `X_AVM-DE_` of TR-064, `X_AVM_DE_` of IGD and the generic `X_`.
+const VENDOR_PREFIXES: &[&str] = &["X_AVM-DE_", "X_AVM_DE_", "X_"];
+
+/// Returns `name` without a vendor prefix like `X_AVM-DE_`, see `VENDOR_PREFIXES`.
+fn without_vendor_prefix(name: &str) -> &str {
+    VENDOR_PREFIXES
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .unwrap_or(name)
+}
+
+/// Whether the action `name` must never be generated for the read-only profile.
+fn is_forbidden(name: &str) -> bool {
+    let name = without_vendor_prefix(name);
+    FORBIDDEN_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+/// Whether `action` only reads from the device: its name starts with `Get`, or it has no `in`
+/// arguments and its name doesn't start with a verb like `Set`, `Delete` or `Reboot`.
+/// Vendor prefixes like `X_AVM-DE_` are skipped.
+pub fn is_read_only(action: &Action) -> bool {
+    let name = without_vendor_prefix(&action.name);
+    if name.starts_with("Get") {
+        return true;
+    }
+    !MUTATING_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+        && !action
+            .argument_list
+            .argument
+            .iter()
+            .any(|argument| argument.direction == "in")
+}
+
+/// Whether `text` matches `pattern`, in which `*` stands for any text, including none.
+fn matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match text.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    let last = match parts.split_last() {
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(position) => rest = &rest[position + part.len()..],
+                    None => return false,
+                }
+            }
+            last
+        }
+        // Without `*` the whole text has to match.
+        None => return rest.is_empty(),
+    };
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_handling::api_desc::ApiDesc;
+    use crate::api_handling::description_set::fixtures::{action, scpd, set, variable};
+
+    #[test]
+    fn patterns() {
+        assert!(matches("GetInfo", "GetInfo"));
+        assert!(!matches("GetInfo", "GetInfos"));
+        assert!(matches(
+            "*:service:Hosts:*",
+            "urn:dslforum-org:service:Hosts:1"
+        ));
+        assert!(matches("X_AVM-DE_*", "X_AVM-DE_GetHostListPath"));
+        assert!(matches("*Host*Path", "X_AVM-DE_GetHostListPath"));
+        assert!(!matches("*Host*Paths", "X_AVM-DE_GetHostListPath"));
+        assert!(!matches("a*bb", "ab"));
+        assert!(matches("*", ""));
+    }
+
+    #[test]
+    fn read_only_actions() {
+        let parsed = |name: &str, arguments: &[(&str, &str, &str)]| {
+            let xml = scpd(&action(name, arguments), &variable("X", "string", &[]));
+            let scpd: ApiDesc = serde_xml_rs::from_str(&xml).unwrap();
+            scpd.action_list.action[0].clone()
+        };
+        let mac = ("NewMACAddress", "in", "X");
+        assert!(is_read_only(&parsed("GetSpecificHostEntry", &[mac])));
+        assert!(is_read_only(&parsed("X_AVM-DE_GetHostListPath", &[])));
+        assert!(is_read_only(&parsed("GetInfo", &[])));
+        assert!(!is_read_only(&parsed("Reboot", &[])));
+        assert!(!is_read_only(&parsed("X_AVM-DE_DoUpdate", &[])));
+        assert!(!is_read_only(&parsed(
+            "SetEnable",
+            &[("NewEnable", "in", "X")]
+        )));
+        assert!(!is_read_only(&parsed("WakeOnLAN", &[mac])));
+        assert!(is_read_only(&parsed(
+            "X_AVM_DE_GetExternalIPv6Address",
+            &[]
+        )));
+        assert!(!is_read_only(&parsed("X_AVM_DE_Reboot", &[])));
+        assert!(!is_read_only(&parsed("X_AVM_DE_SetIPv6PinholeEnable", &[])));
+        assert!(!is_read_only(&parsed(
+            "X_AVM_DE_DeleteAllPortMappings",
+            &[]
+        )));
+    }
+
+    #[test]
+    fn selection() {
+        let hosts = scpd(
+            &[
+                action("GetHostNumberOfEntries", &[("NewNumber", "out", "Number")]),
+                action(
+                    "X_AVM-DE_SetHostNameByMACAddress",
+                    &[("NewName", "in", "Name")],
+                ),
+            ]
+            .concat(),
+            &[
+                variable("Number", "ui2", &[]),
+                variable("Name", "string", &[]),
+            ]
+            .concat(),
+        );
+        let info = scpd(&action("Reboot", &[]), &variable("X", "string", &[]));
+        let set = set(&[("Hosts", &hosts), ("DeviceInfo", &info)]);
+        let service = |index: usize| &set.services[index].service;
+
+        let mut filter = Filter::new();
+        assert!(filter.selects_service(service(0)));
+        filter.include_services = vec!["*:Hosts:1".to_string(), "urn:DeviceInfo-com:*".to_string()];
+        assert!(filter.selects_service(service(0)));
+        assert!(filter.selects_service(service(1)));
+        filter.exclude_services = vec!["*DeviceInfo*".to_string()];
+        assert!(!filter.selects_service(service(1)));
+
+        let mut filter = Filter::new();
+        filter.action_kinds = ActionKinds::ReadOnly;
+        assert!(check_read_only(&set.services, &filter.allowlist).is_err());
+        let selected = filter.select(set.services);
+        assert!(check_read_only(&selected, &filter.allowlist).is_ok());
+        assert_eq!(selected.len(), 1);
+        let actions: Vec<&str> = selected[0]
+            .scpd
+            .action_list
+            .action
+            .iter()
+            .map(|action| action.name.as_str())
+            .collect();
+        assert_eq!(actions, vec!["GetHostNumberOfEntries"]);
+    }
+
+    #[test]
+    fn read_only_allowlist() {
+        let allowlist = ReadOnlyAllowlist::parse(
+            "# edge cases\n\nurn:dslforum-org:service:DeviceConfig#X_AVM-DE_CreateUrlSID\n",
+        )
+        .unwrap();
+        let xml = scpd(
+            &[action("X_AVM-DE_CreateUrlSID", &[]), action("Reboot", &[])].concat(),
+            &variable("X", "string", &[]),
+        );
+        let set = set(&[("DeviceConfig", &xml)]);
+        let service = &set.services[0].service;
+        let actions = &set.services[0].scpd.action_list.action;
+        assert!(allowlist.allows(service, &actions[0]));
+        assert!(!allowlist.allows(service, &actions[1]));
+
+        let mut filter = Filter::new();
+        filter.action_kinds = ActionKinds::ReadOnly;
+        filter.allowlist = allowlist;
+        let selected = filter.select(set.services);
+        assert_eq!(selected[0].scpd.action_list.action.len(), 1);
+        assert!(check_read_only(&selected, &filter.allowlist).is_ok());
+
+        let error =
+            ReadOnlyAllowlist::parse("urn:dslforum-org:service:DeviceConfig#X_AVM-DE_SetPassword")
+                .unwrap_err();
+        assert!(error.contains("can't be allowlisted"), "{}", error);
+        assert!(ReadOnlyAllowlist::parse("Reboot").is_err());
+        assert!(ReadOnlyAllowlist::parse(
+            "urn:schemas-upnp-org:service:WANIPConnection#X_AVM_DE_Reboot"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn forbidden_actions() {
+        assert!(is_forbidden("Reboot"));
+        assert!(is_forbidden("X_AVM-DE_SetPassword"));
+        assert!(is_forbidden("X_AVM_DE_Reboot"));
+        assert!(is_forbidden("X_AVM_DE_DeletePortMapping"));
+        assert!(is_forbidden("X_SetConfig"));
+        assert!(!is_forbidden("X_AVM_DE_GetExternalIPv6Address"));
+        assert!(!is_forbidden("X_AVM-DE_CreateUrlSID"));
+        assert_eq!(without_vendor_prefix("X_AVM_DE_Reboot"), "Reboot");
+        assert_eq!(without_vendor_prefix("X_AVM-DE_Reboot"), "Reboot");
+    }
+}
@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// Acronyms used in UPnP and AVM names. Only consulted to decide where a run of capitals
+/// ends, e.g. `WANIPConnection` -> `WAN`, `IP`, `Connection` or `IPTVoptimize` -> `IPTV`, `optimize`.
+/// Unknown acronyms are still handled, they just can't be split further.
+const KNOWN_ACRONYMS: &[&str] = &[
+    "AIN", "AP", "ATM", "ATUC", "ATUR", "AVM", "BSSID", "CGI", "CRC", "DAV", "DDNS", "DE", "DECT",
+    "DHCP", "DNS", "DS", "DSL", "FCS", "FEC", "FTP", "HEC", "HTTP", "HTTPS", "ID", "IGD", "IP",
+    "IPTV", "ISP", "LAN", "LATN", "LKZ", "MAC", "MTU", "NAT", "NTP", "OKZ", "OUI", "PIN", "PPP",
+    "PSK", "RSIP", "RX", "SID", "SIP", "SMB", "SNR", "SNRG", "SNRMT", "SSID", "SSL", "STUN", "TAM",
+    "TR", "TX", "UDN", "URI", "URL", "US", "USB", "UUID", "VLAN", "WAN", "WEP", "WLAN", "WOL",
+    "WPA", "WPS",
+];
+
+/// Mixed case words which must never be split, e.g. `VoIP` would otherwise become `vo_ip`.
+const MIXED_CASE_WORDS: &[&str] = &[
+    "GHz", "IPv4", "IPv6", "PPPoA", "PPPoE", "QoS", "UPnP", "VoIP", "WiFi",
+];
+
+/// Keywords which can be used as raw identifiers (`r#type`).
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// Keywords which can't be raw identifiers and get an underscore appended instead.
+const PATH_KEYWORDS: &[&str] = &["crate", "self", "Self", "super"];
+
+/// Converts an UPnP/AVM name like `NewX_AVM-DE_TotalBytesSent64` into a snake case identifier
+/// (`new_x_avm_de_total_bytes_sent64`). Reserved words and leading digits are escaped.
+pub fn snake_case(input: &str) -> String {
+    let words: Vec<String> = split_words(input)
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect();
+    escape_identifier(words.join("_"))
+}
+
+/// Converts an UPnP name like `WLANConfiguration` into a snake case module name
+/// (`wlan_configuration`). Module names are file names too, so keywords get an underscore
+/// appended instead of becoming raw identifiers.
+pub fn module_name(input: &str) -> String {
+    match snake_case(input) {
+        name if name.starts_with("r#") => format!("{}_", &name[2..]),
+        name => name,
+    }
+}
+
+/// Returns whether `identifier` is a keyword and can't be used as module name as is.
+pub fn is_keyword(identifier: &str) -> bool {
+    KEYWORDS.contains(&identifier) || PATH_KEYWORDS.contains(&identifier)
+}
+
+/// Converts an UPnP/AVM name like `X_AVM-DE_GetDSLInfo` into a pascal case identifier
+/// (`XAvmDeGetDslInfo`). Reserved words and leading digits are escaped.
+pub fn pascal_case(input: &str) -> String {
+    let words: Vec<String> = split_words(input)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => format!("{}{}", first.to_uppercase(), chars.as_str().to_lowercase()),
+                None => String::new(),
+            }
+        })
+        .collect();
+    escape_identifier(words.join(""))
+}
+
+/// Makes `identifier` usable in Rust source: keywords become raw identifiers (or get an
+/// underscore appended if that isn't possible), a leading digit gets an underscore prepended.
+pub fn escape_identifier(identifier: String) -> String {
+    if identifier.is_empty() {
+        return String::from("unnamed");
+    }
+    if identifier.starts_with(|c: char| c.is_ascii_digit()) {
+        return format!("_{}", identifier);
+    }
+    if PATH_KEYWORDS.contains(&identifier.as_str()) {
+        return format!("{}_", identifier);
+    }
+    if KEYWORDS.contains(&identifier.as_str()) {
+        return format!("r#{}", identifier);
+    }
+    identifier
+}
+
+/// Splits `input` into words. Non alphanumeric characters separate words, inside a segment words
+/// are separated at case changes. Digits stick to the word before them.
+pub fn split_words(input: &str) -> Vec<String> {
+    let mut words = vec![];
+    for segment in input
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|segment| !segment.is_empty())
+    {
+        let mut rest = segment;
+        while let Some((position, word)) = find_mixed_case_word(rest) {
+            split_camel_case(&rest[..position], &mut words);
+            words.push(word.to_string());
+            rest = &rest[position + word.len()..];
+        }
+        split_camel_case(rest, &mut words);
+    }
+    words
+}
+
+/// Returns the position of the first mixed case word in `input` which isn't directly followed by
+/// a lowercase letter.
+fn find_mixed_case_word(input: &str) -> Option<(usize, &'static str)> {
+    (0..input.len()).find_map(|position| {
+        MIXED_CASE_WORDS
+            .iter()
+            .find(|word| {
+                input[position..].starts_with(*word)
+                    && !input[position + word.len()..].starts_with(|c: char| c.is_ascii_lowercase())
+            })
+            .map(|word| (position, *word))
+    })
+}
+
+/// Character class of a run, see `split_camel_case`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CharClass {
+    Upper,
+    Lower,
+    Digit,
+}
+
+impl CharClass {
+    fn of(c: char) -> Self {
+        if c.is_ascii_uppercase() {
+            CharClass::Upper
+        } else if c.is_ascii_digit() {
+            CharClass::Digit
+        } else {
+            CharClass::Lower
+        }
+    }
+}
+
+/// Splits a segment without separators and mixed case words at case changes and pushes the
+/// words into `words`.
+fn split_camel_case(input: &str, words: &mut Vec<String>) {
+    let mut runs: Vec<(CharClass, &str)> = vec![];
+    let mut start = 0;
+    for (position, c) in input.char_indices() {
+        let class = CharClass::of(c);
+        match runs.last_mut() {
+            Some((last_class, run)) if *last_class == class => *run = &input[start..=position],
+            _ => {
+                start = position;
+                runs.push((class, &input[position..=position]));
+            }
+        }
+    }
+
+    let mut current = String::new();
+    let mut index = 0;
+    while index < runs.len() {
+        let (class, run) = runs[index];
+        match class {
+            CharClass::Lower | CharClass::Digit => current.push_str(run),
+            CharClass::Upper => {
+                if !current.is_empty() {
+                    words.push(std::mem::take(&mut current));
+                }
+                let next = runs.get(index + 1);
+                let after_next = runs.get(index + 2);
+                match next {
+                    // Versioned acronyms like `TLSv1` stay one word.
+                    Some((CharClass::Lower, "v"))
+                        if run.len() > 1
+                            && after_next.map(|(class, _)| *class) == Some(CharClass::Digit) =>
+                    {
+                        current = format!("{}v{}", run, after_next.unwrap().1);
+                        index += 2;
+                    }
+                    Some((CharClass::Lower, _)) if run.len() > 1 => {
+                        if let Some(acronyms) = split_acronyms(run) {
+                            words.extend(acronyms);
+                        } else {
+                            // The last capital belongs to the following lowercase word.
+                            let (head, tail) = run.split_at(run.len() - 1);
+                            words.extend(
+                                split_acronyms(head).unwrap_or_else(|| vec![head.to_string()]),
+                            );
+                            current = tail.to_string();
+                        }
+                    }
+                    Some((CharClass::Lower, _)) => current = run.to_string(),
+                    _ => {
+                        let mut acronyms =
+                            split_acronyms(run).unwrap_or_else(|| vec![run.to_string()]);
+                        current = acronyms.pop().unwrap();
+                        words.extend(acronyms);
+                    }
+                }
+            }
+        }
+        index += 1;
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+}
+
+/// Splits a run of capitals into known acronyms, preferring as few acronyms as possible.
+/// Returns `None` if the run can't be completely split into known acronyms.
+fn split_acronyms(run: &str) -> Option<Vec<String>> {
+    if run.is_empty() {
+        return Some(vec![]);
+    }
+    let mut best: Option<Vec<String>> = None;
+    for length in (2..=run.len()).rev() {
+        if !KNOWN_ACRONYMS.contains(&&run[..length]) {
+            continue;
+        }
+        if let Some(mut rest) = split_acronyms(&run[length..]) {
+            rest.insert(0, run[..length].to_string());
+            let shorter = match &best {
+                Some(best) => rest.len() < best.len(),
+                None => true,
+            };
+            if shorter {
+                best = Some(rest);
+            }
+        }
+    }
+    best
+}
+
+/// Two different API items which map to the same Rust identifier in one scope.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdentifierCollision {
+    pub identifier: String,
+    pub first_source: String,
+    pub second_source: String,
+}
+
+impl fmt::Display for IdentifierCollision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is used for both {} and {}",
+            self.identifier, self.first_source, self.second_source
+        )
+    }
+}
+
+impl std::error::Error for IdentifierCollision {}
+
+/// Keeps track of the identifiers used in one Rust scope (a module, a struct, a function
+/// signature) and where they came from.
+#[derive(Debug, Default)]
+pub struct IdentifierScope {
+    used: HashMap<String, String>,
+}
+
+impl IdentifierScope {
+    pub fn new() -> Self {
+        IdentifierScope {
+            used: HashMap::new(),
+        }
+    }
+
+    /// Registers `identifier` as generated from `source`.
+    /// `r#type` and `type` are the same identifier to the compiler and collide.
+    pub fn insert(&mut self, identifier: &str, source: &str) -> Result<(), IdentifierCollision> {
+        let key = identifier.trim_start_matches("r#").to_string();
+        if let Some(first_source) = self.used.get(&key) {
+            return Err(IdentifierCollision {
+                identifier: identifier.to_string(),
+                first_source: first_source.clone(),
+                second_source: source.to_string(),
+            });
+        }
+        self.used.insert(key, source.to_string());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_snake(cases: &[(&str, &str)]) {
+        for (input, expected) in cases {
+            assert_eq!(snake_case(input), *expected, "snake case of {}", input);
+        }
+    }
+
+    #[test]
+    fn service_names() {
+        assert_snake(&[
+            ("DeviceInfo", "device_info"),
+            ("DeviceConfig", "device_config"),
+            ("Layer3Forwarding", "layer3_forwarding"),
+            ("LANConfigSecurity", "lan_config_security"),
+            (
+                "LANEthernetInterfaceConfig",
+                "lan_ethernet_interface_config",
+            ),
+            ("LANHostConfigManagement", "lan_host_config_management"),
+            ("WLANConfiguration", "wlan_configuration"),
+            ("Hosts", "hosts"),
+            ("WANCommonInterfaceConfig", "wan_common_interface_config"),
+            ("WANDSLInterfaceConfig", "wan_dsl_interface_config"),
+            ("WANDSLLinkConfig", "wan_dsl_link_config"),
+            ("WANEthernetLinkConfig", "wan_ethernet_link_config"),
+            ("WANIPConnection", "wan_ip_connection"),
+            ("WANPPPConnection", "wan_ppp_connection"),
+            ("WANIPv6FirewallControl", "wan_ipv6_firewall_control"),
+            ("X_AVM-DE_AppSetup", "x_avm_de_app_setup"),
+            ("X_AVM-DE_Homeauto", "x_avm_de_homeauto"),
+            ("X_AVM-DE_OnTel", "x_avm_de_on_tel"),
+            ("X_AVM-DE_TAM", "x_avm_de_tam"),
+            ("X_AVM-DE_UPnP", "x_avm_de_upnp"),
+            ("X_AVM-DE_WebDAVClient", "x_avm_de_web_dav_client"),
+            ("X_VoIP", "x_voip"),
+        ]);
+    }
+
+    #[test]
+    fn action_names() {
+        assert_snake(&[
+            ("GetInfo", "get_info"),
+            ("GetSecurityPort", "get_security_port"),
+            ("GetCommonLinkProperties", "get_common_link_properties"),
+            ("GetDSLInfo", "get_dsl_info"),
+            (
+                "GetGenericPortMappingEntry",
+                "get_generic_port_mapping_entry",
+            ),
+            ("GetExternalIPAddress", "get_external_ip_address"),
+            ("GetNATRSIPStatus", "get_nat_rsip_status"),
+            ("GetSSID", "get_ssid"),
+            (
+                "X_AVM-DE_GetSpecificHostEntryByIP",
+                "x_avm_de_get_specific_host_entry_by_ip",
+            ),
+            (
+                "X_AVM-DE_GetVoIPCommonAreaCode",
+                "x_avm_de_get_voip_common_area_code",
+            ),
+            ("X_AVM-DE_GetIPTVOptimized", "x_avm_de_get_iptv_optimized"),
+            (
+                "X_AVM-DE_GetTR069FirmwareDownloadEnabled",
+                "x_avm_de_get_tr069_firmware_download_enabled",
+            ),
+            (
+                "X_AVM-DE_GetDSLDiagnoseInfo",
+                "x_avm_de_get_dsl_diagnose_info",
+            ),
+            ("X_AVM-DE_GetWPSConfig", "x_avm_de_get_wps_config"),
+            ("X_AVM-DE_GetIPv6Prefix", "x_avm_de_get_ipv6_prefix"),
+            ("X_AVM-DE_GetDDNSInfo", "x_avm_de_get_ddns_info"),
+            ("X_AVM-DE_CreateUrlSID", "x_avm_de_create_url_sid"),
+            (
+                "X_AVM-DE_GetWLANHybridMode",
+                "x_avm_de_get_wlan_hybrid_mode",
+            ),
+        ]);
+    }
+
+    #[test]
+    fn argument_names() {
+        assert_snake(&[
+            ("NewPersistentData", "new_persistent_data"),
+            ("NewExternalIPAddress", "new_external_ip_address"),
+            ("NewMACAddress", "new_mac_address"),
+            ("NewBSSID", "new_bssid"),
+            ("NewSSID", "new_ssid"),
+            ("NewUUID", "new_uuid"),
+            ("NewWPAEncryptionModes", "new_wpa_encryption_modes"),
+            ("NewRSIPAvailable", "new_rsip_available"),
+            ("NewNATEnabled", "new_nat_enabled"),
+            (
+                "NewLayer1DownstreamMaxBitRate",
+                "new_layer1_downstream_max_bit_rate",
+            ),
+            ("NewATUCCRCErrors", "new_atuc_crc_errors"),
+            ("NewATURFECErrors", "new_atur_fec_errors"),
+            ("NewSNRGds", "new_snrg_ds"),
+            ("NewSNRMTus", "new_snrmt_us"),
+            ("NewSNRpsds", "new_snr_psds"),
+            ("NewLATNds", "new_latn_ds"),
+            (
+                "NewX_AVM-DE_TotalBytesSent64",
+                "new_x_avm_de_total_bytes_sent64",
+            ),
+            ("NewX_AVM-DE_IPTVoptimize", "new_x_avm_de_iptv_optimize"),
+            ("NewX_AVM-DE_OKZPrefix", "new_x_avm_de_okz_prefix"),
+            ("NewX_AVM-DE_UPnPEnabled", "new_x_avm_de_upnp_enabled"),
+            (
+                "NewX_AVM_DE_CurrentUserList",
+                "new_x_avm_de_current_user_list",
+            ),
+            (
+                "NewX_AVM-DE_FrequencyBand5GHz",
+                "new_x_avm_de_frequency_band5_ghz",
+            ),
+            ("NewIPv6Address", "new_ipv6_address"),
+            ("NewTLSv1Enabled", "new_tlsv1_enabled"),
+        ]);
+    }
+
+    #[test]
+    fn pascal_case_names() {
+        let cases = [
+            ("GetDSLInfo", "GetDslInfo"),
+            ("GetPersistentData", "GetPersistentData"),
+            (
+                "X_AVM-DE_GetSpecificHostEntryByIP",
+                "XAvmDeGetSpecificHostEntryByIp",
+            ),
+            (
+                "X_AVM-DE_GetVoIPCommonAreaCode",
+                "XAvmDeGetVoipCommonAreaCode",
+            ),
+            ("WANIPv6FirewallControl", "WanIpv6FirewallControl"),
+            (
+                "X_AVM-DE_GetTR069FirmwareDownloadEnabled",
+                "XAvmDeGetTr069FirmwareDownloadEnabled",
+            ),
+        ];
+        for (input, expected) in &cases {
+            assert_eq!(pascal_case(input), *expected, "pascal case of {}", input);
+        }
+    }
+
+    #[test]
+    fn reserved_words_and_digits() {
+        assert_eq!(snake_case("Type"), "r#type");
+        assert_eq!(snake_case("Match"), "r#match");
+        assert_eq!(snake_case("Self"), "self_");
+        assert_eq!(pascal_case("self"), "Self_");
+        assert_eq!(snake_case("5GHzEnabled"), "_5_ghz_enabled");
+        assert_eq!(pascal_case("2ndAddress"), "_2ndAddress");
+        assert_eq!(snake_case("--"), "unnamed");
+        assert_eq!(module_name("Type"), "type_");
+        assert_eq!(module_name("Self"), "self_");
+        assert_eq!(module_name("WLANConfiguration"), "wlan_configuration");
+    }
+
+    #[test]
+    fn scope_collisions() {
+        let mut scope = IdentifierScope::new();
+        scope
+            .insert(&snake_case("NewMACAddress"), "argument NewMACAddress")
+            .unwrap();
+        scope.insert(&snake_case("Type"), "argument Type").unwrap();
+        let collision = scope
+            .insert(&snake_case("NewMacAddress"), "argument NewMacAddress")
+            .unwrap_err();
+        assert_eq!(collision.identifier, "new_mac_address");
+        assert_eq!(collision.first_source, "argument NewMACAddress");
+        assert_eq!(collision.second_source, "argument NewMacAddress");
+        assert!(scope.insert("type", "argument type").is_err());
+    }
+}
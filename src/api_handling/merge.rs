@@ -0,0 +1,382 @@
+//! Merging of the description sets of several box models into one union API, recording which
+//! models declare which services, actions and arguments.
+
+use crate::api_handling::api_desc::{ApiDesc, Argument};
+use crate::api_handling::api_desc_dir::{Device, Service};
+use crate::api_handling::description_set::DescriptionSet;
+use crate::api_handling::filter::Filter;
+use crate::api_handling::naming::{action_item, argument_item, service_item};
+use std::collections::HashMap;
+
+/// Which of the merged models declare which services, actions and arguments, looked up by item
+/// path, see `naming::argument_item`. Empty for a single description set.
+#[derive(Debug, Default)]
+pub struct Availability {
+    /// Model name and firmware version of every merged set, e.g. `("FRITZ!Box 7590", "154.07.29")`.
+    pub models: Vec<(String, String)>,
+    declared: HashMap<String, Vec<usize>>,
+}
+
+impl Availability {
+    pub fn new() -> Self {
+        Availability::default()
+    }
+
+    /// Whether this is the availability of merged sets.
+    pub fn is_merged(&self) -> bool {
+        !self.models.is_empty()
+    }
+
+    /// Returns model name and firmware version of the models declaring `item`.
+    pub fn models(&self, item: &str) -> Vec<(String, String)> {
+        self.declared
+            .get(item)
+            .map(|models| {
+                models
+                    .iter()
+                    .map(|&model| self.models[model].clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Describes the models declaring `item`, e.g. `FRITZ!Box 7590 (FRITZ!OS 154.07.29), FRITZ!Box 6490 Cable`.
+    pub fn describe(&self, item: &str) -> String {
+        self.models(item)
+            .iter()
+            .map(|(name, version)| {
+                if version.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{} (FRITZ!OS {})", name, version)
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// Whether every model declaring `parent` also declares `item`, e.g. an argument of an action.
+    pub fn declared_with(&self, item: &str, parent: &str) -> bool {
+        self.declared.get(item) == self.declared.get(parent)
+    }
+
+    fn declare(&mut self, item: String, model: usize) {
+        let models = self.declared.entry(item).or_default();
+        if !models.contains(&model) {
+            models.push(model);
+        }
+    }
+}
+
+/// Merges `sets` into one set with the services, actions and `out` arguments of all of them.
+/// The root description and services present in several sets are taken from the first set which
+/// has them. Fails if an action has different `in` arguments in two sets, or an argument has
+/// incompatible data types, as no request function or response field could serve both.
+pub fn merge(sets: Vec<DescriptionSet>) -> Result<(DescriptionSet, Availability), String> {
+    let mut availability = Availability::new();
+    let mut merged: Option<DescriptionSet> = None;
+    for (model, set) in sets.into_iter().enumerate() {
+        availability.models.push((
+            set.description.device.model_name.clone(),
+            set.description.system_version.display.clone(),
+        ));
+        for description in &set.services {
+            let service = &description.service;
+            availability.declare(service_item(service), model);
+            for action in &description.scpd.action_list.action {
+                availability.declare(action_item(service, action), model);
+                for argument in &action.argument_list.argument {
+                    availability.declare(argument_item(service, action, argument), model);
+                }
+            }
+        }
+        match merged.as_mut() {
+            None => merged = Some(set),
+            Some(merged) => merge_set(merged, set)?,
+        }
+    }
+    let merged = merged.ok_or("there are no description sets to merge")?;
+    Ok((merged, availability))
+}
+
+fn merge_set(target: &mut DescriptionSet, set: DescriptionSet) -> Result<(), String> {
+    let known_types = target.description.device.service_types(&Filter::new());
+    let mut new_services = vec![];
+    collect_new_services(&set.description.device, &known_types, &mut new_services);
+    target
+        .description
+        .device
+        .service_list
+        .service
+        .extend(new_services);
+    target.source = format!("{} + {}", target.source, set.source);
+
+    let label = set.label();
+    for description in set.services {
+        let item = service_item(&description.service);
+        match target
+            .services
+            .iter_mut()
+            .find(|known| service_item(&known.service) == item)
+        {
+            Some(known) => merge_scpd(&mut known.scpd, &description.scpd, &item, &label)?,
+            None => {
+                // New service types are added to the root device, see `collect_new_services`.
+                let mut description = description;
+                description.service.device_path.clear();
+                target.services.push(description)
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Collects the services of `device` and its embedded devices with types not in `known_types`,
+/// so the root device of the merged set lists every service type, e.g. for discovery.
+fn collect_new_services(device: &Device, known_types: &[String], services: &mut Vec<Service>) {
+    for service in &device.service_list.service {
+        if !known_types.contains(&service.service_type)
+            && !services
+                .iter()
+                .any(|known| known.service_type == service.service_type)
+        {
+            services.push(service.clone());
+        }
+    }
+    for device in &device.device_list.device {
+        collect_new_services(device, known_types, services);
+    }
+}
+
+/// Adds the actions and `out` arguments of `source` missing in `target`.
+fn merge_scpd(
+    target: &mut ApiDesc,
+    source: &ApiDesc,
+    item: &str,
+    label: &str,
+) -> Result<(), String> {
+    for action in &source.action_list.action {
+        let position = target
+            .action_list
+            .action
+            .iter()
+            .position(|known| known.name == action.name);
+        let position = match position {
+            Some(position) => position,
+            None => {
+                for argument in &action.argument_list.argument {
+                    add_state_variable(target, source, argument, item, label)?;
+                }
+                target.action_list.action.push(action.clone());
+                continue;
+            }
+        };
+
+        let in_arguments = |arguments: &[Argument]| -> Vec<String> {
+            arguments
+                .iter()
+                .filter(|argument| argument.direction == "in")
+                .map(|argument| argument.name.clone())
+                .collect()
+        };
+        let known_in = in_arguments(&target.action_list.action[position].argument_list.argument);
+        let source_in = in_arguments(&action.argument_list.argument);
+        if known_in != source_in {
+            return Err(format!(
+                "{}#{} has the in arguments ({}) in {}, but ({}) before",
+                item,
+                action.name,
+                source_in.join(", "),
+                label,
+                known_in.join(", ")
+            ));
+        }
+        for argument in &action.argument_list.argument {
+            add_state_variable(target, source, argument, item, label)?;
+            let arguments = &mut target.action_list.action[position].argument_list.argument;
+            match arguments.iter().find(|known| known.name == argument.name) {
+                Some(known) if known.direction != argument.direction => {
+                    return Err(format!(
+                        "{}#{}.{} is an {} argument in {}, but was an {} argument before",
+                        item,
+                        action.name,
+                        argument.name,
+                        argument.direction,
+                        label,
+                        known.direction
+                    ))
+                }
+                Some(_) => {}
+                None => arguments.push(argument.clone()),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Adds the state variable related to `argument` from `source` to `target`, unless `target` has
+/// a state variable of that name already. Fails if the data types of both aren't compatible.
+fn add_state_variable(
+    target: &mut ApiDesc,
+    source: &ApiDesc,
+    argument: &Argument,
+    item: &str,
+    label: &str,
+) -> Result<(), String> {
+    let variable = match source.state_variable(&argument.related_state_variable) {
+        Some(variable) => variable,
+        None => return Ok(()),
+    };
+    match target.state_variable(&variable.name) {
+        Some(known) if !compatible(&known.data_type, &variable.data_type) => Err(format!(
+            "state variable {} of {} has the data type {} in {}, but {} before",
+            variable.name, item, variable.data_type, label, known.data_type
+        )),
+        Some(_) => Ok(()),
+        None => {
+            target
+                .service_state_table
+                .state_variable
+                .push(variable.clone());
+            Ok(())
+        }
+    }
+}
+
+/// Whether values of both UPnP data types fit the same Rust type and encoding.
+fn compatible(data_type: &str, other: &str) -> bool {
+    let family = |data_type| match data_type {
+        "ui1" | "ui2" | "ui4" => "unsigned",
+        "i1" | "i2" | "i4" => "signed",
+        data_type => data_type,
+    };
+    family(data_type) == family(other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_handling::description_set::fixtures::{action, model_set, scpd, variable};
+
+    #[test]
+    fn union_with_availability() {
+        let hosts_7590 = scpd(
+            &[
+                action(
+                    "GetSpecificHostEntry",
+                    &[
+                        ("NewMACAddress", "in", "MACAddress"),
+                        ("NewIPAddress", "out", "IPAddress"),
+                    ],
+                ),
+                action("GetHostListPath", &[("NewPath", "out", "Path")]),
+            ]
+            .concat(),
+            &[
+                variable("MACAddress", "string", &[]),
+                variable("IPAddress", "string", &[]),
+                variable("Path", "string", &[]),
+            ]
+            .concat(),
+        );
+        let hosts_6490 = scpd(
+            &action(
+                "GetSpecificHostEntry",
+                &[
+                    ("NewMACAddress", "in", "MACAddress"),
+                    ("NewIPAddress", "out", "IPAddress"),
+                    ("NewActive", "out", "Active"),
+                ],
+            ),
+            &[
+                variable("MACAddress", "string", &[]),
+                variable("IPAddress", "string", &[]),
+                variable("Active", "boolean", &[]),
+            ]
+            .concat(),
+        );
+        let time = scpd(
+            &action("GetInfo", &[("NewNTPServer1", "out", "NTPServer")]),
+            &variable("NTPServer", "string", &[]),
+        );
+        let (merged, availability) = merge(vec![
+            model_set("FRITZ!Box 7590", "154.07.29", &[("Hosts", &hosts_7590)]),
+            model_set(
+                "FRITZ!Box 6490 Cable",
+                "141.07.20",
+                &[("Hosts", &hosts_6490), ("Time", &time)],
+            ),
+        ])
+        .unwrap();
+
+        assert_eq!(merged.services.len(), 2);
+        assert_eq!(
+            merged
+                .description
+                .device
+                .service_types(&Filter::new())
+                .len(),
+            2
+        );
+        let hosts = &merged.services[0].scpd;
+        let actions: Vec<&str> = hosts
+            .action_list
+            .action
+            .iter()
+            .map(|action| action.name.as_str())
+            .collect();
+        assert_eq!(actions, vec!["GetSpecificHostEntry", "GetHostListPath"]);
+        assert_eq!(hosts.action_list.action[0].argument_list.argument.len(), 3);
+        assert!(hosts.state_variable("Active").is_some());
+
+        let service = "urn:dslforum-org:service:Hosts";
+        assert_eq!(availability.models(service).len(), 2);
+        assert_eq!(
+            availability.describe(&format!("{}#GetHostListPath", service)),
+            "FRITZ!Box 7590 (FRITZ!OS 154.07.29)"
+        );
+        assert_eq!(
+            availability.models("urn:dslforum-org:service:Time"),
+            vec![("FRITZ!Box 6490 Cable".to_string(), "141.07.20".to_string())]
+        );
+        let entry = format!("{}#GetSpecificHostEntry", service);
+        assert!(availability.declared_with(&format!("{}.NewIPAddress", entry), &entry));
+        assert!(!availability.declared_with(&format!("{}.NewActive", entry), &entry));
+    }
+
+    #[test]
+    fn conflicts() {
+        let set = |arguments: &[(&str, &str, &str)], data_type: &str| {
+            model_set(
+                "Box",
+                "",
+                &[(
+                    "Time",
+                    &scpd(
+                        &action("SetNTPServers", arguments),
+                        &variable("NTPServer", data_type, &[]),
+                    ),
+                )],
+            )
+        };
+        let first = || set(&[("NewNTPServer1", "in", "NTPServer")], "ui2");
+
+        assert!(merge(vec![
+            first(),
+            set(&[("NewNTPServer1", "in", "NTPServer")], "ui4")
+        ])
+        .is_ok());
+        let error = merge(vec![
+            first(),
+            set(&[("NewNTPServer2", "in", "NTPServer")], "ui2"),
+        ])
+        .unwrap_err();
+        assert!(error.contains("in arguments (NewNTPServer2)"), "{}", error);
+        let error = merge(vec![
+            first(),
+            set(&[("NewNTPServer1", "in", "NTPServer")], "string"),
+        ])
+        .unwrap_err();
+        assert!(error.contains("data type string"), "{}", error);
+    }
+}
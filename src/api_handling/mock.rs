@@ -0,0 +1,854 @@
+//! A stand-in for a box, to test applications using the bindings without hardware. It serves the
+//! root descriptions and SCPDs of description sets and answers SOAP calls on the control URLs of
+//! their services, with values or faults from a `MockScript` or with default values of the right
+//! types. Calls are checked against the SCPDs like a box would, but aren't authenticated.
+
+use crate::api_handling::api_desc::{Action, ApiDesc, StateVariable};
+use crate::api_handling::api_desc_dir::ServiceDescription;
+use crate::api_handling::description_set::DescriptionSet;
+use crate::api_handling::eventing::{header, unescape};
+use crate::api_handling::examples::{envelope, escape_xml, example_response};
+use crate::api_handling::naming::{action_item, service_item};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// How the mock answers an action.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockAnswer {
+    /// Values of `out` arguments by name, the others get default values.
+    Values(Vec<(String, String)>),
+    /// A UPnP error with code and description, e.g. `714` and `NoSuchEntryInArray`.
+    Fault(u32, String),
+}
+
+/// Answers of actions pinned by the user. Read from a file with one line per `out` argument value
+/// or fault, the action given as service type without version and action name:
+/// `urn:dslforum-org:service:DeviceInfo#GetInfo NewModelName FRITZ!Box 7590` or
+/// `urn:dslforum-org:service:Hosts#GetSpecificHostEntry fault 714 NoSuchEntryInArray`.
+/// Empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Default)]
+pub struct MockScript {
+    answers: HashMap<String, MockAnswer>,
+}
+
+impl MockScript {
+    pub fn new() -> Self {
+        MockScript::default()
+    }
+
+    /// Reads the script from `path`. A missing file means an empty script.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(Self::parse(&content).map_err(|e| format!("{}: {}", path, e))?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Parses the content of a script file.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut script = Self::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.splitn(3, char::is_whitespace);
+            let (item, name) = match (parts.next(), parts.next()) {
+                (Some(item), Some(name)) if item.contains('#') => (item, name),
+                _ => {
+                    return Err(format!(
+                        "line {}: expected `<service>#<action> <argument> <value>` or \
+                         `<service>#<action> fault <code> <description>`, found `{}`",
+                        number + 1,
+                        line
+                    ))
+                }
+            };
+            let rest = parts.next().unwrap_or_default().trim();
+            if name == "fault" {
+                let mut fault = rest.splitn(2, char::is_whitespace);
+                let code = fault
+                    .next()
+                    .and_then(|code| code.parse().ok())
+                    .ok_or_else(|| format!("line {}: the fault has no valid code", number + 1))?;
+                let description = fault.next().unwrap_or_default().trim().to_string();
+                if script.answers.contains_key(item) {
+                    return Err(format!(
+                        "line {}: {} has values, it can't fail",
+                        number + 1,
+                        item
+                    ));
+                }
+                script
+                    .answers
+                    .insert(item.to_string(), MockAnswer::Fault(code, description));
+                continue;
+            }
+            match script
+                .answers
+                .entry(item.to_string())
+                .or_insert_with(|| MockAnswer::Values(vec![]))
+            {
+                MockAnswer::Values(values) => values.push((name.to_string(), rest.to_string())),
+                MockAnswer::Fault(..) => {
+                    return Err(format!(
+                        "line {}: {} fails, it has no values",
+                        number + 1,
+                        item
+                    ))
+                }
+            }
+        }
+        Ok(script)
+    }
+}
+
+/// An HTTP response of the mock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+/// The stand-in for a box, see `serve`.
+#[derive(Debug)]
+pub struct MockServer {
+    sets: Vec<DescriptionSet>,
+    script: MockScript,
+}
+
+impl MockServer {
+    /// Creates a mock serving `sets`, e.g. the TR-064 and the IGD set of a snapshot, which answers
+    /// actions as `script` says. Fails if `script` names actions or `out` arguments the sets don't have.
+    pub fn new(sets: Vec<DescriptionSet>, script: MockScript) -> Result<Self, String> {
+        for (item, answer) in &script.answers {
+            let action = sets
+                .iter()
+                .flat_map(|set| &set.services)
+                .find_map(|description| {
+                    description
+                        .scpd
+                        .action_list
+                        .action
+                        .iter()
+                        .find(|action| action_item(&description.service, action) == *item)
+                })
+                .ok_or_else(|| format!("the mock script names the unknown action {}", item))?;
+            if let MockAnswer::Values(values) = answer {
+                for (name, _) in values {
+                    if !action
+                        .argument_list
+                        .argument
+                        .iter()
+                        .any(|argument| argument.direction == "out" && argument.name == *name)
+                    {
+                        return Err(format!("{} has no out argument {}", item, name));
+                    }
+                }
+            }
+        }
+        Ok(MockServer { sets, script })
+    }
+
+    /// Answers requests on `listener` until accepting a connection fails, one thread per connection.
+    pub fn serve(self, listener: TcpListener) -> io::Result<()> {
+        let server = Arc::new(self);
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = Arc::clone(&server);
+            thread::spawn(move || {
+                if let Err(e) = server.handle_connection(stream) {
+                    eprintln!("mock: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn handle_connection(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+        let mut request = vec![];
+        let mut buffer = [0u8; 4096];
+        let head_end = loop {
+            if let Some(position) = find(&request, b"\r\n\r\n") {
+                break position;
+            }
+            let read = stream.read(&mut buffer)?;
+            if read == 0 {
+                return Ok(());
+            }
+            request.extend_from_slice(&buffer[..read]);
+        };
+        let head = String::from_utf8_lossy(&request[..head_end]).to_string();
+        let content_length: usize = header(&head, "Content-Length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0);
+        while request.len() < head_end + 4 + content_length {
+            let read = stream.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..read]);
+        }
+        let body = String::from_utf8_lossy(&request[head_end + 4..]);
+
+        let mut request_line = head.lines().next().unwrap_or_default().split_whitespace();
+        let method = request_line.next().unwrap_or_default();
+        let path = request_line.next().unwrap_or_default();
+        let soap_action = header(&head, "SOAPACTION");
+        let response = self.respond(method, path, soap_action.as_deref(), &body);
+        println!(
+            "mock: {} {}{} -> {}",
+            method,
+            path,
+            soap_action
+                .map(|action| format!(" ({})", action))
+                .unwrap_or_default(),
+            response.status
+        );
+        let reason = match response.status {
+            200 => "OK",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nCONTENT-TYPE: text/xml; charset=\"utf-8\"\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n{}",
+            response.status,
+            reason,
+            response.body.len(),
+            response.body
+        )
+    }
+
+    /// Answers a request: the descriptions for `GET`, SOAP calls with the `SOAPACTION` header
+    /// `soap_action` for `POST`.
+    pub fn respond(
+        &self,
+        method: &str,
+        path: &str,
+        soap_action: Option<&str>,
+        body: &str,
+    ) -> MockResponse {
+        let not_found = MockResponse {
+            status: 404,
+            body: String::new(),
+        };
+        let path = &path[..path.find(['?', '#']).unwrap_or(path.len())];
+        match method {
+            "GET" => {
+                for set in &self.sets {
+                    if root_path(set) == path {
+                        return ok(&set.description_xml);
+                    }
+                    for description in &set.services {
+                        if description.service.scpd_url == path {
+                            return ok(&description.scpd_xml);
+                        }
+                    }
+                }
+                not_found
+            }
+            "POST" => {
+                let services: Vec<&ServiceDescription> = self
+                    .sets
+                    .iter()
+                    .flat_map(|set| &set.services)
+                    .filter(|description| {
+                        description.service.control_url == path
+                            || description
+                                .service
+                                .instances
+                                .iter()
+                                .any(|(_, url)| url == path)
+                    })
+                    .collect();
+                match services.first() {
+                    Some(first) => {
+                        self.call(&services, &first.service.service_type, soap_action, body)
+                    }
+                    None => not_found,
+                }
+            }
+            _ => MockResponse {
+                status: 405,
+                body: String::new(),
+            },
+        }
+    }
+
+    /// Answers the SOAP call `body` to one of `services`, which share a control URL.
+    fn call(
+        &self,
+        services: &[&ServiceDescription],
+        service_type: &str,
+        soap_action: Option<&str>,
+        body: &str,
+    ) -> MockResponse {
+        let invalid_action = || fault(service_type, 401, "Invalid Action");
+        let (action_type, action_name) = match soap_action
+            .map(|soap_action| soap_action.trim().trim_matches('"'))
+            .and_then(|soap_action| soap_action.split_once('#'))
+        {
+            Some(soap_action) => soap_action,
+            None => return invalid_action(),
+        };
+        let description = match services
+            .iter()
+            .find(|description| service_item(&description.service) == without_version(action_type))
+        {
+            Some(description) => description,
+            None => return invalid_action(),
+        };
+        let service_type = &description.service.service_type;
+        let scpd = &description.scpd;
+        let action = match scpd
+            .action_list
+            .action
+            .iter()
+            .find(|action| action.name == action_name)
+        {
+            Some(action) => action,
+            None => return fault(service_type, 401, "Invalid Action"),
+        };
+        let arguments = match parse_call(body, action_type) {
+            Ok((name, arguments)) if name == action.name => arguments,
+            _ => return fault(service_type, 401, "Invalid Action"),
+        };
+        if let Err((code, description)) = check_arguments(scpd, action, &arguments) {
+            return fault(service_type, code, &description);
+        }
+
+        let scripted = self
+            .script
+            .answers
+            .get(&action_item(&description.service, action));
+        let scripted = match scripted {
+            Some(MockAnswer::Fault(code, description)) => {
+                return fault(service_type, *code, description)
+            }
+            Some(MockAnswer::Values(values)) => values.as_slice(),
+            None => &[],
+        };
+        ok(&example_response(service_type, scpd, action, scripted))
+    }
+}
+
+/// Returns the path the root description of `set` is served under, the file name of its source,
+/// e.g. `/tr64desc.xml`.
+fn root_path(set: &DescriptionSet) -> String {
+    format!(
+        "/{}",
+        set.source.rsplit(['/', '\\']).next().unwrap_or_default()
+    )
+}
+
+/// Checks `arguments`, the names and values of the call of `action`, against the `in` arguments
+/// and their state variables. Returns the UPnP error code and description if they don't match.
+fn check_arguments(
+    scpd: &ApiDesc,
+    action: &Action,
+    arguments: &[(String, String)],
+) -> Result<(), (u32, String)> {
+    let expected: Vec<&str> = action
+        .argument_list
+        .argument
+        .iter()
+        .filter(|argument| argument.direction == "in")
+        .map(|argument| argument.name.as_str())
+        .collect();
+    let names: Vec<&str> = arguments.iter().map(|(name, _)| name.as_str()).collect();
+    if names != expected {
+        return Err((
+            402,
+            format!(
+                "Invalid Args: expected ({}), found ({})",
+                expected.join(", "),
+                names.join(", ")
+            ),
+        ));
+    }
+    for ((name, value), argument) in arguments.iter().zip(
+        action
+            .argument_list
+            .argument
+            .iter()
+            .filter(|argument| argument.direction == "in"),
+    ) {
+        if let Some(variable) = scpd.state_variable(&argument.related_state_variable) {
+            check_value(variable, value)
+                .map_err(|(code, reason)| (code, format!("{}: {}", name, reason)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks `value` against the data type and the allowed values of `variable`.
+fn check_value(variable: &StateVariable, value: &str) -> Result<(), (u32, String)> {
+    let invalid = || {
+        Err((
+            600,
+            format!(
+                "Argument Value Invalid: `{}` isn't a {}",
+                value, variable.data_type
+            ),
+        ))
+    };
+    let range = match variable.data_type.as_str() {
+        "ui1" => Some((0, u8::MAX as i64)),
+        "ui2" => Some((0, u16::MAX as i64)),
+        "ui4" => Some((0, u32::MAX as i64)),
+        "i1" => Some((i8::MIN as i64, i8::MAX as i64)),
+        "i2" => Some((i16::MIN as i64, i16::MAX as i64)),
+        "i4" => Some((i32::MIN as i64, i32::MAX as i64)),
+        _ => None,
+    };
+    if let Some((min, max)) = range {
+        match value.trim().parse::<i64>() {
+            Ok(number) if number < min || number > max => {
+                return Err((
+                    601,
+                    format!(
+                        "Argument Value Out of Range: {} isn't a {}",
+                        number, variable.data_type
+                    ),
+                ))
+            }
+            Ok(_) => {}
+            Err(_) => return invalid(),
+        }
+    }
+    let valid = match variable.data_type.as_str() {
+        "boolean" => ["0", "1", "true", "false", "yes", "no"].contains(&value.trim()),
+        "bin.base64" => value
+            .trim()
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '='),
+        "bin.hex" => {
+            value.trim().len().is_multiple_of(2)
+                && value.trim().chars().all(|c| c.is_ascii_hexdigit())
+        }
+        _ => true,
+    };
+    if !valid {
+        return invalid();
+    }
+    let allowed = &variable.allowed_value_list.allowed_value;
+    if !allowed.is_empty() && !allowed.iter().any(|allowed| allowed == value) {
+        return Err((
+            600,
+            format!(
+                "Argument Value Invalid: `{}` isn't one of {}",
+                value,
+                allowed.join(", ")
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// A start, end or empty-element tag of an XML document.
+struct Tag {
+    name: String,
+    /// Everything between the name and the end of the tag.
+    attributes: String,
+    end: bool,
+    empty: bool,
+    /// Position after the tag.
+    after: usize,
+}
+
+/// Returns the first tag in `xml` from `position` on, skipping declarations and comments.
+fn next_tag(xml: &str, mut position: usize) -> Option<Tag> {
+    loop {
+        let start = position + xml[position..].find('<')?;
+        let end = start + xml[start..].find('>')?;
+        position = end + 1;
+        let content = &xml[start + 1..end];
+        if content.starts_with('?') || content.starts_with('!') {
+            continue;
+        }
+        let content_trimmed = content.trim_start_matches('/').trim_end_matches('/');
+        let name = content_trimmed
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
+        return Some(Tag {
+            name: name.to_string(),
+            attributes: content_trimmed[name.len()..].to_string(),
+            end: content.starts_with('/'),
+            empty: content.ends_with('/'),
+            after: position,
+        });
+    }
+}
+
+/// Checks that `xml` is well-formed. The XML reader accepts attributes without whitespace
+/// between them, e.g. `xmlns:s="…"s:encodingStyle="…"`, so that is checked separately.
+fn check_well_formed(xml: &str) -> Result<(), String> {
+    for event in serde_xml_rs::EventReader::from_str(xml) {
+        event.map_err(|e| format!("the envelope isn't well-formed: {}", e))?;
+    }
+    let mut in_tag = false;
+    let mut quote = None;
+    let mut after_value = false;
+    for c in xml.chars() {
+        if let Some(open) = quote {
+            if c == open {
+                quote = None;
+                after_value = true;
+            }
+            continue;
+        }
+        if after_value && !(c.is_whitespace() || c == '/' || c == '?' || c == '>') {
+            return Err(
+                "the envelope isn't well-formed: attributes without whitespace".to_string(),
+            );
+        }
+        after_value = false;
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            '"' | '\'' if in_tag => quote = Some(c),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+impl Tag {
+    /// Returns the unescaped value of the attribute `name`.
+    fn attribute(&self, name: &str) -> Option<String> {
+        let mut rest = self.attributes.as_str();
+        loop {
+            let (key, value) = rest.split_once('=')?;
+            let value = value.trim_start();
+            let quote = value.chars().next()?;
+            let end = value[1..].find(quote)? + 1;
+            if key.trim() == name {
+                return Some(unescape(&value[1..end]));
+            }
+            rest = &value[end + 1..];
+        }
+    }
+
+    /// Returns the namespace declared on the tag for the prefix of its name.
+    fn namespace(&self) -> Option<String> {
+        match self.name.split_once(':') {
+            Some((prefix, _)) => self.attribute(&format!("xmlns:{}", prefix)),
+            None => self.attribute("xmlns"),
+        }
+    }
+}
+
+/// Returns the local name of the action element in the body of the SOAP `envelope` and the names
+/// and unescaped values of its children. `envelope` has to be well-formed XML and the action
+/// element has to be in the namespace `service_type`, the one of the `SOAPACTION` header.
+fn parse_call(
+    envelope: &str,
+    service_type: &str,
+) -> Result<(String, Vec<(String, String)>), String> {
+    check_well_formed(envelope)?;
+    let local_name = |name: &str| name.rsplit(':').next().unwrap_or_default().to_string();
+    let mut position = 0;
+    loop {
+        let tag = next_tag(envelope, position).ok_or("the envelope has no body")?;
+        position = tag.after;
+        if !tag.end && !tag.empty && local_name(&tag.name) == "Body" {
+            break;
+        }
+    }
+    let action = next_tag(envelope, position).ok_or("the body is empty")?;
+    position = action.after;
+    let mut arguments = vec![];
+    if action.end {
+        return Err("the body is empty".to_string());
+    }
+    if action.namespace().as_deref() != Some(service_type) {
+        return Err(format!(
+            "the action {} isn't in the namespace {}",
+            action.name, service_type
+        ));
+    }
+    if action.empty {
+        return Ok((local_name(&action.name), arguments));
+    }
+    loop {
+        let tag = next_tag(envelope, position)
+            .ok_or_else(|| format!("the action {} isn't closed", action.name))?;
+        position = tag.after;
+        if tag.end {
+            if tag.name == action.name {
+                break;
+            }
+            return Err(format!("unexpected end tag of {}", tag.name));
+        }
+        if tag.empty {
+            arguments.push((tag.name, String::new()));
+            continue;
+        }
+        let end_tag = format!("</{}>", tag.name);
+        let value_end = position
+            + envelope[position..]
+                .find(&end_tag)
+                .ok_or_else(|| format!("the argument {} isn't closed", tag.name))?;
+        arguments.push((tag.name, unescape(&envelope[position..value_end])));
+        position = value_end + end_tag.len();
+    }
+    Ok((local_name(&action.name), arguments))
+}
+
+fn ok(body: &str) -> MockResponse {
+    MockResponse {
+        status: 200,
+        body: body.to_string(),
+    }
+}
+
+/// Returns a UPnP error response, in the control namespace of TR-064 for its services.
+fn fault(service_type: &str, code: u32, description: &str) -> MockResponse {
+    let namespace = if service_type.starts_with("urn:dslforum-org:") {
+        "urn:dslforum-org:control-1-0"
+    } else {
+        "urn:schemas-upnp-org:control-1-0"
+    };
+    MockResponse {
+        status: 500,
+        body: envelope(&format!(
+            "<s:Fault>\n<faultcode>s:Client</faultcode>\n<faultstring>UPnPError</faultstring>\n\
+             <detail>\n<UPnPError xmlns=\"{}\">\n<errorCode>{}</errorCode>\n\
+             <errorDescription>{}</errorDescription>\n</UPnPError>\n</detail>\n</s:Fault>",
+            namespace,
+            code,
+            escape_xml(description)
+        )),
+    }
+}
+
+// Matches service types like the generated capabilities module does.
+include!("capabilities_module/without_version.rs");
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_handling::description_set::fixtures::{action, scpd, set, variable};
+    use reqwest::blocking::Client;
+
+    const HOSTS: &str = "urn:dslforum-org:service:Hosts";
+
+    fn mock(script: &str) -> MockServer {
+        let hosts = scpd(
+            &[
+                action(
+                    "GetSpecificHostEntry",
+                    &[
+                        ("NewMACAddress", "in", "MACAddress"),
+                        ("NewIPAddress", "out", "IPAddress"),
+                        ("NewActive", "out", "Active"),
+                        ("NewInterfaceType", "out", "InterfaceType"),
+                    ],
+                ),
+                action(
+                    "SetHostNameByMACAddress",
+                    &[
+                        ("NewMACAddress", "in", "MACAddress"),
+                        ("NewPort", "in", "Port"),
+                        ("NewActive", "in", "Active"),
+                    ],
+                ),
+            ]
+            .concat(),
+            &[
+                variable("MACAddress", "string", &[]),
+                variable("IPAddress", "string", &[]),
+                variable("Active", "boolean", &[]),
+                variable("InterfaceType", "string", &["Ethernet", "802.11"]),
+                variable("Port", "ui2", &[]),
+            ]
+            .concat(),
+        );
+        MockServer::new(
+            vec![set(&[("Hosts", &hosts)])],
+            MockScript::parse(script).unwrap(),
+        )
+        .unwrap()
+    }
+
+    fn call(action: &str, arguments: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\"?><s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" \
+             s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body><u:{} \
+             xmlns:u=\"{}:1\">{}</u:{}></s:Body></s:Envelope>",
+            action, HOSTS, arguments, action
+        )
+    }
+
+    #[test]
+    fn scripts() {
+        let script = MockScript::parse(
+            "# answers\n\
+             urn:dslforum-org:service:Hosts#GetSpecificHostEntry NewIPAddress 192.168.178.20\n\
+             urn:dslforum-org:service:Hosts#GetSpecificHostEntry NewActive\n\
+             urn:dslforum-org:service:Time#GetInfo fault 501 Action Failed\n",
+        )
+        .unwrap();
+        assert_eq!(
+            script.answers[&format!("{}#GetSpecificHostEntry", HOSTS)],
+            MockAnswer::Values(vec![
+                ("NewIPAddress".to_string(), "192.168.178.20".to_string()),
+                ("NewActive".to_string(), String::new()),
+            ])
+        );
+        assert_eq!(
+            script.answers["urn:dslforum-org:service:Time#GetInfo"],
+            MockAnswer::Fault(501, "Action Failed".to_string())
+        );
+        assert!(MockScript::parse("GetInfo NewModelName x").is_err());
+        assert!(MockScript::parse("a#B fault x").is_err());
+        assert!(MockScript::parse("a#B NewX 1\na#B fault 501").is_err());
+
+        let error = MockServer::new(vec![], script).unwrap_err();
+        assert!(error.contains("unknown action"), "{}", error);
+        let hosts = scpd(&action("GetInfo", &[]), &variable("X", "string", &[]));
+        let script = MockScript::parse("urn:dslforum-org:service:Hosts#GetInfo NewX 1").unwrap();
+        let error = MockServer::new(vec![set(&[("Hosts", &hosts)])], script).unwrap_err();
+        assert!(error.contains("no out argument NewX"), "{}", error);
+    }
+
+    #[test]
+    fn calls() {
+        let mock = mock(&format!(
+            "{}#GetSpecificHostEntry NewIPAddress 192.168.178.20\n\
+             {}#SetHostNameByMACAddress fault 714 NoSuchEntryInArray",
+            HOSTS, HOSTS
+        ));
+        let soap_action = format!("{}:1#GetSpecificHostEntry", HOSTS);
+        let response = mock.respond(
+            "POST",
+            "/upnp/control/Hosts",
+            Some(&soap_action),
+            &call(
+                "GetSpecificHostEntry",
+                "<NewMACAddress>AA:BB</NewMACAddress>",
+            ),
+        );
+        assert_eq!(response.status, 200, "{}", response.body);
+        assert!(response.body.contains(&format!(
+            "<u:GetSpecificHostEntryResponse xmlns:u=\"{}:1\"><NewIPAddress>192.168.178.20</NewIPAddress>\
+             <NewActive>0</NewActive><NewInterfaceType>Ethernet</NewInterfaceType></u:GetSpecificHostEntryResponse>",
+            HOSTS
+        )));
+
+        let error_code = |soap_action: &str, body: &str| {
+            let response = mock.respond("POST", "/upnp/control/Hosts", Some(soap_action), body);
+            assert_eq!(response.status, 500, "{}", response.body);
+            let start = response.body.find("<errorCode>").unwrap() + "<errorCode>".len();
+            response.body[start..start + 3].to_string()
+        };
+        let set_host = format!("\"{}:1#SetHostNameByMACAddress\"", HOSTS);
+        let arguments = |port: &str, active: &str| {
+            call(
+                "SetHostNameByMACAddress",
+                &format!(
+                    "<NewMACAddress>AA:BB</NewMACAddress><NewPort>{}</NewPort><NewActive>{}</NewActive>",
+                    port, active
+                ),
+            )
+        };
+        assert_eq!(error_code(&set_host, &arguments("80", "1")), "714");
+        assert_eq!(error_code(&set_host, &arguments("x", "1")), "600");
+        assert_eq!(error_code(&set_host, &arguments("70000", "1")), "601");
+        assert_eq!(error_code(&set_host, &arguments("80", "maybe")), "600");
+        assert_eq!(
+            error_code(
+                &set_host,
+                &call("SetHostNameByMACAddress", "<NewPort>80</NewPort>")
+            ),
+            "402"
+        );
+        assert_eq!(
+            error_code(&format!("{}:1#Reboot", HOSTS), &call("Reboot", "")),
+            "401"
+        );
+        assert_eq!(
+            error_code(&set_host, &call("GetSpecificHostEntry", "")),
+            "401"
+        );
+        let valid = arguments("80", "1");
+        for malformed in [
+            valid.replacen("\" s:encodingStyle", "\"s:encodingStyle", 1),
+            valid.replace("</s:Envelope>", ""),
+            valid.replace("</NewPort>", "</NewActive>"),
+            valid.replace("AA:BB", "AA&BB"),
+            format!("{}<s:Envelope/>", valid),
+        ] {
+            assert_eq!(error_code(&set_host, &malformed), "401", "{}", malformed);
+        }
+        let namespace = format!("xmlns:u=\"{}:1\"", HOSTS);
+        for other_namespace in [
+            valid.replace(&namespace, "xmlns:u=\"urn:dslforum-org:service:Time:1\""),
+            valid.replace(&namespace, &format!("xmlns:u=\"{}:2\"", HOSTS)),
+            valid.replace(&namespace, &format!("xmlns:v=\"{}:1\"", HOSTS)),
+        ] {
+            assert_eq!(
+                error_code(&set_host, &other_namespace),
+                "401",
+                "{}",
+                other_namespace
+            );
+        }
+        let single_quotes = valid.replace(&namespace, &format!("xmlns:u='{}:1'", HOSTS));
+        assert_eq!(error_code(&set_host, &single_quotes), "714");
+        assert_eq!(
+            mock.respond("POST", "/upnp/control/Time", Some(&set_host), "")
+                .status,
+            404
+        );
+    }
+
+    #[test]
+    fn serves_over_http() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let mock = mock("");
+        let description_xml = mock.sets[0].description_xml.clone();
+        let scpd_xml = mock.sets[0].services[0].scpd_xml.clone();
+        thread::spawn(move || mock.serve(listener));
+
+        let client = Client::new();
+        let get = |path: &str| client.get(format!("{}{}", base_url, path)).send().unwrap();
+        assert_eq!(get("/Box.xml").text().unwrap(), description_xml);
+        assert_eq!(get("/Hosts.xml").text().unwrap(), scpd_xml);
+        assert_eq!(get("/missing.xml").status(), 404);
+
+        let response = client
+            .post(format!("{}/upnp/control/Hosts", base_url))
+            .header("SOAPACTION", format!("{}:1#GetSpecificHostEntry", HOSTS))
+            .header("CONTENT-TYPE", "text/xml; charset=\"utf-8\"")
+            .body(call(
+                "GetSpecificHostEntry",
+                "<NewMACAddress>AA:BB</NewMACAddress>",
+            ))
+            .send()
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(response
+            .text()
+            .unwrap()
+            .contains("<NewIPAddress></NewIPAddress>"));
+    }
+}
@@ -1,2 +1,23 @@
 pub mod api_desc;
 pub mod api_desc_dir;
+pub mod cache;
+pub mod capabilities_module;
+pub mod description_set;
+pub mod device_metadata_module;
+pub mod diff;
+pub mod digest;
+pub mod discovery;
+pub mod discovery_module;
+// Written into the output, the generator itself doesn't subscribe to events.
+#[allow(dead_code)]
+pub mod eventing;
+pub mod examples;
+pub mod fetch;
+pub mod filter;
+pub mod identifier;
+pub mod merge;
+pub mod mock;
+pub mod naming;
+pub mod options;
+pub mod serde_helpers;
+pub mod url;
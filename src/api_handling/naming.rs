@@ -0,0 +1,547 @@
+use crate::api_handling::api_desc::{Action, Argument, StateVariable};
+use crate::api_handling::api_desc_dir::{DeviceModule, Service, ServiceDescription};
+use crate::api_handling::capabilities_module::marker_type;
+use crate::api_handling::identifier::{
+    is_keyword, module_name, pascal_case, snake_case, IdentifierCollision, IdentifierScope,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+
+/// Kinds of generated items which get a Rust name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NameKind {
+    /// Request and response file of a service.
+    Module,
+    /// Request function of an action.
+    Function,
+    /// Response struct of an action.
+    Struct,
+    /// Request function parameter or response struct field of an argument.
+    Argument,
+    /// Event struct field of an evented state variable.
+    Variable,
+}
+
+impl NameKind {
+    fn parse(input: &str) -> Option<Self> {
+        match input {
+            "module" => Some(NameKind::Module),
+            "function" => Some(NameKind::Function),
+            "struct" => Some(NameKind::Struct),
+            "argument" => Some(NameKind::Argument),
+            "variable" => Some(NameKind::Variable),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the item path of a service: its service type without the version,
+/// e.g. `urn:dslforum-org:service:WLANConfiguration`.
+pub fn service_item(service: &Service) -> String {
+    match service.service_type.rfind(':') {
+        Some(position) => service.service_type[..position].to_string(),
+        None => service.service_type.clone(),
+    }
+}
+
+/// Returns the item path of an action, e.g. `urn:dslforum-org:service:Hosts#GetHostNumberOfEntries`.
+pub fn action_item(service: &Service, action: &Action) -> String {
+    format!("{}#{}", service_item(service), action.name)
+}
+
+/// Returns the item path of an argument, e.g. `urn:dslforum-org:service:Hosts#GetGenericHostEntry.NewIPAddress`.
+pub fn argument_item(service: &Service, action: &Action, argument: &Argument) -> String {
+    format!("{}.{}", action_item(service, action), argument.name)
+}
+
+/// Names pinned by the user. Read from a file with one override per line in the form
+/// `<kind> <item> <name>`, e.g. `argument urn:dslforum-org:service:Hosts#GetGenericHostEntry.NewIPAddress ip`.
+/// `kind` is one of `module`, `function`, `struct`, `argument` and `variable`. Empty lines and lines starting with `#` are ignored.
+#[derive(Debug, Default)]
+pub struct NameOverrides {
+    names: HashMap<(NameKind, String), String>,
+}
+
+impl NameOverrides {
+    pub fn new() -> Self {
+        NameOverrides {
+            names: HashMap::new(),
+        }
+    }
+
+    /// Reads the overrides from `path`. A missing file means no overrides.
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(Self::parse(&content).map_err(|e| format!("{}: {}", path, e))?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Parses the content of an override file.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut overrides = Self::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() != 3 {
+                return Err(format!(
+                    "line {}: expected `<kind> <item> <name>`, found `{}`",
+                    number + 1,
+                    line
+                ));
+            }
+            let kind = NameKind::parse(parts[0])
+                .ok_or_else(|| format!("line {}: unknown kind `{}`", number + 1, parts[0]))?;
+            if !is_identifier(parts[2]) {
+                return Err(format!(
+                    "line {}: `{}` is not a valid identifier",
+                    number + 1,
+                    parts[2]
+                ));
+            }
+            if kind == NameKind::Module && (parts[2].starts_with("r#") || is_keyword(parts[2])) {
+                return Err(format!(
+                    "line {}: `{}` is not a valid module name",
+                    number + 1,
+                    parts[2]
+                ));
+            }
+            overrides
+                .names
+                .insert((kind, parts[1].to_string()), parts[2].to_string());
+        }
+        Ok(overrides)
+    }
+
+    fn get(&self, kind: NameKind, item: &str) -> Option<String> {
+        self.names.get(&(kind, item.to_string())).cloned()
+    }
+}
+
+/// Returns whether `input` can be used as identifier as is.
+fn is_identifier(input: &str) -> bool {
+    let identifier = input.trim_start_matches("r#");
+    !identifier.is_empty()
+        && !identifier.starts_with(|c: char| c.is_ascii_digit())
+        && identifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// All conflicts found by `assign_names`.
+#[derive(Debug)]
+pub struct NamingError {
+    pub collisions: Vec<IdentifierCollision>,
+}
+
+impl fmt::Display for NamingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} naming conflict(s):", self.collisions.len())?;
+        for collision in &self.collisions {
+            writeln!(f, "  {}", collision)?;
+        }
+        write!(
+            f,
+            "Pin one of the names in the name override file to resolve them."
+        )
+    }
+}
+
+impl std::error::Error for NamingError {}
+
+/// Module paths of the devices by their device path, empty unless the modules mirror the device tree.
+type Folders = HashMap<Vec<usize>, Vec<String>>;
+
+/// Returns the item path of `service` for names. If the modules mirror the device tree, it starts
+/// with the module path of the device, e.g. `internet_gateway_device/lan_device/urn:dslforum-org:service:Hosts`,
+/// so services of the same type in different devices get their own names.
+fn service_key(folders: &Folders, service: &Service) -> String {
+    match folders.get(&service.device_path) {
+        Some(folder) => format!("{}/{}", folder.join("/"), service_item(service)),
+        None => service_item(service),
+    }
+}
+
+/// Rust names of all generated items of one output set, looked up by item path.
+#[derive(Debug, Default)]
+pub struct Names {
+    names: HashMap<(NameKind, String), String>,
+    folders: Folders,
+}
+
+impl Names {
+    fn get(&self, kind: NameKind, item: &str) -> &str {
+        self.names
+            .get(&(kind, item.to_string()))
+            .unwrap_or_else(|| panic!("no name assigned to {:?} {}", kind, item))
+    }
+
+    fn action_key(&self, service: &Service, action: &Action) -> String {
+        format!("{}#{}", service_key(&self.folders, service), action.name)
+    }
+
+    /// Module path of the device of `service`, e.g. `internet_gateway_device` and `lan_device`.
+    /// Empty unless the modules mirror the device tree.
+    pub fn folder(&self, service: &Service) -> &[String] {
+        self.folders
+            .get(&service.device_path)
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Name of the request and response file of `service`.
+    pub fn module(&self, service: &Service) -> &str {
+        self.get(NameKind::Module, &service_key(&self.folders, service))
+    }
+
+    /// Name of the request function of `action`, without the `generate_` and `_request` affixes.
+    pub fn function(&self, service: &Service, action: &Action) -> &str {
+        self.get(NameKind::Function, &self.action_key(service, action))
+    }
+
+    /// Name of the response struct of `action`.
+    pub fn response_struct(&self, service: &Service, action: &Action) -> &str {
+        self.get(NameKind::Struct, &self.action_key(service, action))
+    }
+
+    /// Name of the parameter or field of `argument`.
+    pub fn argument(&self, service: &Service, action: &Action, argument: &Argument) -> &str {
+        self.get(
+            NameKind::Argument,
+            &format!("{}.{}", self.action_key(service, action), argument.name),
+        )
+    }
+
+    /// Name of the event struct field of the evented state variable `variable`, whose item path
+    /// is like the one of an action, e.g. `urn:dslforum-org:service:Hosts#X_AVM-DE_ChangeCounter`.
+    pub fn variable(&self, service: &Service, variable: &StateVariable) -> &str {
+        self.get(
+            NameKind::Variable,
+            &format!("{}#{}", service_key(&self.folders, service), variable.name),
+        )
+    }
+}
+
+/// Assigns a Rust name to every item of `services`. Names only depend on the API name of the
+/// item and `overrides`, so they are stable across runs. Every identifier which is used twice
+/// in the same Rust scope is reported with the locations of both items.
+/// If `devices` isn't empty, the modules mirror the device tree and the module of a service only
+/// has to be unique within the module of its device.
+pub fn assign_names(
+    services: &[ServiceDescription],
+    overrides: &NameOverrides,
+    devices: &[DeviceModule],
+) -> Result<Names, NamingError> {
+    let mut names = Names::default();
+    let mut device_scopes: HashMap<Vec<usize>, IdentifierScope> = HashMap::new();
+    for device in devices {
+        names
+            .folders
+            .insert(device.device_path.clone(), device.module_path.clone());
+        let mut scope = IdentifierScope::new();
+        for child in &device.children {
+            scope
+                .insert(child, &format!("the device module {}", child))
+                .unwrap();
+        }
+        device_scopes.insert(device.device_path.clone(), scope);
+    }
+    let folders = names.folders.clone();
+    let mut collisions = vec![];
+    let mut assign = |scope: &mut IdentifierScope,
+                      kind: NameKind,
+                      item: String,
+                      default: String,
+                      source: &str| {
+        let name = overrides.get(kind, &item).unwrap_or(default);
+        if let Err(collision) = scope.insert(&name, source) {
+            collisions.push(collision);
+        }
+        names.names.insert((kind, item), name.clone());
+        name
+    };
+    // Collisions of the items of the request files besides the request functions.
+    let mut request_collisions = vec![];
+
+    // The generated modules next to the service modules.
+    let mut module_scope = IdentifierScope::new();
+    for (module, source) in [
+        ("multi_use", "the envelope module"),
+        ("serde_helpers", "the serde helpers module"),
+        ("discovery", "the discovery module"),
+        ("url", "the url module"),
+        ("capabilities", "the capabilities module"),
+        ("device_metadata", "the device metadata module"),
+        ("eventing", "the eventing module"),
+    ] {
+        module_scope.insert(module, source).unwrap();
+    }
+    for description in services {
+        let service = &description.service;
+        let service_source = format!("service {} ({})", service.service_type, service.scpd_url);
+        let service_key = service_key(&folders, service);
+        let scope = device_scopes
+            .get_mut(&service.device_path)
+            .unwrap_or(&mut module_scope);
+        assign(
+            scope,
+            NameKind::Module,
+            service_key.clone(),
+            module_name(service.service_type.split(':').nth(3).unwrap_or_default()),
+            &service_source,
+        );
+
+        let mut function_scope = IdentifierScope::new();
+        let mut struct_scope = IdentifierScope::new();
+        let mut request_scope = IdentifierScope::new();
+        request_scope.insert("Event", "the event struct").unwrap();
+        request_scope
+            .insert("EVENT_URL", "the event URL constant")
+            .unwrap();
+        for action in &description.scpd.action_list.action {
+            let action_source = format!("{}, action {}", service_source, action.name);
+            let action_key = format!("{}#{}", service_key, action.name);
+            assign(
+                &mut function_scope,
+                NameKind::Function,
+                action_key.clone(),
+                snake_case(&action.name),
+                &action_source,
+            );
+            let response_struct = assign(
+                &mut struct_scope,
+                NameKind::Struct,
+                action_key.clone(),
+                format!("{}Response", pascal_case(&action.name)),
+                &action_source,
+            );
+            if let Err(collision) = request_scope.insert(
+                marker_type(&response_struct),
+                &format!("{} (marker type)", action_source),
+            ) {
+                request_collisions.push(collision);
+            }
+
+            let mut field_scope = IdentifierScope::new();
+            let mut parameter_scope = IdentifierScope::new();
+            // Every request function has an `id` parameter for the service number.
+            parameter_scope
+                .insert("id", "the service id parameter")
+                .unwrap();
+            for argument in &action.argument_list.argument {
+                let scope = if argument.direction == "in" {
+                    &mut parameter_scope
+                } else {
+                    &mut field_scope
+                };
+                assign(
+                    scope,
+                    NameKind::Argument,
+                    format!("{}.{}", action_key, argument.name),
+                    snake_case(&argument.name),
+                    &format!("{}, argument {}", action_source, argument.name),
+                );
+            }
+        }
+
+        let mut event_scope = IdentifierScope::new();
+        for variable in &description.scpd.service_state_table.state_variable {
+            if variable.is_evented() {
+                assign(
+                    &mut event_scope,
+                    NameKind::Variable,
+                    format!("{}#{}", service_key, variable.name),
+                    snake_case(&variable.name),
+                    &format!("{}, state variable {}", service_source, variable.name),
+                );
+            }
+        }
+    }
+
+    collisions.append(&mut request_collisions);
+    if collisions.is_empty() {
+        Ok(names)
+    } else {
+        Err(NamingError { collisions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api_handling::description_set::fixtures::{action, scpd, set, variable};
+
+    #[test]
+    fn nested_device_scopes() {
+        let hosts = scpd(
+            &action("GetHostNumberOfEntries", &[("NewCount", "out", "Count")]),
+            &variable("Count", "ui2", &[]),
+        );
+        let service = |device_path: Vec<usize>| {
+            let mut description = set(&[("Hosts", &hosts)]).services.remove(0);
+            description.service.device_path = device_path;
+            description
+        };
+        let device = |device_path: Vec<usize>, module: &str, children: &[&str]| DeviceModule {
+            device_path,
+            module_path: vec!["internet_gateway_device".to_string(), module.to_string()],
+            children: children.iter().map(|child| child.to_string()).collect(),
+            metadata: String::new(),
+        };
+        let services = vec![service(vec![0]), service(vec![1])];
+        let devices = vec![
+            device(vec![0], "lan_device", &[]),
+            device(vec![1], "lan_device_2", &[]),
+        ];
+
+        let names = assign_names(&services, &NameOverrides::new(), &devices).unwrap();
+        assert_eq!(names.module(&services[0].service), "hosts");
+        assert_eq!(names.module(&services[1].service), "hosts");
+        assert_eq!(names.folder(&services[1].service)[1], "lan_device_2");
+        assert!(assign_names(&services, &NameOverrides::new(), &[]).is_err());
+
+        let devices = vec![device(vec![0], "lan_device", &["hosts"])];
+        let error = assign_names(&services[..1], &NameOverrides::new(), &devices).unwrap_err();
+        assert!(
+            error.to_string().contains("device module hosts"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn parse_overrides() {
+        let overrides = NameOverrides::parse(
+            "# pinned names\n\n  argument urn:dslforum-org:service:Hosts#GetGenericHostEntry.NewIPAddress ip\n\
+             module urn:dslforum-org:service:Hosts lan_hosts\n",
+        )
+        .unwrap();
+        assert_eq!(
+            overrides.get(
+                NameKind::Argument,
+                "urn:dslforum-org:service:Hosts#GetGenericHostEntry.NewIPAddress"
+            ),
+            Some("ip".to_string())
+        );
+        assert_eq!(
+            overrides.get(NameKind::Module, "urn:dslforum-org:service:Hosts"),
+            Some("lan_hosts".to_string())
+        );
+        assert_eq!(
+            overrides.get(NameKind::Function, "urn:dslforum-org:service:Hosts"),
+            None
+        );
+
+        for (content, error) in [
+            ("module urn:dslforum-org:service:Hosts", "line 1: expected"),
+            ("\nmodule a b c", "line 2: expected"),
+            (
+                "field urn:dslforum-org:service:Hosts hosts",
+                "unknown kind `field`",
+            ),
+            (
+                "module urn:dslforum-org:service:Hosts 2hosts",
+                "`2hosts` is not a valid",
+            ),
+            (
+                "module urn:dslforum-org:service:Hosts lan-hosts",
+                "`lan-hosts` is not a valid",
+            ),
+            (
+                "module urn:dslforum-org:service:Hosts r#",
+                "`r#` is not a valid",
+            ),
+            (
+                "module urn:dslforum-org:service:Hosts r#hosts",
+                "`r#hosts` is not a valid module name",
+            ),
+            (
+                "module urn:dslforum-org:service:Hosts type",
+                "`type` is not a valid module name",
+            ),
+        ] {
+            let actual = NameOverrides::parse(content).unwrap_err();
+            assert!(actual.contains(error), "{:?}: {}", content, actual);
+        }
+    }
+
+    #[test]
+    fn collisions() {
+        let hosts = scpd(
+            &[
+                action("GetInfo", &[("Id", "in", "Count")]),
+                action("Get_Info", &[]),
+            ]
+            .concat(),
+            &variable("Count", "ui2", &[]),
+        );
+        let services = set(&[("Hosts", &hosts), ("Url", &hosts)]).services;
+
+        let error = assign_names(&services, &NameOverrides::new(), &[]).unwrap_err();
+        let message = error.to_string();
+        // Per service the function, the struct and the marker type of `Get_Info`, the `id`
+        // argument and once the `url` module.
+        assert_eq!(error.collisions.len(), 9, "{}", message);
+        assert!(message.contains("action Get_Info"), "{}", message);
+        assert!(message.contains("the service id parameter"), "{}", message);
+        assert!(message.contains("the url module"), "{}", message);
+
+        let events = scpd(
+            &[action("Event", &[]), action("GetEvent", &[])].concat(),
+            &variable("Count", "ui2", &[]),
+        );
+        let event_services = set(&[("Hosts", &events)]).services;
+        let error = assign_names(&event_services, &NameOverrides::new(), &[]).unwrap_err();
+        assert_eq!(error.collisions.len(), 1, "{}", error);
+        assert!(error.to_string().contains("the event struct"), "{}", error);
+        assert!(
+            error.to_string().contains("action Event (marker type)"),
+            "{}",
+            error
+        );
+        let overrides =
+            NameOverrides::parse("struct urn:dslforum-org:service:Hosts#Event EventNotification")
+                .unwrap();
+        assert!(assign_names(&event_services, &overrides, &[]).is_ok());
+
+        for (service, module) in [
+            ("SerdeHelpers", "the serde helpers module"),
+            ("Capabilities", "the capabilities module"),
+            ("Eventing", "the eventing module"),
+        ] {
+            let services = set(&[(service, &hosts)]).services;
+            let error = assign_names(&services, &NameOverrides::new(), &[]).unwrap_err();
+            assert!(error.to_string().contains(module), "{}", error);
+        }
+
+        let overrides = NameOverrides::parse(
+            "module urn:dslforum-org:service:Url url_service\n\
+             function urn:dslforum-org:service:Hosts#Get_Info get_info_2\n\
+             struct urn:dslforum-org:service:Hosts#Get_Info GetInfo2Response\n\
+             argument urn:dslforum-org:service:Hosts#GetInfo.Id service_id\n",
+        )
+        .unwrap();
+        let error = assign_names(&services, &overrides, &[]).unwrap_err();
+        assert_eq!(error.collisions.len(), 4, "{}", error);
+        assert!(!error.to_string().contains("service:Hosts:1"), "{}", error);
+
+        let names = assign_names(&services[..1], &overrides, &[]).unwrap();
+        let actions = &services[0].scpd.action_list.action;
+        assert_eq!(
+            names.function(&services[0].service, &actions[1]),
+            "get_info_2"
+        );
+        assert_eq!(
+            names.argument(
+                &services[0].service,
+                &actions[0],
+                &actions[0].argument_list.argument[0]
+            ),
+            "service_id"
+        );
+    }
+}
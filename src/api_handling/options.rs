@@ -0,0 +1,181 @@
+use crate::api_handling::filter::Filter;
+use crate::api_handling::naming::NameOverrides;
+use std::str::FromStr;
+
+/// How the fields of generated response structs deal with missing and empty elements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldMode {
+    /// `T`, deserialization fails if an element is missing or can't be parsed.
+    Strict,
+    /// `T` with `#[serde(default)]`, missing elements become the default value of `T`.
+    Default,
+    /// `Option<T>`, missing elements become `None`. Empty elements of non string types still fail.
+    Optional,
+    /// `Option<T>` with a custom deserializer, missing and empty elements (`<NewX></NewX>`) become `None`.
+    EmptyAsNone,
+}
+
+impl FromStr for FieldMode {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "strict" => Ok(FieldMode::Strict),
+            "default" => Ok(FieldMode::Default),
+            "optional" => Ok(FieldMode::Optional),
+            "empty-as-none" => Ok(FieldMode::EmptyAsNone),
+            _ => Err(format!(
+                "unknown field mode `{}`, expected one of `strict`, `default`, `optional` and `empty-as-none`",
+                input
+            )),
+        }
+    }
+}
+
+/// How `dateTime` state variables are represented in the generated code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DateTimeMapping {
+    /// `String`, no extra dependency.
+    String,
+    /// `chrono::NaiveDateTime`, requires the `chrono` crate.
+    Chrono,
+    /// `time::PrimitiveDateTime`, requires the `time` crate (0.3) with the `parsing` feature.
+    Time,
+}
+
+impl DateTimeMapping {
+    /// Returns the Rust type `dateTime` is mapped to.
+    pub fn rust_type(&self) -> &'static str {
+        match self {
+            DateTimeMapping::String => "String",
+            DateTimeMapping::Chrono => "chrono::NaiveDateTime",
+            DateTimeMapping::Time => "time::PrimitiveDateTime",
+        }
+    }
+}
+
+impl FromStr for DateTimeMapping {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "string" => Ok(DateTimeMapping::String),
+            "chrono" => Ok(DateTimeMapping::Chrono),
+            "time" => Ok(DateTimeMapping::Time),
+            _ => Err(format!(
+                "unknown dateTime mapping `{}`, expected one of `string`, `chrono` and `time`",
+                input
+            )),
+        }
+    }
+}
+
+/// How `uuid` state variables are represented in the generated code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UuidMapping {
+    /// `String`, no extra dependency.
+    String,
+    /// `uuid::Uuid`, requires the `uuid` crate.
+    Uuid,
+}
+
+impl UuidMapping {
+    /// Returns the Rust type `uuid` is mapped to.
+    pub fn rust_type(&self) -> &'static str {
+        match self {
+            UuidMapping::String => "String",
+            UuidMapping::Uuid => "uuid::Uuid",
+        }
+    }
+}
+
+impl FromStr for UuidMapping {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "string" => Ok(UuidMapping::String),
+            "uuid" => Ok(UuidMapping::Uuid),
+            _ => Err(format!(
+                "unknown uuid mapping `{}`, expected one of `string` and `uuid`",
+                input
+            )),
+        }
+    }
+}
+
+/// Sets of actions the generator may write.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Profile {
+    /// Whatever the filter selects.
+    Full,
+    /// Only actions which read from the device, see `filter::is_read_only` and `ReadOnlyAllowlist`.
+    /// Generation fails rather than writing any other action.
+    ReadOnly,
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "full" => Ok(Profile::Full),
+            "read-only" => Ok(Profile::ReadOnly),
+            _ => Err(format!(
+                "unknown profile `{}`, expected one of `full` and `read-only`",
+                input
+            )),
+        }
+    }
+}
+
+/// Options of the generator which apply to all generated files.
+#[derive(Debug)]
+pub struct GeneratorOptions {
+    pub name_overrides: NameOverrides,
+    pub field_mode: FieldMode,
+    pub date_time_mapping: DateTimeMapping,
+    pub uuid_mapping: UuidMapping,
+    /// Write `<prefix>discovery.rs` next to the request files, see `discovery_module`.
+    pub generate_discovery: bool,
+    /// Write `<prefix>capabilities.rs` and marker types of the actions next to the request files,
+    /// see `capabilities_module`.
+    pub generate_capabilities: bool,
+    /// Write `<prefix>device_metadata.rs` with the data of the root device next to the request files,
+    /// see `device_metadata_module`.
+    pub generate_device_metadata: bool,
+    /// Write `<prefix>eventing.rs` next to the request files and an `Event` struct into the request
+    /// files of services with evented state variables, see `eventing`.
+    pub generate_eventing: bool,
+    /// Write a `tests` module into every request file, which checks the request bodies and
+    /// deserializes an example response of every action, see `examples`.
+    pub generate_tests: bool,
+    /// Write the files of the services into nested modules mirroring the device tree, with the
+    /// metadata of every device in its `mod.rs`, see `Device::modules`.
+    pub nest_modules: bool,
+    /// The folder the response and request folders are written into.
+    pub output_folder: String,
+    /// The services and actions to generate.
+    pub filter: Filter,
+    pub profile: Profile,
+}
+
+impl GeneratorOptions {
+    pub fn new() -> Self {
+        GeneratorOptions {
+            name_overrides: NameOverrides::new(),
+            field_mode: FieldMode::Strict,
+            date_time_mapping: DateTimeMapping::String,
+            uuid_mapping: UuidMapping::String,
+            generate_discovery: false,
+            generate_capabilities: false,
+            generate_device_metadata: false,
+            generate_eventing: false,
+            generate_tests: false,
+            nest_modules: false,
+            output_folder: "output".to_string(),
+            filter: Filter::new(),
+            profile: Profile::Full,
+        }
+    }
+}
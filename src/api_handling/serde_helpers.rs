@@ -0,0 +1,481 @@
+//! Source code of the helpers the generated files use for (de)serialization.
+//! Only the helpers which are actually used get written to `<prefix>serde_helpers.rs` of the
+//! response and the request folder.
+
+use crate::api_handling::options::FieldMode;
+
+/// Imports and the `FromUpnp` trait, always the start of the helper file.
+pub const HEADER: &str = include_str!("serde_helpers/header.rs");
+
+/// Strings are taken as they are.
+pub const FROM_UPNP_STRING: &str = include_str!("serde_helpers/from_upnp_string.rs");
+
+/// Booleans are `0`/`1` on the wire, some services use `true`/`false` or `yes`/`no`.
+pub const FROM_UPNP_BOOL: &str = include_str!("serde_helpers/from_upnp_bool.rs");
+
+/// `ui1`, `ui2` and `ui4`, surrounding whitespace is ignored.
+pub const FROM_UPNP_U32: &str = include_str!("serde_helpers/from_upnp_u32.rs");
+
+/// `i1`, `i2` and `i4`, surrounding whitespace is ignored.
+pub const FROM_UPNP_I32: &str = include_str!("serde_helpers/from_upnp_i32.rs");
+
+/// `dateTime` with `DateTimeMapping::Chrono`. Accepts dates, date times with optional fractional
+/// seconds and date times with a time zone, of which the local time is used.
+pub const FROM_UPNP_CHRONO_DATE_TIME: &str =
+    include_str!("serde_helpers/from_upnp_chrono_date_time.rs");
+
+/// `dateTime` with `DateTimeMapping::Time`, accepts the same formats as `FROM_UPNP_CHRONO_DATE_TIME`.
+pub const FROM_UPNP_TIME_DATE_TIME: &str =
+    include_str!("serde_helpers/from_upnp_time_date_time.rs");
+
+/// `uuid` with `UuidMapping::Uuid`, an `uuid:` prefix as used by `UDN`s is accepted.
+pub const FROM_UPNP_UUID: &str = include_str!("serde_helpers/from_upnp_uuid.rs");
+
+/// Deserializer of `FieldMode::Strict` and `FieldMode::Default` fields.
+pub const UPNP: &str = include_str!("serde_helpers/upnp.rs");
+
+/// Deserializer of `FieldMode::Optional` fields, missing elements are handled by `#[serde(default)]`.
+pub const UPNP_OPTION: &str = include_str!("serde_helpers/upnp_option.rs");
+
+/// Deserializer of `FieldMode::EmptyAsNone` fields.
+pub const EMPTY_AS_NONE: &str = include_str!("serde_helpers/empty_as_none.rs");
+
+/// Returns the `FromUpnp` implementation for `rust_type`, as returned by `search_state_variable_type`.
+pub fn from_upnp_impl(rust_type: &str) -> &'static str {
+    match rust_type {
+        "bool" => FROM_UPNP_BOOL,
+        "u32" => FROM_UPNP_U32,
+        "i32" => FROM_UPNP_I32,
+        "chrono::NaiveDateTime" => FROM_UPNP_CHRONO_DATE_TIME,
+        "time::PrimitiveDateTime" => FROM_UPNP_TIME_DATE_TIME,
+        "uuid::Uuid" => FROM_UPNP_UUID,
+        _ => FROM_UPNP_STRING,
+    }
+}
+
+/// Decoder of `bin.base64` values, whitespace (e.g. line breaks) is ignored.
+pub const DECODE_BASE64: &str = include_str!("serde_helpers/decode_base64.rs");
+
+/// Decoder of `bin.hex` values, upper and lower case digits are accepted, whitespace is ignored.
+pub const DECODE_HEX: &str = include_str!("serde_helpers/decode_hex.rs");
+
+/// Deserializer of `bin.base64` fields in `FieldMode::Strict` and `FieldMode::Default`.
+pub const BASE64: &str = include_str!("serde_helpers/base64.rs");
+
+/// Deserializer of `bin.base64` fields in `FieldMode::Optional`.
+pub const BASE64_OPTION: &str = include_str!("serde_helpers/base64_option.rs");
+
+/// Deserializer of `bin.base64` fields in `FieldMode::EmptyAsNone`.
+pub const BASE64_EMPTY_AS_NONE: &str = include_str!("serde_helpers/base64_empty_as_none.rs");
+
+/// Deserializer of `bin.hex` fields in `FieldMode::Strict` and `FieldMode::Default`.
+pub const HEX: &str = include_str!("serde_helpers/hex.rs");
+
+/// Deserializer of `bin.hex` fields in `FieldMode::Optional`.
+pub const HEX_OPTION: &str = include_str!("serde_helpers/hex_option.rs");
+
+/// Deserializer of `bin.hex` fields in `FieldMode::EmptyAsNone`.
+pub const HEX_EMPTY_AS_NONE: &str = include_str!("serde_helpers/hex_empty_as_none.rs");
+
+/// Returns the helpers needed by a response field with the UPnP `data_type` and the Rust type
+/// `rust_type` in `field_mode`, together with the name of its deserializer.
+pub fn deserializer(
+    data_type: &str,
+    rust_type: &str,
+    field_mode: FieldMode,
+) -> (Vec<&'static str>, &'static str) {
+    match (data_type, field_mode) {
+        ("bin.base64", FieldMode::Strict) | ("bin.base64", FieldMode::Default) => {
+            (vec![DECODE_BASE64, BASE64], "base64")
+        }
+        ("bin.base64", FieldMode::Optional) => {
+            (vec![DECODE_BASE64, BASE64, BASE64_OPTION], "base64_option")
+        }
+        ("bin.base64", FieldMode::EmptyAsNone) => (
+            vec![DECODE_BASE64, BASE64_EMPTY_AS_NONE],
+            "base64_empty_as_none",
+        ),
+        ("bin.hex", FieldMode::Strict) | ("bin.hex", FieldMode::Default) => {
+            (vec![DECODE_HEX, HEX], "hex")
+        }
+        ("bin.hex", FieldMode::Optional) => (vec![DECODE_HEX, HEX, HEX_OPTION], "hex_option"),
+        ("bin.hex", FieldMode::EmptyAsNone) => {
+            (vec![DECODE_HEX, HEX_EMPTY_AS_NONE], "hex_empty_as_none")
+        }
+        (_, FieldMode::Strict) | (_, FieldMode::Default) => {
+            (vec![from_upnp_impl(rust_type), UPNP], "upnp")
+        }
+        (_, FieldMode::Optional) => (
+            vec![from_upnp_impl(rust_type), UPNP, UPNP_OPTION],
+            "upnp_option",
+        ),
+        (_, FieldMode::EmptyAsNone) => (
+            vec![from_upnp_impl(rust_type), EMPTY_AS_NONE],
+            "empty_as_none",
+        ),
+    }
+}
+
+/// Encoder of `bin.base64` request parameters.
+pub const ENCODE_BASE64: &str = include_str!("serde_helpers/encode_base64.rs");
+
+/// Encoder of `bin.hex` request parameters.
+pub const ENCODE_HEX: &str = include_str!("serde_helpers/encode_hex.rs");
+
+/// Escaper of string request parameters.
+pub const ESCAPE_XML: &str = include_str!("serde_helpers/escape_xml.rs");
+
+#[cfg(test)]
+mod tests {
+    /// The shipped helper sources, compiled the way they end up in the generated files. They are
+    /// written for the compilers of the users, not for the lints of this crate.
+    #[allow(dead_code, clippy::all)]
+    mod helpers {
+        include!("serde_helpers/header.rs");
+        include!("serde_helpers/from_upnp_string.rs");
+        include!("serde_helpers/from_upnp_bool.rs");
+        include!("serde_helpers/from_upnp_u32.rs");
+        include!("serde_helpers/from_upnp_i32.rs");
+        include!("serde_helpers/from_upnp_chrono_date_time.rs");
+        include!("serde_helpers/from_upnp_time_date_time.rs");
+        include!("serde_helpers/from_upnp_uuid.rs");
+        include!("serde_helpers/upnp.rs");
+        include!("serde_helpers/upnp_option.rs");
+        include!("serde_helpers/empty_as_none.rs");
+        include!("serde_helpers/decode_base64.rs");
+        include!("serde_helpers/decode_hex.rs");
+        include!("serde_helpers/encode_base64.rs");
+        include!("serde_helpers/encode_hex.rs");
+        include!("serde_helpers/base64.rs");
+        include!("serde_helpers/base64_option.rs");
+        include!("serde_helpers/base64_empty_as_none.rs");
+        include!("serde_helpers/hex.rs");
+        include!("serde_helpers/hex_option.rs");
+        include!("serde_helpers/hex_empty_as_none.rs");
+        include!("serde_helpers/escape_xml.rs");
+    }
+
+    use super::deserializer;
+    use crate::api_handling::options::FieldMode;
+    use helpers::*;
+
+    #[test]
+    fn from_upnp() {
+        for (text, value) in [
+            ("1", true),
+            ("0", false),
+            ("true", true),
+            ("False", false),
+            (" yes ", true),
+            ("no", false),
+        ] {
+            assert_eq!(bool::from_upnp(text), Ok(value), "{:?}", text);
+        }
+        assert!(bool::from_upnp("2").is_err());
+        assert!(bool::from_upnp("").is_err());
+
+        assert_eq!(u32::from_upnp(" 4294967295\n"), Ok(u32::MAX));
+        assert!(u32::from_upnp("-1").is_err());
+        assert!(u32::from_upnp("4294967296").is_err());
+        assert!(u32::from_upnp("").is_err());
+        assert_eq!(i32::from_upnp("-2147483648"), Ok(i32::MIN));
+        assert_eq!(i32::from_upnp(" 42 "), Ok(42));
+        assert!(i32::from_upnp("4.2").is_err());
+        assert_eq!(String::from_upnp(" a b "), Ok(" a b ".to_string()));
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Response {
+        #[serde(rename = "NewEnable", deserialize_with = "upnp")]
+        enable: bool,
+        #[serde(rename = "NewCount", default, deserialize_with = "upnp_option")]
+        count: Option<u32>,
+    }
+
+    #[test]
+    fn upnp_deserializers() {
+        let response: Response = serde_xml_rs::from_str(
+            "<Response><NewEnable>1</NewEnable><NewCount> 7 </NewCount></Response>",
+        )
+        .unwrap();
+        assert!(response.enable);
+        assert_eq!(response.count, Some(7));
+
+        let response: Response =
+            serde_xml_rs::from_str("<Response><NewEnable>no</NewEnable></Response>").unwrap();
+        assert!(!response.enable);
+        assert_eq!(response.count, None);
+
+        for xml in [
+            "<Response><NewEnable>maybe</NewEnable></Response>",
+            "<Response><NewEnable>1</NewEnable><NewCount>-7</NewCount></Response>",
+            "<Response><NewCount>7</NewCount></Response>",
+        ] {
+            assert!(serde_xml_rs::from_str::<Response>(xml).is_err(), "{}", xml);
+        }
+    }
+
+    /// Year, month, day, hour, minute and second.
+    type Fields = (i32, u8, u8, u8, u8, u8);
+
+    const DATE_TIMES: [(&str, Fields); 5] = [
+        ("2024-05-01T12:30:45", (2024, 5, 1, 12, 30, 45)),
+        (" 2024-05-01T12:30:45.250 ", (2024, 5, 1, 12, 30, 45)),
+        ("2024-05-01T12:30:45Z", (2024, 5, 1, 12, 30, 45)),
+        ("2024-05-01T12:30:45+02:00", (2024, 5, 1, 12, 30, 45)),
+        ("2024-05-01", (2024, 5, 1, 0, 0, 0)),
+    ];
+
+    const INVALID_DATE_TIMES: [&str; 5] = [
+        "",
+        "yesterday",
+        "12:30:45",
+        "2024-13-01T12:30:45",
+        "2024-05-01T25:30:45",
+    ];
+
+    #[test]
+    fn chrono_date_time() {
+        use chrono::{Datelike, Timelike};
+        for (text, expected) in DATE_TIMES {
+            let value = chrono::NaiveDateTime::from_upnp(text).unwrap();
+            let actual = (
+                value.year(),
+                value.month() as u8,
+                value.day() as u8,
+                value.hour() as u8,
+                value.minute() as u8,
+                value.second() as u8,
+            );
+            assert_eq!(actual, expected, "{:?}", text);
+        }
+        for text in INVALID_DATE_TIMES {
+            assert!(
+                chrono::NaiveDateTime::from_upnp(text).is_err(),
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn time_date_time() {
+        for (text, expected) in DATE_TIMES {
+            let value = time::PrimitiveDateTime::from_upnp(text).unwrap();
+            let actual = (
+                value.year(),
+                u8::from(value.month()),
+                value.day(),
+                value.hour(),
+                value.minute(),
+                value.second(),
+            );
+            assert_eq!(actual, expected, "{:?}", text);
+        }
+        for text in INVALID_DATE_TIMES {
+            assert!(
+                time::PrimitiveDateTime::from_upnp(text).is_err(),
+                "{:?}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn uuid() {
+        let expected = uuid::Uuid::from_u128(0x75802409_bccb_40e7_8e6c_00040e123456);
+        for text in [
+            "75802409-bccb-40e7-8e6c-00040e123456",
+            "uuid:75802409-bccb-40e7-8e6c-00040e123456",
+            " 75802409BCCB40E78E6C00040E123456 ",
+        ] {
+            assert_eq!(uuid::Uuid::from_upnp(text), Ok(expected), "{:?}", text);
+        }
+        for text in [
+            "",
+            "uuid:",
+            "75802409-bccb-40e7-8e6c",
+            "uuid:uuid:75802409-bccb-40e7-8e6c-00040e123456",
+        ] {
+            assert!(uuid::Uuid::from_upnp(text).is_err(), "{:?}", text);
+        }
+    }
+
+    /// The fields as generated for a `ui4`, a `bin.hex` and a `bin.base64` argument in each
+    /// `FieldMode`.
+    #[derive(Debug, Deserialize)]
+    struct StrictFields {
+        #[serde(rename = "NewCount", deserialize_with = "upnp")]
+        count: u32,
+        #[serde(rename = "NewKey", deserialize_with = "hex")]
+        key: Vec<u8>,
+        #[serde(rename = "NewData", deserialize_with = "base64")]
+        data: Vec<u8>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct DefaultFields {
+        #[serde(rename = "NewCount", default, deserialize_with = "upnp")]
+        count: u32,
+        #[serde(rename = "NewKey", default, deserialize_with = "hex")]
+        key: Vec<u8>,
+        #[serde(rename = "NewData", default, deserialize_with = "base64")]
+        data: Vec<u8>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct OptionalFields {
+        #[serde(rename = "NewCount", default, deserialize_with = "upnp_option")]
+        count: Option<u32>,
+        #[serde(rename = "NewKey", default, deserialize_with = "hex_option")]
+        key: Option<Vec<u8>>,
+        #[serde(rename = "NewData", default, deserialize_with = "base64_option")]
+        data: Option<Vec<u8>>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct EmptyAsNoneFields {
+        #[serde(rename = "NewCount", default, deserialize_with = "empty_as_none")]
+        count: Option<u32>,
+        #[serde(rename = "NewKey", default, deserialize_with = "hex_empty_as_none")]
+        key: Option<Vec<u8>>,
+        #[serde(rename = "NewData", default, deserialize_with = "base64_empty_as_none")]
+        data: Option<Vec<u8>>,
+    }
+
+    const MISSING: &str = "<R></R>";
+    const EMPTY: &str = "<R><NewCount></NewCount><NewKey></NewKey><NewData></NewData></R>";
+    const PRESENT: &str =
+        "<R><NewCount>7</NewCount><NewKey>0aff</NewKey><NewData>QUI=</NewData></R>";
+
+    #[test]
+    fn field_modes() {
+        for (mode, upnp, hex, base64) in [
+            (FieldMode::Strict, "upnp", "hex", "base64"),
+            (FieldMode::Default, "upnp", "hex", "base64"),
+            (
+                FieldMode::Optional,
+                "upnp_option",
+                "hex_option",
+                "base64_option",
+            ),
+            (
+                FieldMode::EmptyAsNone,
+                "empty_as_none",
+                "hex_empty_as_none",
+                "base64_empty_as_none",
+            ),
+        ] {
+            assert_eq!(deserializer("ui4", "u32", mode).1, upnp, "{:?}", mode);
+            assert_eq!(
+                deserializer("bin.hex", "Vec<u8>", mode).1,
+                hex,
+                "{:?}",
+                mode
+            );
+            assert_eq!(
+                deserializer("bin.base64", "Vec<u8>", mode).1,
+                base64,
+                "{:?}",
+                mode
+            );
+        }
+
+        assert!(serde_xml_rs::from_str::<StrictFields>(MISSING).is_err());
+        assert!(serde_xml_rs::from_str::<StrictFields>(EMPTY).is_err());
+        let strict: StrictFields = serde_xml_rs::from_str(PRESENT).unwrap();
+        assert_eq!(
+            (strict.count, strict.key, strict.data),
+            (7, vec![10, 255], b"AB".to_vec())
+        );
+
+        let default: DefaultFields = serde_xml_rs::from_str(MISSING).unwrap();
+        assert_eq!(
+            (default.count, default.key, default.data),
+            (0, vec![], vec![])
+        );
+        assert!(serde_xml_rs::from_str::<DefaultFields>(EMPTY).is_err());
+        let default: DefaultFields = serde_xml_rs::from_str(PRESENT).unwrap();
+        assert_eq!(
+            (default.count, default.key, default.data),
+            (7, vec![10, 255], b"AB".to_vec())
+        );
+
+        let optional: OptionalFields = serde_xml_rs::from_str(MISSING).unwrap();
+        assert_eq!(
+            (optional.count, optional.key, optional.data),
+            (None, None, None)
+        );
+        assert!(serde_xml_rs::from_str::<OptionalFields>(EMPTY).is_err());
+        let optional: OptionalFields = serde_xml_rs::from_str(PRESENT).unwrap();
+        assert_eq!(
+            (optional.count, optional.key, optional.data),
+            (Some(7), Some(vec![10, 255]), Some(b"AB".to_vec()))
+        );
+
+        let empty_as_none: EmptyAsNoneFields = serde_xml_rs::from_str(MISSING).unwrap();
+        assert_eq!(
+            (empty_as_none.count, empty_as_none.key, empty_as_none.data),
+            (None, None, None)
+        );
+        let empty_as_none: EmptyAsNoneFields = serde_xml_rs::from_str(EMPTY).unwrap();
+        assert_eq!(
+            (empty_as_none.count, empty_as_none.key, empty_as_none.data),
+            (None, None, None)
+        );
+        let empty_as_none: EmptyAsNoneFields = serde_xml_rs::from_str(PRESENT).unwrap();
+        assert_eq!(
+            (empty_as_none.count, empty_as_none.key, empty_as_none.data),
+            (Some(7), Some(vec![10, 255]), Some(b"AB".to_vec()))
+        );
+
+        // An empty element is an empty value to the `Optional` helpers, unlike a missing one.
+        let optional: OptionalFields =
+            serde_xml_rs::from_str("<R><NewKey></NewKey><NewData></NewData></R>").unwrap();
+        assert_eq!((optional.key, optional.data), (Some(vec![]), Some(vec![])));
+    }
+
+    #[test]
+    fn base64_codec() {
+        for (text, bytes) in [
+            ("", &b""[..]),
+            ("QQ==", b"A"),
+            ("QUI=", b"AB"),
+            ("QUJD", b"ABC"),
+            ("QUJD\r\n RA==", b"ABCD"),
+            ("+/8=", &[0xfb, 0xff]),
+        ] {
+            assert_eq!(decode_base64(text).unwrap(), bytes, "{:?}", text);
+            assert_eq!(
+                encode_base64(bytes),
+                text.replace(|c: char| c.is_whitespace(), "")
+            );
+        }
+        for text in [
+            "A", "QQ", "QQ=", "AB=C", "QQ==QQ==", "====", "Q===", "QR==", "QUJ=", "QU*=",
+        ] {
+            assert!(decode_base64(text).is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn xml_escaping() {
+        assert_eq!(
+            escape_xml("a & <b> \"c\" 'd'"),
+            "a &amp; &lt;b&gt; &quot;c&quot; &apos;d&apos;"
+        );
+        assert_eq!(escape_xml("&amp;"), "&amp;amp;");
+        assert_eq!(escape_xml("plain"), "plain");
+    }
+
+    #[test]
+    fn hex_codec() {
+        for (text, bytes) in [("", &b""[..]), ("00ff", &[0, 255]), ("0A b1", &[10, 177])] {
+            assert_eq!(decode_hex(text).unwrap(), bytes, "{:?}", text);
+        }
+        assert_eq!(encode_hex(&[0, 10, 255]), "000aff");
+        for text in ["0", "abc", "0g", "+1"] {
+            assert!(decode_hex(text).is_err(), "{:?}", text);
+        }
+    }
+}
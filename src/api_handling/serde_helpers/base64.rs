@@ -0,0 +1,9 @@
+
+/// Deserializes a `bin.base64` element into its bytes.
+pub fn base64<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    decode_base64(&value).map_err(serde::de::Error::custom)
+}
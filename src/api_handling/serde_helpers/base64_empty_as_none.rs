@@ -0,0 +1,12 @@
+
+/// Deserializes empty `bin.base64` elements as `None`, everything else into `Some` of its bytes.
+pub fn base64_empty_as_none<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    decode_base64(&value).map(Some).map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,8 @@
+
+/// Deserializes a present `bin.base64` element into `Some` of its bytes.
+pub fn base64_option<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    base64(deserializer).map(Some)
+}
@@ -0,0 +1,19 @@
+
+/// Decodes the text of a `bin.hex` element.
+pub fn decode_hex(value: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<char> = value.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.len() % 2 != 0 {
+        return Err(format!("invalid UPnP bin.hex `{}`: odd number of digits", value));
+    }
+    if let Some(c) = digits.iter().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(format!("invalid UPnP bin.hex character `{}`", c));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let byte: String = pair.iter().collect();
+            u8::from_str_radix(&byte, 16)
+                .map_err(|e| format!("invalid UPnP bin.hex `{}`: {}", value, e))
+        })
+        .collect()
+}
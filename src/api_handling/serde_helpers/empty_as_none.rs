@@ -0,0 +1,13 @@
+
+/// Deserializes empty (or whitespace only) elements as `None`, everything else with `FromUpnp`.
+pub fn empty_as_none<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromUpnp,
+{
+    let value = String::deserialize(deserializer)?;
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    T::from_upnp(&value).map(Some).map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,20 @@
+
+/// Encodes bytes as the text of a `bin.base64` element.
+pub fn encode_base64(value: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut result = String::new();
+    for chunk in value.chunks(3) {
+        let buffer = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |buffer, (i, byte)| buffer | u32::from(*byte) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                result.push(char::from(ALPHABET[(buffer >> (18 - 6 * i) & 63) as usize]));
+            } else {
+                result.push('=');
+            }
+        }
+    }
+    result
+}
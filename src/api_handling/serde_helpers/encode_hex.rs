@@ -0,0 +1,5 @@
+
+/// Encodes bytes as the text of a `bin.hex` element.
+pub fn encode_hex(value: &[u8]) -> String {
+    value.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,10 @@
+
+impl FromUpnp for bool {
+    fn from_upnp(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "1" | "true" | "yes" => Ok(true),
+            "0" | "false" | "no" => Ok(false),
+            other => Err(format!("invalid UPnP boolean `{}`", other)),
+        }
+    }
+}
@@ -0,0 +1,15 @@
+
+impl FromUpnp for chrono::NaiveDateTime {
+    fn from_upnp(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if let Ok(date_time) = chrono::DateTime::parse_from_rfc3339(value) {
+            return Ok(date_time.naive_local());
+        }
+        chrono::NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+            .or_else(|_| {
+                chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                    .map(|date| date.and_hms_opt(0, 0, 0).expect("midnight is a valid time"))
+            })
+            .map_err(|e| format!("invalid UPnP dateTime `{}`: {}", value, e))
+    }
+}
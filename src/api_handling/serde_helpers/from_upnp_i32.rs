@@ -0,0 +1,9 @@
+
+impl FromUpnp for i32 {
+    fn from_upnp(value: &str) -> Result<Self, String> {
+        value
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid UPnP integer `{}`: {}", value, e))
+    }
+}
@@ -0,0 +1,6 @@
+
+impl FromUpnp for String {
+    fn from_upnp(value: &str) -> Result<Self, String> {
+        Ok(value.to_string())
+    }
+}
@@ -0,0 +1,12 @@
+
+impl FromUpnp for time::PrimitiveDateTime {
+    fn from_upnp(value: &str) -> Result<Self, String> {
+        use time::format_description::well_known::Iso8601;
+        let value = value.trim();
+        time::OffsetDateTime::parse(value, &Iso8601::DEFAULT)
+            .map(|date_time| time::PrimitiveDateTime::new(date_time.date(), date_time.time()))
+            .or_else(|_| time::PrimitiveDateTime::parse(value, &Iso8601::DEFAULT))
+            .or_else(|_| time::Date::parse(value, &Iso8601::DEFAULT).map(|date| date.midnight()))
+            .map_err(|e| format!("invalid UPnP dateTime `{}`: {}", value, e))
+    }
+}
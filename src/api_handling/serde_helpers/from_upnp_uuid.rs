@@ -0,0 +1,8 @@
+
+impl FromUpnp for uuid::Uuid {
+    fn from_upnp(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        uuid::Uuid::parse_str(value.strip_prefix("uuid:").unwrap_or(value))
+            .map_err(|e| format!("invalid UPnP uuid `{}`: {}", value, e))
+    }
+}
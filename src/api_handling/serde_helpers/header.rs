@@ -0,0 +1,6 @@
+use serde::{Deserialize, Deserializer};
+
+/// Parses the text of an UPnP element into the Rust type of a response field.
+pub trait FromUpnp: Sized {
+    fn from_upnp(value: &str) -> Result<Self, String>;
+}
@@ -0,0 +1,9 @@
+
+/// Deserializes a `bin.hex` element into its bytes.
+pub fn hex<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    decode_hex(&value).map_err(serde::de::Error::custom)
+}
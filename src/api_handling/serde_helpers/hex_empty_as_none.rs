@@ -0,0 +1,12 @@
+
+/// Deserializes empty `bin.hex` elements as `None`, everything else into `Some` of its bytes.
+pub fn hex_empty_as_none<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    decode_hex(&value).map(Some).map_err(serde::de::Error::custom)
+}
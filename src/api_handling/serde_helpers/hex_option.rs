@@ -0,0 +1,8 @@
+
+/// Deserializes a present `bin.hex` element into `Some` of its bytes.
+pub fn hex_option<'de, D>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    hex(deserializer).map(Some)
+}
@@ -0,0 +1,10 @@
+
+/// Deserializes an UPnP element with `FromUpnp`.
+pub fn upnp<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromUpnp,
+{
+    let value = String::deserialize(deserializer)?;
+    T::from_upnp(&value).map_err(serde::de::Error::custom)
+}
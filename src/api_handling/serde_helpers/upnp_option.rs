@@ -0,0 +1,9 @@
+
+/// Deserializes a present UPnP element with `FromUpnp` into `Some`.
+pub fn upnp_option<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromUpnp,
+{
+    upnp(deserializer).map(Some)
+}
@@ -5,10 +5,50 @@ extern crate serde_xml_rs;
 
 mod api_handling;
 
-use crate::api_handling::api_desc_dir::ApiDescDir;
+use crate::api_handling::description_set::DescriptionSet;
+use crate::api_handling::diff::ApiDiff;
+use crate::api_handling::discovery::{choose_box, Discovery};
+use crate::api_handling::examples::write_examples;
+use crate::api_handling::fetch::{CertificateCheck, Credentials, FetchOptions, Fetcher};
+use crate::api_handling::filter::{ActionKinds, Filter, ReadOnlyAllowlist};
+use crate::api_handling::merge::{merge, Availability};
+use crate::api_handling::mock::{MockScript, MockServer};
+use crate::api_handling::naming::NameOverrides;
+use crate::api_handling::options::{GeneratorOptions, Profile};
+use std::path::Path;
+use std::time::Duration;
 
 /// If you can't reach your target or wish to specify it via IP, this is the place.
 const ADDRESS: &str = "http://fritz.box:49000";
+/// Set to `true` to find the FRITZ!Box with an SSDP search instead of using `ADDRESS`.
+/// If several boxes answer, you are asked to pick one.
+const DISCOVER: bool = false;
+/// Set to `true` to fetch all descriptions over HTTPS on `HTTPS_PORT` instead of plain HTTP.
+/// Requires `CERTIFICATE_FINGERPRINT` or `CA_FILE` to check the certificate of the box.
+const FETCH_OVER_HTTPS: bool = false;
+/// Specify the HTTPS port, the TR-064 security port of the FRITZ!Box is `49443`.
+const HTTPS_PORT: u16 = 49443;
+/// Specify the SHA-256 fingerprint of the box certificate, e.g. `3A:7F:...`, to accept it even though it is self-signed.
+const CERTIFICATE_FINGERPRINT: &str = "";
+/// Specify a PEM file with the CA which issued the box certificate, used if there is no `CERTIFICATE_FINGERPRINT`.
+const CA_FILE: &str = "";
+/// Specify how long connecting and each request may take, in seconds.
+const FETCH_TIMEOUT_SECONDS: u64 = 10;
+/// Specify how often a request is repeated after a connection error, a timeout or a `503`, waiting
+/// `RETRY_DELAY_MILLISECONDS` before the first retry and twice as long before every further one.
+const FETCH_RETRIES: u32 = 3;
+const RETRY_DELAY_MILLISECONDS: u64 = 500;
+/// Specify how many service descriptions are fetched at the same time.
+const CONCURRENT_FETCHES: usize = 4;
+/// Specify a proxy for all requests, e.g. `http://proxy:3128`, or leave empty to connect directly.
+const PROXY: &str = "";
+/// Specify the folder to cache service descriptions in between runs, e.g. `cache`, or leave empty
+/// to always fetch them. They are kept per box and firmware version, see `DescriptionCache`.
+const CACHE_FOLDER: &str = "";
+/// Specify the file to read credentials from if neither the `--username`/`--password` flags nor
+/// the `FRITZ_USERNAME`/`FRITZ_PASSWORD` environment variables are set, see `Credentials::parse`.
+/// Ignored if missing.
+const CREDENTIALS_FILE: &str = "credentials.txt";
 /// Specify the requests output folder.
 const REQUESTS_OUTPUT_FOLDER: &str = "requests";
 /// Specify the responses output folder.
@@ -17,24 +57,338 @@ const RESPONSES_OUTPUT_FOLDER: &str = "responses";
 const TR064_PREFIX: &str = "tr064";
 /// Specify the IGD folder and files prefix
 const IGD_PREFIX: &str = "igd";
+/// Specify the file to pin names of generated items in, see `NameOverrides`. Ignored if missing.
+const NAME_OVERRIDES_FILE: &str = "name_overrides.txt";
+/// Specify how response struct fields handle missing or empty elements:
+/// `strict`, `default`, `optional` or `empty-as-none`, see `FieldMode`.
+const RESPONSE_FIELD_MODE: &str = "strict";
+/// Specify the type of `dateTime` values: `string`, `chrono` (`chrono::NaiveDateTime`) or
+/// `time` (`time::PrimitiveDateTime`, requires the `parsing` feature).
+const DATE_TIME_MAPPING: &str = "string";
+/// Specify the type of `uuid` values: `string` or `uuid` (`uuid::Uuid`).
+const UUID_MAPPING: &str = "string";
+/// Set to `true` to add a `discovery` module to the request files, which finds devices offering
+/// the generated services with SSDP.
+const GENERATE_DISCOVERY: bool = false;
+/// Set to `true` to add a `capabilities` module to the request files, which reads the descriptions
+/// of the connected device to check whether it offers an action before calling it.
+const GENERATE_CAPABILITIES: bool = false;
+/// Set to `true` to add a `device_metadata` module to the request files, with the model, firmware
+/// version, `UDN` and icons of the box as constants.
+const GENERATE_DEVICE_METADATA: bool = false;
+/// Set to `true` to add an `eventing` module to the request files, with GENA subscription requests
+/// and a parser for notifications, and an `Event` struct to every service with evented variables.
+const GENERATE_EVENTING: bool = false;
+/// Set to `true` to add a `tests` module to every request file, which checks that the requests are
+/// well-formed and the response structs deserialize an example response of every action.
+const GENERATE_TESTS: bool = false;
+/// Set to `true` to download the icons listed in the descriptions into `output/<prefix>_icons`.
+const DOWNLOAD_ICONS: bool = false;
+/// Set to `true` to write the files of the services into nested modules mirroring the devices of
+/// the description, e.g. `tr064_internet_gateway_device::lan_device::hosts`, with the type, `UDN`
+/// and model of every device as constants.
+const NESTED_MODULES: bool = false;
+/// Specify the services to generate as patterns matched against the service type and the service
+/// id, in which `*` stands for any text, e.g. `*:service:Hosts:1`. All services if empty.
+const INCLUDE_SERVICES: &[&str] = &[];
+/// Specify services not to generate, like `INCLUDE_SERVICES`.
+const EXCLUDE_SERVICES: &[&str] = &[];
+/// Specify the actions to generate as patterns matched against the action name, e.g. `Get*`.
+/// All actions if empty.
+const INCLUDE_ACTIONS: &[&str] = &[];
+/// Specify actions not to generate, like `INCLUDE_ACTIONS`.
+const EXCLUDE_ACTIONS: &[&str] = &[];
+/// Specify the actions to generate by their effect: `all`, `read-only` or `mutating`, see `is_read_only`.
+const ACTION_KINDS: &str = "all";
+/// Specify `read-only` to only ever generate actions which read from the device, e.g. for
+/// monitoring tools, or `full`. Unlike `ACTION_KINDS`, generation fails rather than writing an
+/// action which changes the device.
+const PROFILE: &str = "full";
+/// Specify the file listing actions the `read-only` profile generates although they look like they
+/// change the device, see `ReadOnlyAllowlist`. Ignored if missing.
+const READ_ONLY_ALLOWLIST_FILE: &str = "read_only_allowlist.txt";
+
+/// Specify the address the `mock` command listens on.
+const MOCK_ADDRESS: &str = "127.0.0.1:49000";
+/// Specify the file with the answers of the `mock` command, see `MockScript`. Ignored if missing.
+const MOCK_SCRIPT_FILE: &str = "mock_script.txt";
+
+/// Without a command the files are generated.
+const USAGE: &str = "usage: fritz_box_tr064_igd_api_files_generator [<flags>] [<command>]
+commands:
+  snapshot <folder>                   save the descriptions of the box into <folder>
+  diff [--json] <old> <new>           compare two description sets, each either the URL of a
+                                      root description or a root description file in a snapshot
+  merge <prefix> <source>...          generate one API for several models from their description
+                                      sets, given like for diff, into files named after <prefix>
+  mock <source>...                    serve description sets, given like for diff, and answer
+                                      SOAP calls like a box, to test without hardware
+  examples <folder> <source>...       write an example request and response of every action of
+                                      description sets, given like for diff, into <folder>
+flags:
+  --username <name>, --password <password>, --credentials-file <file>";
+
+/// Command line flags and arguments. Prefer the environment or a credentials file over `--password`,
+/// the arguments of a process are visible to other users of the system.
+struct Flags {
+    username: Option<String>,
+    password: Option<String>,
+    credentials_file: Option<String>,
+    /// Print the diff as JSON instead of text.
+    json: bool,
+    /// The command and its arguments.
+    arguments: Vec<String>,
+}
+
+/// Parses `--name value` and `--name=value` flags, everything else is an argument.
+fn parse_flags(mut args: impl Iterator<Item = String>) -> Result<Flags, String> {
+    let mut flags = Flags {
+        username: None,
+        password: None,
+        credentials_file: None,
+        json: false,
+        arguments: vec![],
+    };
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            flags.arguments.push(arg);
+            continue;
+        }
+        if arg == "--json" {
+            flags.json = true;
+            continue;
+        }
+        let (name, value) = match arg.find('=') {
+            Some(position) => (
+                arg[..position].to_string(),
+                Some(arg[position + 1..].to_string()),
+            ),
+            None => (arg, None),
+        };
+        let flag = match name.as_str() {
+            "--username" => &mut flags.username,
+            "--password" => &mut flags.password,
+            "--credentials-file" => &mut flags.credentials_file,
+            _ => return Err(format!("unknown flag `{}`", name)),
+        };
+        let value = match value {
+            Some(value) => value,
+            None => args
+                .next()
+                .ok_or_else(|| format!("`{}` requires a value", name))?,
+        };
+        *flag = Some(value);
+    }
+    Ok(flags)
+}
+
+/// Returns the credentials from the flags, the environment or the credentials file, in this order.
+fn credentials(flags: &Flags) -> Result<Option<Credentials>, String> {
+    match (flags.username.clone(), flags.password.clone()) {
+        (username, Some(password)) => Ok(Some(Credentials {
+            username: username.unwrap_or_default(),
+            password,
+        })),
+        (Some(_), None) => Err("`--username` requires `--password`".to_string()),
+        (None, None) => {
+            if let Some(credentials) = Credentials::from_env() {
+                return Ok(Some(credentials));
+            }
+            match &flags.credentials_file {
+                Some(path) => match Credentials::from_file(path)? {
+                    Some(credentials) => Ok(Some(credentials)),
+                    None => Err(format!("{}: file not found", path)),
+                },
+                None => Credentials::from_file(CREDENTIALS_FILE),
+            }
+        }
+    }
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let resp = reqwest::blocking::get(format!("{}/tr64desc.xml", ADDRESS).as_str())?.text()?;
-    let tr64desc: ApiDescDir = serde_xml_rs::from_str(&*resp)?;
-    tr64desc.generate_files(
-        ADDRESS,
-        RESPONSES_OUTPUT_FOLDER.to_string(),
-        REQUESTS_OUTPUT_FOLDER.to_string(),
-        Some(TR064_PREFIX.to_string()),
-    );
-    let resp = reqwest::blocking::get(format!("{}/igddesc.xml", ADDRESS).as_str())?.text()?;
-    let igddesc: ApiDescDir = serde_xml_rs::from_str(&*resp)?;
-    igddesc.generate_files(
-        ADDRESS,
-        RESPONSES_OUTPUT_FOLDER.to_string(),
-        REQUESTS_OUTPUT_FOLDER.to_string(),
-        Some(IGD_PREFIX.to_string()),
-    );
+    let flags = parse_flags(std::env::args().skip(1))?;
+    let mut options = GeneratorOptions::new();
+    options.name_overrides = NameOverrides::from_file(NAME_OVERRIDES_FILE)?;
+    options.field_mode = RESPONSE_FIELD_MODE.parse()?;
+    options.date_time_mapping = DATE_TIME_MAPPING.parse()?;
+    options.uuid_mapping = UUID_MAPPING.parse()?;
+    options.generate_discovery = GENERATE_DISCOVERY;
+    options.generate_capabilities = GENERATE_CAPABILITIES;
+    options.generate_device_metadata = GENERATE_DEVICE_METADATA;
+    options.generate_eventing = GENERATE_EVENTING;
+    options.generate_tests = GENERATE_TESTS;
+    options.nest_modules = NESTED_MODULES;
+    let to_strings =
+        |patterns: &[&str]| patterns.iter().map(|pattern| pattern.to_string()).collect();
+    options.filter.include_services = to_strings(INCLUDE_SERVICES);
+    options.filter.exclude_services = to_strings(EXCLUDE_SERVICES);
+    options.filter.include_actions = to_strings(INCLUDE_ACTIONS);
+    options.filter.exclude_actions = to_strings(EXCLUDE_ACTIONS);
+    options.filter.action_kinds = ACTION_KINDS.parse()?;
+    options.profile = PROFILE.parse()?;
+    if options.profile == Profile::ReadOnly {
+        if options.filter.action_kinds == ActionKinds::Mutating {
+            return Err("the read-only profile can't generate mutating actions".into());
+        }
+        options.filter.action_kinds = ActionKinds::ReadOnly;
+        options.filter.allowlist = ReadOnlyAllowlist::from_file(READ_ONLY_ALLOWLIST_FILE)?;
+    }
+    let mut fetch_options = FetchOptions::new();
+    if FETCH_OVER_HTTPS {
+        fetch_options.https_port = Some(HTTPS_PORT);
+        if !CERTIFICATE_FINGERPRINT.is_empty() {
+            fetch_options.certificate_check =
+                Some(CertificateCheck::fingerprint(CERTIFICATE_FINGERPRINT)?);
+        } else if !CA_FILE.is_empty() {
+            fetch_options.certificate_check = Some(CertificateCheck::CaFile(CA_FILE.to_string()));
+        }
+    }
+    fetch_options.timeout = Duration::from_secs(FETCH_TIMEOUT_SECONDS);
+    fetch_options.retries = FETCH_RETRIES;
+    fetch_options.retry_delay = Duration::from_millis(RETRY_DELAY_MILLISECONDS);
+    fetch_options.concurrency = CONCURRENT_FETCHES;
+    if !PROXY.is_empty() {
+        fetch_options.proxy = Some(PROXY.to_string());
+    }
+    if !CACHE_FOLDER.is_empty() {
+        fetch_options.cache_folder = Some(CACHE_FOLDER.to_string());
+    }
+    fetch_options.credentials = credentials(&flags)?;
+    let fetcher = Fetcher::new(&fetch_options)?;
+
+    let arguments: Vec<&str> = flags.arguments.iter().map(String::as_str).collect();
+    match arguments.as_slice() {
+        [] => generate(&fetcher, &options),
+        ["snapshot", folder] => snapshot(&fetcher, folder),
+        ["diff", old, new] => {
+            let old = DescriptionSet::open(&fetcher, old, &Filter::new())?;
+            let new = DescriptionSet::open(&fetcher, new, &Filter::new())?;
+            let diff = ApiDiff::new(&old, &new, &options)?;
+            if flags.json {
+                println!("{}", diff.to_json());
+            } else {
+                println!("{}", diff);
+            }
+            Ok(())
+        }
+        ["merge", prefix, sources @ ..] if !sources.is_empty() => {
+            let mut sets = vec![];
+            for source in sources {
+                sets.push(DescriptionSet::open(&fetcher, source, &options.filter)?);
+            }
+            let (merged, availability) = merge(sets)?;
+            merged.description.generate_files(
+                &merged.services,
+                &availability,
+                RESPONSES_OUTPUT_FOLDER.to_string(),
+                REQUESTS_OUTPUT_FOLDER.to_string(),
+                Some(prefix.to_string()),
+                &options,
+            )
+        }
+        ["mock", sources @ ..] if !sources.is_empty() => {
+            let mut sets = vec![];
+            for source in sources {
+                sets.push(DescriptionSet::open(&fetcher, source, &Filter::new())?);
+            }
+            let server = MockServer::new(sets, MockScript::from_file(MOCK_SCRIPT_FILE)?)?;
+            let listener = std::net::TcpListener::bind(MOCK_ADDRESS)?;
+            println!("mock listening on http://{}", MOCK_ADDRESS);
+            Ok(server.serve(listener)?)
+        }
+        ["examples", folder, sources @ ..] if !sources.is_empty() => {
+            for source in sources {
+                let set = DescriptionSet::open(&fetcher, source, &options.filter)?;
+                // one folder per root description, e.g. `tr64desc`
+                let stem = Path::new(source).file_stem().unwrap_or_default();
+                let set_folder = Path::new(folder).join(stem);
+                let written = write_examples(&set, &set_folder.to_string_lossy())?;
+                println!(
+                    "wrote {} examples of {} into {}",
+                    written,
+                    source,
+                    set_folder.display()
+                );
+            }
+            Ok(())
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Returns the locations of the TR-064 and the IGD root description, found with SSDP if `DISCOVER` is set.
+fn locations() -> Result<(Option<String>, Option<String>), Box<dyn std::error::Error>> {
+    if DISCOVER {
+        let boxes = Discovery::new().search()?;
+        let found = choose_box(boxes, std::io::stdin().lock(), std::io::stdout())?;
+        Ok((found.tr64_location, found.igd_location))
+    } else {
+        Ok((
+            Some(format!("{}/tr64desc.xml", ADDRESS)),
+            Some(format!("{}/igddesc.xml", ADDRESS)),
+        ))
+    }
+}
+
+fn generate(
+    fetcher: &Fetcher,
+    options: &GeneratorOptions,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (tr64_location, igd_location) = locations()?;
+    if let Some(location) = tr64_location {
+        let tr64 = DescriptionSet::fetch(fetcher, &location, &options.filter)?;
+        tr64.description.generate_files(
+            &tr64.services,
+            &Availability::new(),
+            RESPONSES_OUTPUT_FOLDER.to_string(),
+            REQUESTS_OUTPUT_FOLDER.to_string(),
+            Some(TR064_PREFIX.to_string()),
+            options,
+        )?;
+        if DOWNLOAD_ICONS {
+            download_icons(fetcher, &tr64, &location, TR064_PREFIX)?;
+        }
+    }
+    if let Some(location) = igd_location {
+        let igd = DescriptionSet::fetch(fetcher, &location, &options.filter)?;
+        igd.description.generate_files(
+            &igd.services,
+            &Availability::new(),
+            RESPONSES_OUTPUT_FOLDER.to_string(),
+            REQUESTS_OUTPUT_FOLDER.to_string(),
+            Some(IGD_PREFIX.to_string()),
+            options,
+        )?;
+        if DOWNLOAD_ICONS {
+            download_icons(fetcher, &igd, &location, IGD_PREFIX)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Downloads the icons of `set`, fetched from `location`, into the icons folder of `prefix`.
+fn download_icons(
+    fetcher: &Fetcher,
+    set: &DescriptionSet,
+    location: &str,
+    prefix: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let folder = format!("output/{}_icons", prefix);
+    let count = set.description.download_icons(fetcher, location, &folder)?;
+    println!("saved {} icon(s) of {} in {}", count, location, folder);
+    Ok(())
+}
 
+/// Saves the TR-064 and the IGD description sets into `folder`, to compare them with `diff` later on.
+fn snapshot(fetcher: &Fetcher, folder: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let (tr64_location, igd_location) = locations()?;
+    for location in tr64_location.iter().chain(igd_location.iter()) {
+        let path = DescriptionSet::fetch(fetcher, location, &Filter::new())?.save(folder)?;
+        println!("saved {} as {}", location, path);
+    }
     Ok(())
 }